@@ -2,5 +2,13 @@
 
 pub mod prelude;
 
+pub mod api_tokens;
+pub mod library_entries;
+pub mod mal_auth;
+pub mod mal_oauth_states;
 pub mod series;
 pub mod series_sources;
+pub mod sessions;
+pub mod skip_markers;
+pub mod user_settings;
+pub mod users;
@@ -1,4 +1,12 @@
 //! `SeaORM` Entity. Generated by sea-orm-codegen 0.12.3
 
+pub use super::api_tokens::Entity as ApiTokens;
+pub use super::library_entries::Entity as LibraryEntries;
+pub use super::mal_auth::Entity as MalAuth;
+pub use super::mal_oauth_states::Entity as MalOauthStates;
 pub use super::series::Entity as Series;
 pub use super::series_sources::Entity as SeriesSources;
+pub use super::sessions::Entity as Sessions;
+pub use super::skip_markers::Entity as SkipMarkers;
+pub use super::user_settings::Entity as UserSettings;
+pub use super::users::Entity as Users;
@@ -2,10 +2,12 @@
 
 use sea_orm::{entity::prelude::*, ActiveValue};
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
-#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize, ToSchema)]
 #[sea_orm(table_name = "series")]
 #[serde(rename_all = "camelCase")]
+#[schema(as = Series)]
 pub struct Model {
     #[sea_orm(primary_key)]
     pub id: i32,
@@ -14,12 +16,26 @@ pub struct Model {
     pub created_at: Option<String>,
     pub updated_at: Option<String>,
     pub mal_id: Option<i32>,
+    pub alt_names: Option<String>,
+    pub genres: Option<String>,
+    #[schema(value_type = Option<SeriesProvenance>)]
+    pub provenance: Option<Json>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(has_many = "super::library_entries::Entity")]
+    LibraryEntries,
     #[sea_orm(has_many = "super::series_sources::Entity")]
     SeriesSources,
+    #[sea_orm(has_many = "super::skip_markers::Entity")]
+    SkipMarkers,
+}
+
+impl Related<super::library_entries::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::LibraryEntries.def()
+    }
 }
 
 impl Related<super::series_sources::Entity> for Entity {
@@ -28,6 +44,12 @@ impl Related<super::series_sources::Entity> for Entity {
     }
 }
 
+impl Related<super::skip_markers::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::SkipMarkers.def()
+    }
+}
+
 #[async_trait::async_trait]
 impl ActiveModelBehavior for ActiveModel {
     async fn before_save<C>(self, _db: &C, insert: bool) -> Result<Self, DbErr>
@@ -2,10 +2,12 @@
 
 use sea_orm::{entity::prelude::*, ActiveValue};
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
-#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize, ToSchema)]
 #[sea_orm(table_name = "series_sources")]
 #[serde(rename_all = "camelCase")]
+#[schema(as = SeriesSource)]
 pub struct Model {
     #[sea_orm(primary_key)]
     pub id: i32,
@@ -14,6 +16,18 @@ pub struct Model {
     pub series_site_id: String,
     pub created_at: Option<String>,
     pub updated_at: Option<String>,
+    pub verified_name: Option<String>,
+    pub verified_mal_id: Option<i32>,
+    pub verified_at: Option<String>,
+    pub last_success_at: Option<String>,
+    pub last_failure_at: Option<String>,
+    pub consecutive_failures: i32,
+    pub last_error_kind: Option<String>,
+    pub last_error: Option<String>,
+    pub broken: bool,
+    #[schema(value_type = Option<EpisodeMapping>)]
+    pub episode_mapping: Option<Json>,
+    pub next_release_at: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -2,6 +2,18 @@ pub use sea_orm_migration::prelude::*;
 
 mod m20220101_000001_create_table;
 mod m20231011_082054_add_mal_id_to_series;
+mod m20231020_174212_add_list_fields_to_series;
+mod m20231022_193518_add_mal_sync;
+mod m20231024_151203_add_users;
+mod m20231025_201044_add_api_token_scopes;
+mod m20231026_140312_add_series_search;
+mod m20231027_093418_add_series_provenance;
+mod m20231027_181542_add_source_verification;
+mod m20231028_102207_add_source_health;
+mod m20231029_120437_add_source_episode_mapping;
+mod m20231029_183951_add_skip_fillers;
+mod m20231030_091522_add_skip_markers;
+mod m20231031_104512_add_source_next_release;
 
 pub struct Migrator;
 
@@ -11,6 +23,18 @@ impl MigratorTrait for Migrator {
         vec![
             Box::new(m20220101_000001_create_table::Migration),
             Box::new(m20231011_082054_add_mal_id_to_series::Migration),
+            Box::new(m20231020_174212_add_list_fields_to_series::Migration),
+            Box::new(m20231022_193518_add_mal_sync::Migration),
+            Box::new(m20231024_151203_add_users::Migration),
+            Box::new(m20231025_201044_add_api_token_scopes::Migration),
+            Box::new(m20231026_140312_add_series_search::Migration),
+            Box::new(m20231027_093418_add_series_provenance::Migration),
+            Box::new(m20231027_181542_add_source_verification::Migration),
+            Box::new(m20231028_102207_add_source_health::Migration),
+            Box::new(m20231029_120437_add_source_episode_mapping::Migration),
+            Box::new(m20231029_183951_add_skip_fillers::Migration),
+            Box::new(m20231030_091522_add_skip_markers::Migration),
+            Box::new(m20231031_104512_add_source_next_release::Migration),
         ]
     }
 }
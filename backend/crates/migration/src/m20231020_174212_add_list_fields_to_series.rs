@@ -0,0 +1,79 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // SQLite only supports one change per `ALTER TABLE` statement
+        for mut column in [
+            ColumnDef::new(Series::ListStatus).string().to_owned(),
+            ColumnDef::new(Series::Score).integer().to_owned(),
+            ColumnDef::new(Series::WatchedEpisodes).integer().to_owned(),
+            ColumnDef::new(Series::StartedWatchingAt).date().to_owned(),
+            ColumnDef::new(Series::FinishedWatchingAt).date().to_owned(),
+        ] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Series::Table)
+                        .add_column(&mut column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        manager
+            .create_index(
+                Index::create()
+                    .if_not_exists()
+                    .name("idx__series__mal_id")
+                    .table(Series::Table)
+                    .col(Series::MalId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx__series__mal_id")
+                    .table(Series::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        for column in [
+            Series::ListStatus,
+            Series::Score,
+            Series::WatchedEpisodes,
+            Series::StartedWatchingAt,
+            Series::FinishedWatchingAt,
+        ] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Series::Table)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum Series {
+    Table,
+    MalId,
+    ListStatus,
+    Score,
+    WatchedEpisodes,
+    StartedWatchingAt,
+    FinishedWatchingAt,
+}
@@ -0,0 +1,145 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // MalAuth table
+        {
+            manager
+                .create_table(
+                    Table::create()
+                        .table(MalAuth::Table)
+                        .if_not_exists()
+                        .col(
+                            ColumnDef::new(MalAuth::Id)
+                                .integer()
+                                .not_null()
+                                .auto_increment()
+                                .primary_key(),
+                        )
+                        .col(ColumnDef::new(MalAuth::AccessToken).string().not_null())
+                        .col(ColumnDef::new(MalAuth::RefreshToken).string().not_null())
+                        .col(ColumnDef::new(MalAuth::ExpiresAt).date_time().not_null())
+                        .col(ColumnDef::new(MalAuth::LastPulledAt).date_time())
+                        .col(
+                            ColumnDef::new(MalAuth::CreatedAt)
+                                .date_time()
+                                .default(Expr::current_timestamp()),
+                        )
+                        .col(
+                            ColumnDef::new(MalAuth::UpdatedAt)
+                                .date_time()
+                                .default(Expr::current_timestamp()),
+                        )
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        // MalOauthStates table
+        {
+            manager
+                .create_table(
+                    Table::create()
+                        .table(MalOauthStates::Table)
+                        .if_not_exists()
+                        .col(
+                            ColumnDef::new(MalOauthStates::State)
+                                .string()
+                                .not_null()
+                                .primary_key(),
+                        )
+                        .col(
+                            ColumnDef::new(MalOauthStates::CodeVerifier)
+                                .string()
+                                .not_null(),
+                        )
+                        .col(
+                            ColumnDef::new(MalOauthStates::CreatedAt)
+                                .date_time()
+                                .default(Expr::current_timestamp()),
+                        )
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        // SQLite only supports one change per `ALTER TABLE` statement
+        for mut column in [
+            ColumnDef::new(Series::ListUpdatedAt).date_time().to_owned(),
+            ColumnDef::new(Series::MalSyncedAt).date_time().to_owned(),
+            ColumnDef::new(Series::MalListUpdatedAt)
+                .date_time()
+                .to_owned(),
+        ] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Series::Table)
+                        .add_column(&mut column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for column in [
+            Series::ListUpdatedAt,
+            Series::MalSyncedAt,
+            Series::MalListUpdatedAt,
+        ] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Series::Table)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        manager
+            .drop_table(Table::drop().table(MalOauthStates::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(MalAuth::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum MalAuth {
+    Table,
+    Id,
+    AccessToken,
+    RefreshToken,
+    ExpiresAt,
+    LastPulledAt,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+pub enum MalOauthStates {
+    Table,
+    State,
+    CodeVerifier,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+pub enum Series {
+    Table,
+    ListUpdatedAt,
+    MalSyncedAt,
+    MalListUpdatedAt,
+}
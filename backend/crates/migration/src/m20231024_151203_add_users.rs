@@ -0,0 +1,485 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Users table
+        {
+            manager
+                .create_table(
+                    Table::create()
+                        .table(Users::Table)
+                        .if_not_exists()
+                        .col(
+                            ColumnDef::new(Users::Id)
+                                .integer()
+                                .not_null()
+                                .auto_increment()
+                                .primary_key(),
+                        )
+                        .col(
+                            ColumnDef::new(Users::Username)
+                                .string()
+                                .not_null()
+                                .extra("COLLATE NOCASE"),
+                        )
+                        .col(ColumnDef::new(Users::PasswordHash).string().not_null())
+                        .col(
+                            ColumnDef::new(Users::IsAdmin)
+                                .boolean()
+                                .not_null()
+                                .default(false),
+                        )
+                        .col(
+                            ColumnDef::new(Users::CreatedAt)
+                                .date_time()
+                                .default(Expr::current_timestamp()),
+                        )
+                        .col(
+                            ColumnDef::new(Users::UpdatedAt)
+                                .date_time()
+                                .default(Expr::current_timestamp()),
+                        )
+                        .to_owned(),
+                )
+                .await?;
+
+            manager
+                .create_index(
+                    Index::create()
+                        .if_not_exists()
+                        .name("idx__users__username")
+                        .table(Users::Table)
+                        .col(Users::Username)
+                        .unique()
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        // Sessions table
+        {
+            manager
+                .create_table(
+                    Table::create()
+                        .table(Sessions::Table)
+                        .if_not_exists()
+                        .col(
+                            ColumnDef::new(Sessions::Id)
+                                .integer()
+                                .not_null()
+                                .auto_increment()
+                                .primary_key(),
+                        )
+                        .col(ColumnDef::new(Sessions::UserId).integer().not_null())
+                        .col(ColumnDef::new(Sessions::TokenHash).string().not_null())
+                        .col(ColumnDef::new(Sessions::ExpiresAt).date_time().not_null())
+                        .col(
+                            ColumnDef::new(Sessions::CreatedAt)
+                                .date_time()
+                                .default(Expr::current_timestamp()),
+                        )
+                        .col(
+                            ColumnDef::new(Sessions::UpdatedAt)
+                                .date_time()
+                                .default(Expr::current_timestamp()),
+                        )
+                        .foreign_key(
+                            ForeignKey::create()
+                                .name("fk__sessions__user_id")
+                                .from(Sessions::Table, Sessions::UserId)
+                                .to(Users::Table, Users::Id)
+                                .on_delete(ForeignKeyAction::Cascade)
+                                .on_update(ForeignKeyAction::Cascade),
+                        )
+                        .to_owned(),
+                )
+                .await?;
+
+            manager
+                .create_index(
+                    Index::create()
+                        .if_not_exists()
+                        .name("idx__sessions__token_hash")
+                        .table(Sessions::Table)
+                        .col(Sessions::TokenHash)
+                        .unique()
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        // ApiTokens table
+        {
+            manager
+                .create_table(
+                    Table::create()
+                        .table(ApiTokens::Table)
+                        .if_not_exists()
+                        .col(
+                            ColumnDef::new(ApiTokens::Id)
+                                .integer()
+                                .not_null()
+                                .auto_increment()
+                                .primary_key(),
+                        )
+                        .col(ColumnDef::new(ApiTokens::UserId).integer().not_null())
+                        .col(ColumnDef::new(ApiTokens::Name).string().not_null())
+                        .col(ColumnDef::new(ApiTokens::TokenHash).string().not_null())
+                        .col(
+                            ColumnDef::new(ApiTokens::CreatedAt)
+                                .date_time()
+                                .default(Expr::current_timestamp()),
+                        )
+                        .col(
+                            ColumnDef::new(ApiTokens::UpdatedAt)
+                                .date_time()
+                                .default(Expr::current_timestamp()),
+                        )
+                        .foreign_key(
+                            ForeignKey::create()
+                                .name("fk__api_tokens__user_id")
+                                .from(ApiTokens::Table, ApiTokens::UserId)
+                                .to(Users::Table, Users::Id)
+                                .on_delete(ForeignKeyAction::Cascade)
+                                .on_update(ForeignKeyAction::Cascade),
+                        )
+                        .to_owned(),
+                )
+                .await?;
+
+            manager
+                .create_index(
+                    Index::create()
+                        .if_not_exists()
+                        .name("idx__api_tokens__token_hash")
+                        .table(ApiTokens::Table)
+                        .col(ApiTokens::TokenHash)
+                        .unique()
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        // UserSettings table
+        {
+            manager
+                .create_table(
+                    Table::create()
+                        .table(UserSettings::Table)
+                        .if_not_exists()
+                        .col(
+                            ColumnDef::new(UserSettings::UserId)
+                                .integer()
+                                .not_null()
+                                .primary_key(),
+                        )
+                        .col(
+                            ColumnDef::new(UserSettings::NotifyNewEpisodes)
+                                .boolean()
+                                .not_null()
+                                .default(true),
+                        )
+                        .col(
+                            ColumnDef::new(UserSettings::NotifyWatchingOnly)
+                                .boolean()
+                                .not_null()
+                                .default(true),
+                        )
+                        .col(
+                            ColumnDef::new(UserSettings::CreatedAt)
+                                .date_time()
+                                .default(Expr::current_timestamp()),
+                        )
+                        .col(
+                            ColumnDef::new(UserSettings::UpdatedAt)
+                                .date_time()
+                                .default(Expr::current_timestamp()),
+                        )
+                        .foreign_key(
+                            ForeignKey::create()
+                                .name("fk__user_settings__user_id")
+                                .from(UserSettings::Table, UserSettings::UserId)
+                                .to(Users::Table, Users::Id)
+                                .on_delete(ForeignKeyAction::Cascade)
+                                .on_update(ForeignKeyAction::Cascade),
+                        )
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        // LibraryEntries table
+        {
+            manager
+                .create_table(
+                    Table::create()
+                        .table(LibraryEntries::Table)
+                        .if_not_exists()
+                        .col(
+                            ColumnDef::new(LibraryEntries::Id)
+                                .integer()
+                                .not_null()
+                                .auto_increment()
+                                .primary_key(),
+                        )
+                        // Entries without a user are from before there were users.
+                        // They get claimed by the first user that registers.
+                        .col(ColumnDef::new(LibraryEntries::UserId).integer())
+                        .col(
+                            ColumnDef::new(LibraryEntries::SeriesId)
+                                .integer()
+                                .not_null(),
+                        )
+                        .col(ColumnDef::new(LibraryEntries::ListStatus).string())
+                        .col(ColumnDef::new(LibraryEntries::Score).integer())
+                        .col(ColumnDef::new(LibraryEntries::WatchedEpisodes).integer())
+                        .col(ColumnDef::new(LibraryEntries::StartedWatchingAt).date())
+                        .col(ColumnDef::new(LibraryEntries::FinishedWatchingAt).date())
+                        .col(ColumnDef::new(LibraryEntries::ListUpdatedAt).date_time())
+                        .col(ColumnDef::new(LibraryEntries::MalSyncedAt).date_time())
+                        .col(ColumnDef::new(LibraryEntries::MalListUpdatedAt).date_time())
+                        .col(
+                            ColumnDef::new(LibraryEntries::CreatedAt)
+                                .date_time()
+                                .default(Expr::current_timestamp()),
+                        )
+                        .col(
+                            ColumnDef::new(LibraryEntries::UpdatedAt)
+                                .date_time()
+                                .default(Expr::current_timestamp()),
+                        )
+                        .foreign_key(
+                            ForeignKey::create()
+                                .name("fk__library_entries__user_id")
+                                .from(LibraryEntries::Table, LibraryEntries::UserId)
+                                .to(Users::Table, Users::Id)
+                                .on_delete(ForeignKeyAction::Cascade)
+                                .on_update(ForeignKeyAction::Cascade),
+                        )
+                        .foreign_key(
+                            ForeignKey::create()
+                                .name("fk__library_entries__series_id")
+                                .from(LibraryEntries::Table, LibraryEntries::SeriesId)
+                                .to(Series::Table, Series::Id)
+                                .on_delete(ForeignKeyAction::Cascade)
+                                .on_update(ForeignKeyAction::Cascade),
+                        )
+                        .to_owned(),
+                )
+                .await?;
+
+            manager
+                .create_index(
+                    Index::create()
+                        .if_not_exists()
+                        .name("idx__library_entries__user_series")
+                        .table(LibraryEntries::Table)
+                        .col(LibraryEntries::UserId)
+                        .col(LibraryEntries::SeriesId)
+                        .unique()
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        // Move the list out of the shared series table
+        manager
+            .get_connection()
+            .execute_unprepared(
+                "INSERT INTO library_entries (
+                    series_id, list_status, score, watched_episodes,
+                    started_watching_at, finished_watching_at,
+                    list_updated_at, mal_synced_at, mal_list_updated_at
+                )
+                SELECT
+                    id, list_status, score, watched_episodes,
+                    started_watching_at, finished_watching_at,
+                    list_updated_at, mal_synced_at, mal_list_updated_at
+                FROM series
+                WHERE list_status IS NOT NULL
+                    OR score IS NOT NULL
+                    OR watched_episodes IS NOT NULL",
+            )
+            .await?;
+
+        for column in LIST_COLUMNS {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Series::Table)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        // SQLite only supports one change per `ALTER TABLE` statement
+        for table in [
+            MalAuth::Table.into_iden(),
+            MalOauthStates::Table.into_iden(),
+        ] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(table)
+                        .add_column(ColumnDef::new(Alias::new("user_id")).integer())
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for table in [
+            MalAuth::Table.into_iden(),
+            MalOauthStates::Table.into_iden(),
+        ] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(table)
+                        .drop_column(Alias::new("user_id"))
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        for mut column in [
+            ColumnDef::new(Series::ListStatus).string().to_owned(),
+            ColumnDef::new(Series::Score).integer().to_owned(),
+            ColumnDef::new(Series::WatchedEpisodes).integer().to_owned(),
+            ColumnDef::new(Series::StartedWatchingAt).date().to_owned(),
+            ColumnDef::new(Series::FinishedWatchingAt).date().to_owned(),
+            ColumnDef::new(Series::ListUpdatedAt).date_time().to_owned(),
+            ColumnDef::new(Series::MalSyncedAt).date_time().to_owned(),
+            ColumnDef::new(Series::MalListUpdatedAt)
+                .date_time()
+                .to_owned(),
+        ] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Series::Table)
+                        .add_column(&mut column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        for table in [
+            LibraryEntries::Table.into_iden(),
+            UserSettings::Table.into_iden(),
+            ApiTokens::Table.into_iden(),
+            Sessions::Table.into_iden(),
+            Users::Table.into_iden(),
+        ] {
+            manager
+                .drop_table(Table::drop().table(table).to_owned())
+                .await?;
+        }
+
+        Ok(())
+    }
+}
+
+const LIST_COLUMNS: [Series; 8] = [
+    Series::ListStatus,
+    Series::Score,
+    Series::WatchedEpisodes,
+    Series::StartedWatchingAt,
+    Series::FinishedWatchingAt,
+    Series::ListUpdatedAt,
+    Series::MalSyncedAt,
+    Series::MalListUpdatedAt,
+];
+
+#[derive(DeriveIden)]
+pub enum Users {
+    Table,
+    Id,
+    Username,
+    PasswordHash,
+    IsAdmin,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+pub enum Sessions {
+    Table,
+    Id,
+    UserId,
+    TokenHash,
+    ExpiresAt,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+pub enum ApiTokens {
+    Table,
+    Id,
+    UserId,
+    Name,
+    TokenHash,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+pub enum UserSettings {
+    Table,
+    UserId,
+    NotifyNewEpisodes,
+    NotifyWatchingOnly,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+pub enum LibraryEntries {
+    Table,
+    Id,
+    UserId,
+    SeriesId,
+    ListStatus,
+    Score,
+    WatchedEpisodes,
+    StartedWatchingAt,
+    FinishedWatchingAt,
+    ListUpdatedAt,
+    MalSyncedAt,
+    MalListUpdatedAt,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden, Clone, Copy)]
+pub enum Series {
+    Table,
+    Id,
+    ListStatus,
+    Score,
+    WatchedEpisodes,
+    StartedWatchingAt,
+    FinishedWatchingAt,
+    ListUpdatedAt,
+    MalSyncedAt,
+    MalListUpdatedAt,
+}
+
+#[derive(DeriveIden)]
+pub enum MalAuth {
+    Table,
+}
+
+#[derive(DeriveIden)]
+pub enum MalOauthStates {
+    Table,
+}
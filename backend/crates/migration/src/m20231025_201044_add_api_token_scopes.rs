@@ -0,0 +1,59 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // SQLite only supports one change per `ALTER TABLE` statement
+        for mut column in [
+            // Tokens made before scopes existed could already change the library
+            ColumnDef::new(ApiTokens::Scope)
+                .string()
+                .not_null()
+                .default("library-write")
+                .to_owned(),
+            ColumnDef::new(ApiTokens::ExpiresAt).date_time().to_owned(),
+            ColumnDef::new(ApiTokens::LastUsedAt).date_time().to_owned(),
+        ] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(ApiTokens::Table)
+                        .add_column(&mut column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for column in [
+            ApiTokens::Scope,
+            ApiTokens::ExpiresAt,
+            ApiTokens::LastUsedAt,
+        ] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(ApiTokens::Table)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum ApiTokens {
+    Table,
+    Scope,
+    ExpiresAt,
+    LastUsedAt,
+}
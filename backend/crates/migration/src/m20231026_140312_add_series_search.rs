@@ -0,0 +1,104 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Both are cached from the sources' metadata, one entry per line
+        for mut column in [
+            ColumnDef::new(Series::AltNames).text().to_owned(),
+            ColumnDef::new(Series::Genres).text().to_owned(),
+        ] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Series::Table)
+                        .add_column(&mut column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        let db = manager.get_connection();
+
+        // The trigram tokenizer matches any part of a word, which also works for
+        // Japanese titles that have no spaces to split words on
+        db.execute_unprepared(
+            "CREATE VIRTUAL TABLE series_search USING fts5(
+                name, description, alt_names, genres,
+                content = 'series',
+                content_rowid = 'id',
+                tokenize = 'trigram'
+            )",
+        )
+        .await?;
+
+        db.execute_unprepared(
+            "CREATE TRIGGER series_search_after_insert AFTER INSERT ON series BEGIN
+                INSERT INTO series_search (rowid, name, description, alt_names, genres)
+                VALUES (new.id, new.name, new.description, new.alt_names, new.genres);
+            END",
+        )
+        .await?;
+
+        db.execute_unprepared(
+            "CREATE TRIGGER series_search_after_delete AFTER DELETE ON series BEGIN
+                INSERT INTO series_search (series_search, rowid, name, description, alt_names, genres)
+                VALUES ('delete', old.id, old.name, old.description, old.alt_names, old.genres);
+            END",
+        )
+        .await?;
+
+        db.execute_unprepared(
+            "CREATE TRIGGER series_search_after_update AFTER UPDATE ON series BEGIN
+                INSERT INTO series_search (series_search, rowid, name, description, alt_names, genres)
+                VALUES ('delete', old.id, old.name, old.description, old.alt_names, old.genres);
+                INSERT INTO series_search (rowid, name, description, alt_names, genres)
+                VALUES (new.id, new.name, new.description, new.alt_names, new.genres);
+            END",
+        )
+        .await?;
+
+        db.execute_unprepared("INSERT INTO series_search (series_search) VALUES ('rebuild')")
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+
+        for trigger in [
+            "series_search_after_insert",
+            "series_search_after_delete",
+            "series_search_after_update",
+        ] {
+            db.execute_unprepared(&format!("DROP TRIGGER IF EXISTS {trigger}"))
+                .await?;
+        }
+        db.execute_unprepared("DROP TABLE IF EXISTS series_search")
+            .await?;
+
+        for column in [Series::AltNames, Series::Genres] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Series::Table)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Series {
+    Table,
+    AltNames,
+    Genres,
+}
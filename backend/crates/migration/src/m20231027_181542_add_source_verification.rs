@@ -0,0 +1,60 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // What the site reported for the source when it was last checked
+        for mut column in [
+            ColumnDef::new(SeriesSources::VerifiedName)
+                .string()
+                .to_owned(),
+            ColumnDef::new(SeriesSources::VerifiedMalId)
+                .integer()
+                .to_owned(),
+            ColumnDef::new(SeriesSources::VerifiedAt)
+                .string()
+                .to_owned(),
+        ] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(SeriesSources::Table)
+                        .add_column(&mut column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for column in [
+            SeriesSources::VerifiedName,
+            SeriesSources::VerifiedMalId,
+            SeriesSources::VerifiedAt,
+        ] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(SeriesSources::Table)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum SeriesSources {
+    Table,
+    VerifiedName,
+    VerifiedMalId,
+    VerifiedAt,
+}
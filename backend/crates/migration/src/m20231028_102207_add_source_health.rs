@@ -0,0 +1,77 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Outcome of fetching the source's info from its site
+        for mut column in [
+            ColumnDef::new(SeriesSources::LastSuccessAt)
+                .string()
+                .to_owned(),
+            ColumnDef::new(SeriesSources::LastFailureAt)
+                .string()
+                .to_owned(),
+            ColumnDef::new(SeriesSources::ConsecutiveFailures)
+                .integer()
+                .not_null()
+                .default(0)
+                .to_owned(),
+            ColumnDef::new(SeriesSources::LastErrorKind)
+                .string()
+                .to_owned(),
+            ColumnDef::new(SeriesSources::LastError).text().to_owned(),
+            ColumnDef::new(SeriesSources::Broken)
+                .boolean()
+                .not_null()
+                .default(false)
+                .to_owned(),
+        ] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(SeriesSources::Table)
+                        .add_column(&mut column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for column in [
+            SeriesSources::LastSuccessAt,
+            SeriesSources::LastFailureAt,
+            SeriesSources::ConsecutiveFailures,
+            SeriesSources::LastErrorKind,
+            SeriesSources::LastError,
+            SeriesSources::Broken,
+        ] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(SeriesSources::Table)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum SeriesSources {
+    Table,
+    LastSuccessAt,
+    LastFailureAt,
+    ConsecutiveFailures,
+    LastErrorKind,
+    LastError,
+    Broken,
+}
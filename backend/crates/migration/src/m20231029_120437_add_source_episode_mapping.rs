@@ -0,0 +1,36 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // How the episode numbers of a source line up with the episodes of its series
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(SeriesSources::Table)
+                    .add_column(ColumnDef::new(SeriesSources::EpisodeMapping).json())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(SeriesSources::Table)
+                    .drop_column(SeriesSources::EpisodeMapping)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum SeriesSources {
+    Table,
+    EpisodeMapping,
+}
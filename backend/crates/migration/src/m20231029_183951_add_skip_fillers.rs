@@ -0,0 +1,41 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Whether filler episodes count towards what's left to watch
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(LibraryEntries::Table)
+                    .add_column(
+                        ColumnDef::new(LibraryEntries::SkipFillers)
+                            .boolean()
+                            .not_null()
+                            .default(false),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(LibraryEntries::Table)
+                    .drop_column(LibraryEntries::SkipFillers)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum LibraryEntries {
+    Table,
+    SkipFillers,
+}
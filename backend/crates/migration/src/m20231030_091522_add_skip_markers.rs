@@ -0,0 +1,113 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Parts of episodes, like openings, that users marked as skippable
+        manager
+            .create_table(
+                Table::create()
+                    .table(SkipMarkers::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(SkipMarkers::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(SkipMarkers::SeriesId).integer().not_null())
+                    .col(
+                        ColumnDef::new(SkipMarkers::EpisodeNumber)
+                            .double()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(SkipMarkers::Kind).string().not_null())
+                    .col(
+                        ColumnDef::new(SkipMarkers::StartSeconds)
+                            .double()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(SkipMarkers::EndSeconds).double().not_null())
+                    // Markers outlive the user that submitted them
+                    .col(ColumnDef::new(SkipMarkers::UserId).integer())
+                    .col(
+                        ColumnDef::new(SkipMarkers::CreatedAt)
+                            .date_time()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .col(
+                        ColumnDef::new(SkipMarkers::UpdatedAt)
+                            .date_time()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk__skip_markers__series_id")
+                            .from(SkipMarkers::Table, SkipMarkers::SeriesId)
+                            .to(Series::Table, Series::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk__skip_markers__user_id")
+                            .from(SkipMarkers::Table, SkipMarkers::UserId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::SetNull)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .if_not_exists()
+                    .name("idx__skip_markers__series_episode")
+                    .table(SkipMarkers::Table)
+                    .col(SkipMarkers::SeriesId)
+                    .col(SkipMarkers::EpisodeNumber)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(SkipMarkers::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum SkipMarkers {
+    Table,
+    Id,
+    SeriesId,
+    EpisodeNumber,
+    Kind,
+    StartSeconds,
+    EndSeconds,
+    UserId,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Series {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
@@ -0,0 +1,36 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // When the site expects the next episode, as of the last successful fetch of the source
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(SeriesSources::Table)
+                    .add_column(ColumnDef::new(SeriesSources::NextReleaseAt).string())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(SeriesSources::Table)
+                    .drop_column(SeriesSources::NextReleaseAt)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum SeriesSources {
+    Table,
+    NextReleaseAt,
+}
@@ -7,6 +7,7 @@ use serde::{de::DeserializeOwned, Serialize};
 pub mod schema {}
 
 pub mod common_;
+pub mod search_shows;
 pub mod show_info;
 
 pub const BASE_API_URL: &str = "https://api.allanime.day/api";
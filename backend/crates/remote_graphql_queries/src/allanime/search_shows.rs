@@ -0,0 +1,39 @@
+use super::{common_::BigInt, schema};
+
+#[derive(cynic::QueryVariables, Debug)]
+pub struct SearchShowsVariables {
+    pub search: SearchInput,
+    pub limit: Option<i32>,
+}
+
+#[derive(cynic::InputObject, Debug, Default)]
+#[cynic(graphql_type = "SearchInput")]
+pub struct SearchInput {
+    pub query: Option<String>,
+    pub allow_adult: Option<bool>,
+    pub allow_unknown: Option<bool>,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+#[cynic(graphql_type = "Query", variables = "SearchShowsVariables")]
+pub struct SearchShows {
+    #[arguments(search: $search, limit: $limit)]
+    pub shows: ShowsConnection,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+pub struct ShowsConnection {
+    pub edges: Option<Vec<SearchShow>>,
+}
+
+#[derive(cynic::QueryFragment, Debug, Clone)]
+#[cynic(graphql_type = "Show")]
+pub struct SearchShow {
+    #[cynic(rename = "_id")]
+    pub id: Option<String>,
+    pub mal_id: Option<BigInt>,
+    pub english_name: Option<String>,
+    pub native_name: Option<String>,
+    #[cynic(rename = "name")]
+    pub romanji_name: Option<String>,
+}
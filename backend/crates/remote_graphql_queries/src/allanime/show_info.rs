@@ -158,8 +158,30 @@ pub struct EpisodeInfo {
     pub title: Option<String>,
     pub description: Option<String>,
     pub episode_id_num: Option<f64>,
+    pub thumbnails: Option<Vec<Option<String>>>,
+    pub upload_dates: Option<Object<SubDubRaw<String>>>,
     #[cynic(rename = "vidInforssub")]
     pub sub_info: Option<Object>,
     #[cynic(rename = "vidInforsdub")]
     pub dub_info: Option<Object>,
+    #[cynic(rename = "vidInforsraw")]
+    pub raw_info: Option<Object>,
+}
+
+impl EpisodeInfo {
+    /// When the episode was uploaded in a translation, if allanime knows
+    #[must_use]
+    pub fn uploaded_at(&self, for_type: &TranslationType) -> Option<DateTime<Utc>> {
+        let dates = &self.upload_dates.as_ref()?.0;
+
+        let date = match for_type {
+            TranslationType::Sub => dates.sub.as_deref(),
+            TranslationType::Dub => dates.dub.as_deref(),
+            TranslationType::Raw => dates.raw.as_deref(),
+        }?;
+
+        DateTime::parse_from_rfc3339(date)
+            .ok()
+            .map(|x| x.with_timezone(&Utc))
+    }
 }
@@ -0,0 +1,319 @@
+use anyhow::{anyhow, bail, Result};
+use argon2::{
+    password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
+use std::str::FromStr;
+
+use chrono::{DateTime, Duration, Utc};
+use log::{debug, info, warn};
+use sea_orm::{prelude::*, sea_query::Expr, ActiveValue, Condition, TransactionTrait};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use utoipa::ToSchema;
+
+#[cfg(test)]
+mod tests;
+
+const MIN_PASSWORD_LENGTH: usize = 8;
+const MAX_USERNAME_LENGTH: usize = 64;
+/// How stale the last-used time of an API token may get, so that not every request writes to the database
+const LAST_USED_RESOLUTION_SECONDS: i64 = 60;
+
+/// What an API token is allowed to do. Every scope includes the ones before it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum TokenScope {
+    /// Only reading, so only `GET` requests
+    ReadOnly,
+    /// Everything a user can do with their own library
+    LibraryWrite,
+    /// Everything, including the admin endpoints if the user is an admin
+    Admin,
+}
+
+impl ToString for TokenScope {
+    fn to_string(&self) -> String {
+        match self {
+            Self::ReadOnly => "read-only".to_string(),
+            Self::LibraryWrite => "library-write".to_string(),
+            Self::Admin => "admin".to_string(),
+        }
+    }
+}
+
+impl FromStr for TokenScope {
+    type Err = serde_json::Error;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        serde_json::from_str(&format!("\"{}\"", s))
+    }
+}
+
+/// The user a token belongs to, and what the token may do on their behalf
+#[derive(Debug, Clone)]
+pub struct TokenOwner {
+    pub user: entity::users::Model,
+    /// `None` for sessions, which can do anything their user can
+    pub scope: Option<TokenScope>,
+}
+
+pub fn hash_password(password: &str) -> Result<String> {
+    let salt = SaltString::encode_b64(&rand::random::<[u8; 16]>())
+        .map_err(|e| anyhow!("Failed to generate salt: {e}"))?;
+
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|x| x.to_string())
+        .map_err(|e| anyhow!("Failed to hash password: {e}"))
+}
+
+pub fn verify_password(password_hash: &str, password: &str) -> bool {
+    let Ok(hash) = PasswordHash::new(password_hash) else {
+        return false;
+    };
+
+    Argon2::default()
+        .verify_password(password.as_bytes(), &hash)
+        .is_ok()
+}
+
+/// A new random secret for sessions and API tokens
+pub fn generate_token() -> String {
+    hex::encode(rand::random::<[u8; 32]>())
+}
+
+/// Only hashes of tokens are stored, so a leaked database can't be used to log in
+pub fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+fn validate_credentials(username: &str, password: &str) -> Result<()> {
+    if username.trim().is_empty() || username.trim() != username {
+        bail!("Username can't be empty or start or end with whitespace");
+    }
+    if username.chars().count() > MAX_USERNAME_LENGTH {
+        bail!("Username can be at most {MAX_USERNAME_LENGTH} characters long");
+    }
+    if password.chars().count() < MIN_PASSWORD_LENGTH {
+        bail!("Password must be at least {MIN_PASSWORD_LENGTH} characters long");
+    }
+
+    Ok(())
+}
+
+pub async fn has_users(db: &DatabaseConnection) -> Result<bool> {
+    Ok(entity::users::Entity::find().one(db).await?.is_some())
+}
+
+/// Create a user along with their default settings.
+///
+/// The first user to be created becomes an admin and takes over the library
+/// entries and MAL link that were made before there were any users.
+pub async fn create_user(
+    db: &DatabaseConnection,
+    username: &str,
+    password: &str,
+    is_admin: bool,
+) -> Result<entity::users::Model> {
+    validate_credentials(username, password)?;
+    let password_hash = hash_password(password)?;
+
+    let txn = db.begin().await?;
+
+    let is_first = entity::users::Entity::find().one(&txn).await?.is_none();
+
+    let user = entity::users::ActiveModel {
+        username: ActiveValue::Set(username.to_string()),
+        password_hash: ActiveValue::Set(password_hash),
+        is_admin: ActiveValue::Set(is_admin || is_first),
+        ..Default::default()
+    }
+    .insert(&txn)
+    .await?;
+
+    entity::user_settings::ActiveModel {
+        user_id: ActiveValue::Set(user.id),
+        notify_new_episodes: ActiveValue::Set(true),
+        notify_watching_only: ActiveValue::Set(true),
+        ..Default::default()
+    }
+    .insert(&txn)
+    .await?;
+
+    if is_first {
+        let claimed = entity::library_entries::Entity::update_many()
+            .col_expr(
+                entity::library_entries::Column::UserId,
+                Expr::value(user.id),
+            )
+            .filter(entity::library_entries::Column::UserId.is_null())
+            .exec(&txn)
+            .await?;
+        entity::mal_auth::Entity::update_many()
+            .col_expr(entity::mal_auth::Column::UserId, Expr::value(user.id))
+            .filter(entity::mal_auth::Column::UserId.is_null())
+            .exec(&txn)
+            .await?;
+
+        info!(
+            "First user {:?} created, claimed {} existing library entries",
+            user.username, claimed.rows_affected
+        );
+    }
+
+    txn.commit().await?;
+
+    Ok(user)
+}
+
+/// Check a username and password, returning the matching user
+pub async fn authenticate(
+    db: &DatabaseConnection,
+    username: &str,
+    password: &str,
+) -> Result<Option<entity::users::Model>> {
+    let user = entity::users::Entity::find()
+        .filter(entity::users::Column::Username.eq(username))
+        .one(db)
+        .await?;
+
+    Ok(user.filter(|x| verify_password(&x.password_hash, password)))
+}
+
+/// Start a new session, returning the token to hand to the client
+pub async fn create_session(
+    db: &DatabaseConnection,
+    user_id: i32,
+    duration: std::time::Duration,
+) -> Result<(String, entity::sessions::Model)> {
+    let token = generate_token();
+    let expires_at = Utc::now() + Duration::from_std(duration)?;
+
+    let session = entity::sessions::ActiveModel {
+        user_id: ActiveValue::Set(user_id),
+        token_hash: ActiveValue::Set(hash_token(&token)),
+        expires_at: ActiveValue::Set(expires_at.to_rfc3339()),
+        ..Default::default()
+    }
+    .insert(db)
+    .await?;
+
+    // Good a time as any to forget about old sessions
+    entity::sessions::Entity::delete_many()
+        .filter(entity::sessions::Column::ExpiresAt.lt(Utc::now().to_rfc3339()))
+        .exec(db)
+        .await?;
+
+    Ok((token, session))
+}
+
+pub async fn delete_session(db: &DatabaseConnection, token: &str) -> Result<()> {
+    entity::sessions::Entity::delete_many()
+        .filter(entity::sessions::Column::TokenHash.eq(hash_token(token)))
+        .exec(db)
+        .await?;
+
+    Ok(())
+}
+
+/// Create a personal API token, returning the token itself. It can't be recovered later.
+pub async fn create_api_token(
+    db: &DatabaseConnection,
+    user_id: i32,
+    name: &str,
+    scope: TokenScope,
+    expires_at: Option<DateTime<Utc>>,
+) -> Result<(String, entity::api_tokens::Model)> {
+    let token = generate_token();
+
+    let model = entity::api_tokens::ActiveModel {
+        user_id: ActiveValue::Set(user_id),
+        name: ActiveValue::Set(name.to_string()),
+        token_hash: ActiveValue::Set(hash_token(&token)),
+        scope: ActiveValue::Set(scope.to_string()),
+        expires_at: ActiveValue::Set(expires_at.map(|x| x.to_rfc3339())),
+        ..Default::default()
+    }
+    .insert(db)
+    .await?;
+
+    Ok((token, model))
+}
+
+/// Find the user a session or API token belongs to
+pub async fn user_for_token(db: &DatabaseConnection, token: &str) -> Result<Option<TokenOwner>> {
+    let now = Utc::now();
+    let token_hash = hash_token(token);
+
+    let session = entity::sessions::Entity::find()
+        .filter(entity::sessions::Column::TokenHash.eq(token_hash.as_str()))
+        .filter(entity::sessions::Column::ExpiresAt.gt(now.to_rfc3339()))
+        .find_also_related(entity::users::Entity)
+        .one(db)
+        .await?;
+    if let Some((_, user)) = session {
+        return Ok(user.map(|user| TokenOwner { user, scope: None }));
+    }
+
+    let api_token = entity::api_tokens::Entity::find()
+        .filter(entity::api_tokens::Column::TokenHash.eq(token_hash.as_str()))
+        .filter(
+            Condition::any()
+                .add(entity::api_tokens::Column::ExpiresAt.is_null())
+                .add(entity::api_tokens::Column::ExpiresAt.gt(now.to_rfc3339())),
+        )
+        .find_also_related(entity::users::Entity)
+        .one(db)
+        .await?;
+    if let Some((api_token, Some(user))) = api_token {
+        let Ok(scope) = api_token.scope.parse::<TokenScope>() else {
+            warn!(
+                "API token {} has unknown scope {:?}",
+                api_token.id, api_token.scope
+            );
+            return Ok(None);
+        };
+
+        touch_api_token(db, &api_token, now).await?;
+
+        return Ok(Some(TokenOwner {
+            user,
+            scope: Some(scope),
+        }));
+    }
+
+    debug!("Unknown token used");
+
+    Ok(None)
+}
+
+/// Remember when an API token was last used
+async fn touch_api_token(
+    db: &DatabaseConnection,
+    api_token: &entity::api_tokens::Model,
+    now: DateTime<Utc>,
+) -> Result<()> {
+    let recently_used = api_token
+        .last_used_at
+        .as_deref()
+        .and_then(|x| DateTime::parse_from_rfc3339(x).ok())
+        .is_some_and(|x| {
+            now - x.with_timezone(&Utc) < Duration::seconds(LAST_USED_RESOLUTION_SECONDS)
+        });
+    if recently_used {
+        return Ok(());
+    }
+
+    // Not through the active model, using a token isn't changing it
+    entity::api_tokens::Entity::update_many()
+        .col_expr(
+            entity::api_tokens::Column::LastUsedAt,
+            Expr::value(now.to_rfc3339()),
+        )
+        .filter(entity::api_tokens::Column::Id.eq(api_token.id))
+        .exec(db)
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,148 @@
+//! Looking up who a token belongs to, in a migrated in-memory database
+
+use chrono::{Duration, Utc};
+use sea_orm::{prelude::*, ActiveValue};
+
+use super::{create_api_token, hash_token, touch_api_token, user_for_token, TokenScope};
+use crate::test_util::database;
+
+async fn user(db: &DatabaseConnection, username: &str) -> entity::users::Model {
+    // Hashing a real password is slow and none of these log in with one
+    entity::users::ActiveModel {
+        username: ActiveValue::Set(username.to_string()),
+        password_hash: ActiveValue::Set(String::new()),
+        is_admin: ActiveValue::Set(false),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap()
+}
+
+async fn session(db: &DatabaseConnection, user_id: i32, token: &str, expires_in: Duration) {
+    entity::sessions::ActiveModel {
+        user_id: ActiveValue::Set(user_id),
+        token_hash: ActiveValue::Set(hash_token(token)),
+        expires_at: ActiveValue::Set((Utc::now() + expires_in).to_rfc3339()),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap();
+}
+
+async fn last_used_at(db: &DatabaseConnection, id: i32) -> Option<String> {
+    entity::api_tokens::Entity::find_by_id(id)
+        .one(db)
+        .await
+        .unwrap()
+        .unwrap()
+        .last_used_at
+}
+
+#[tokio::test]
+async fn finds_the_owner_of_sessions_and_api_tokens() {
+    let db = database().await;
+    let frieren = user(&db, "frieren").await;
+    session(&db, frieren.id, "session", Duration::hours(1)).await;
+    let (token, _) = create_api_token(&db, frieren.id, "Scripts", TokenScope::ReadOnly, None)
+        .await
+        .unwrap();
+
+    let owner = user_for_token(&db, "session").await.unwrap().unwrap();
+    assert_eq!(owner.user.id, frieren.id);
+    assert_eq!(owner.scope, None);
+
+    let owner = user_for_token(&db, &token).await.unwrap().unwrap();
+    assert_eq!(owner.user.id, frieren.id);
+    assert_eq!(owner.scope, Some(TokenScope::ReadOnly));
+
+    assert!(user_for_token(&db, "unknown").await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn rejects_expired_tokens() {
+    let db = database().await;
+    let frieren = user(&db, "frieren").await;
+    session(&db, frieren.id, "session", -Duration::seconds(1)).await;
+    let (expired, _) = create_api_token(
+        &db,
+        frieren.id,
+        "Scripts",
+        TokenScope::Admin,
+        Some(Utc::now() - Duration::seconds(1)),
+    )
+    .await
+    .unwrap();
+    let (valid, _) = create_api_token(
+        &db,
+        frieren.id,
+        "Scripts",
+        TokenScope::Admin,
+        Some(Utc::now() + Duration::hours(1)),
+    )
+    .await
+    .unwrap();
+
+    assert!(user_for_token(&db, "session").await.unwrap().is_none());
+    assert!(user_for_token(&db, &expired).await.unwrap().is_none());
+    assert!(user_for_token(&db, &valid).await.unwrap().is_some());
+}
+
+#[tokio::test]
+async fn rejects_api_tokens_with_an_unknown_scope() {
+    let db = database().await;
+    let frieren = user(&db, "frieren").await;
+    let (token, model) = create_api_token(&db, frieren.id, "Scripts", TokenScope::Admin, None)
+        .await
+        .unwrap();
+    let mut model = entity::api_tokens::ActiveModel::from(model);
+    model.scope = ActiveValue::Set("everything".to_string());
+    model.update(&db).await.unwrap();
+
+    assert!(user_for_token(&db, &token).await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn remembers_when_api_tokens_were_used() {
+    let db = database().await;
+    let frieren = user(&db, "frieren").await;
+    let (token, model) = create_api_token(&db, frieren.id, "Scripts", TokenScope::Admin, None)
+        .await
+        .unwrap();
+    assert_eq!(model.last_used_at, None);
+
+    user_for_token(&db, &token).await.unwrap().unwrap();
+
+    assert!(last_used_at(&db, model.id).await.is_some());
+}
+
+#[tokio::test]
+async fn touches_api_tokens_at_most_once_a_minute() {
+    let db = database().await;
+    let frieren = user(&db, "frieren").await;
+    let (_, model) = create_api_token(&db, frieren.id, "Scripts", TokenScope::Admin, None)
+        .await
+        .unwrap();
+    let used_at = Utc::now();
+
+    touch_api_token(&db, &model, used_at).await.unwrap();
+    let model = entity::api_tokens::Entity::find_by_id(model.id)
+        .one(&db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(model.last_used_at, Some(used_at.to_rfc3339()));
+
+    touch_api_token(&db, &model, used_at + Duration::seconds(30))
+        .await
+        .unwrap();
+    assert_eq!(
+        last_used_at(&db, model.id).await,
+        Some(used_at.to_rfc3339())
+    );
+
+    let later = used_at + Duration::seconds(61);
+    touch_api_token(&db, &model, later).await.unwrap();
+    assert_eq!(last_used_at(&db, model.id).await, Some(later.to_rfc3339()));
+}
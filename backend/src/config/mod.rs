@@ -1,3 +1,5 @@
+use std::{path::PathBuf, time::Duration};
+
 use clap::{ArgAction, Args, Parser};
 use dotenvy::dotenv;
 use lazy_static::lazy_static;
@@ -11,6 +13,10 @@ pub struct Config {
     pub app: AppConfig,
     pub server: ServerConfig,
     pub database: DatabaseConfig,
+    pub auth: AuthConfig,
+    pub mal: MalConfig,
+    pub library: LibraryConfig,
+    pub stream: StreamConfig,
 }
 
 impl Config {
@@ -22,6 +28,10 @@ impl Config {
             app: args.app,
             server: args.server,
             database: args.database,
+            auth: args.auth,
+            mal: args.mal,
+            library: args.library,
+            stream: args.stream,
         }
     }
 }
@@ -47,6 +57,17 @@ pub struct ServerConfig {
     /// Port to listen on
     #[clap(short, long, default_value = "3001", env = "PORT")]
     pub port: u16,
+    /// Origins allowed to make cross-origin requests, eg. `http://localhost:3000`.
+    ///
+    /// Can be given multiple times or as a comma separated list.
+    /// If not specified, only same-origin requests are allowed.
+    #[clap(long = "cors-origin", env = "CORS_ORIGINS", value_delimiter = ',')]
+    pub cors_origins: Vec<String>,
+    /// Serve interactive API documentation at `/v1/docs`.
+    ///
+    /// The OpenAPI spec itself is always available at `/v1/openapi.json`.
+    #[clap(long = "api-docs", env = "API_DOCS")]
+    pub api_docs: bool,
 }
 
 #[derive(Debug, Clone, Args)]
@@ -59,6 +80,170 @@ pub struct DatabaseConfig {
     /// If not specified, an in-memory database will be used.
     #[clap(long = "database-url", env = "DATABASE_URL")]
     pub url: Option<String>,
+    /// Directory to periodically write database snapshots to.
+    ///
+    /// Snapshots are consistent copies of the whole database made with `VACUUM INTO`.
+    ///
+    /// If not specified, no snapshots will be made.
+    #[clap(long = "database-snapshot-dir", env = "DATABASE_SNAPSHOT_DIR")]
+    pub snapshot_dir: Option<PathBuf>,
+    /// How often to make a snapshot, eg. `6h` or `1d`
+    #[clap(
+        long = "database-snapshot-interval",
+        default_value = "6h",
+        env = "DATABASE_SNAPSHOT_INTERVAL",
+        value_parser = duration_str::parse
+    )]
+    pub snapshot_interval: Duration,
+    /// How many snapshots to keep. The oldest ones get deleted once there are more.
+    ///
+    /// Set to 0 to keep all of them.
+    #[clap(
+        long = "database-snapshot-keep",
+        default_value = "14",
+        env = "DATABASE_SNAPSHOT_KEEP"
+    )]
+    pub snapshot_keep: usize,
+    /// Snapshot file name prefix. Files are named `<prefix>-<UTC timestamp>.sqlite`.
+    #[clap(
+        long = "database-snapshot-prefix",
+        default_value = "database",
+        env = "DATABASE_SNAPSHOT_PREFIX"
+    )]
+    pub snapshot_prefix: String,
+}
+
+#[derive(Debug, Clone, Args)]
+#[clap(next_help_heading = "Auth options")]
+pub struct AuthConfig {
+    /// How long a login session lasts, eg. `30d`
+    #[clap(
+        long = "session-duration",
+        default_value = "30d",
+        env = "SESSION_DURATION",
+        value_parser = duration_str::parse
+    )]
+    pub session_duration: Duration,
+    /// Only send the session cookie over HTTPS.
+    ///
+    /// Should be enabled whenever the server is reachable over HTTPS.
+    #[clap(long = "secure-cookies", env = "SECURE_COOKIES")]
+    pub secure_cookies: bool,
+}
+
+#[derive(Debug, Clone, Args)]
+#[clap(next_help_heading = "MyAnimeList options")]
+pub struct MalConfig {
+    /// Client ID of the MAL API application
+    #[clap(
+        long = "mal-client-id",
+        default_value = "16c0cefeeb62cb0fb474388753256fa5",
+        env = "MAL_CLIENT_ID"
+    )]
+    pub client_id: String,
+    /// Client secret of the MAL API application.
+    ///
+    /// Only needed if the application is registered as a "web" app.
+    #[clap(long = "mal-client-secret", env = "MAL_CLIENT_SECRET")]
+    pub client_secret: Option<String>,
+    /// Where MAL should send the user back to after they authorize the app.
+    ///
+    /// Must match the redirect URL registered for the MAL application
+    /// and should point to `/v1/mal/auth/callback` of this server.
+    #[clap(long = "mal-redirect-url", env = "MAL_REDIRECT_URL")]
+    pub redirect_url: Option<String>,
+    /// Base URL of the MAL API
+    #[clap(
+        long = "mal-api-url",
+        default_value = "https://api.myanimelist.net/v2",
+        env = "MAL_API_URL"
+    )]
+    pub api_url: String,
+    /// Base URL of the MAL OAuth2 endpoints
+    #[clap(
+        long = "mal-auth-url",
+        default_value = "https://myanimelist.net/v1/oauth2",
+        env = "MAL_AUTH_URL"
+    )]
+    pub auth_url: String,
+    /// How often to pull list changes from MAL, eg. `30m` or `1h`
+    #[clap(
+        long = "mal-sync-interval",
+        default_value = "30m",
+        env = "MAL_SYNC_INTERVAL",
+        value_parser = duration_str::parse
+    )]
+    pub sync_interval: Duration,
+}
+
+#[derive(Debug, Clone, Args)]
+#[clap(next_help_heading = "Library options")]
+pub struct LibraryConfig {
+    /// How often to fill in missing series details, like the MAL id, from their sources, eg. `12h` or `1d`
+    #[clap(
+        long = "enrich-interval",
+        default_value = "1d",
+        env = "ENRICH_INTERVAL",
+        value_parser = duration_str::parse
+    )]
+    pub enrich_interval: Duration,
+    /// After how many failed fetches in a row a source is marked as broken.
+    ///
+    /// Broken sources are skipped by background jobs until a fetch of them succeeds again.
+    #[clap(
+        long = "source-broken-after",
+        default_value = "5",
+        env = "SOURCE_BROKEN_AFTER"
+    )]
+    pub source_broken_after: u32,
+    /// How long to wait for a site to return a series' info before giving up on it, eg. `15s`
+    #[clap(
+        long = "source-timeout",
+        default_value = "15s",
+        env = "SOURCE_TIMEOUT",
+        value_parser = duration_str::parse
+    )]
+    pub source_timeout: Duration,
+    /// How long fetched series info is reused for when showing a series, eg. `5m`.
+    ///
+    /// Set to `0s` to always fetch it again.
+    #[clap(
+        long = "source-cache-ttl",
+        default_value = "5m",
+        env = "SOURCE_CACHE_TTL",
+        value_parser = duration_str::parse
+    )]
+    pub source_cache_ttl: Duration,
+    /// Which providers to trust first when merging the info of a series' sources.
+    ///
+    /// Comma separated list of `myanimelist` and site names. Providers that aren't listed come last.
+    #[clap(
+        long = "provider-precedence",
+        default_value = "myanimelist,allanime,aniwatch,aniwave",
+        env = "PROVIDER_PRECEDENCE",
+        value_delimiter = ','
+    )]
+    pub provider_precedence: Vec<String>,
+}
+
+#[derive(Debug, Clone, Args)]
+#[clap(next_help_heading = "Stream options")]
+pub struct StreamConfig {
+    /// Secret the links to `/v1/stream/proxy` are signed with.
+    ///
+    /// If not specified, a random one is made on startup, so handed out links stop working when the server restarts.
+    #[clap(long = "stream-proxy-secret", env = "STREAM_PROXY_SECRET")]
+    pub proxy_secret: Option<String>,
+    /// How long links to `/v1/stream/proxy` work for, eg. `6h`.
+    ///
+    /// Links to streams that expire sooner stop working along with them.
+    #[clap(
+        long = "stream-proxy-ttl",
+        default_value = "6h",
+        env = "STREAM_PROXY_TTL",
+        value_parser = duration_str::parse
+    )]
+    pub proxy_ttl: Duration,
 }
 
 #[derive(Debug, Clone, Parser)]
@@ -76,6 +261,18 @@ struct Cli {
 
     #[command(flatten)]
     database: DatabaseConfig,
+
+    #[command(flatten)]
+    auth: AuthConfig,
+
+    #[command(flatten)]
+    mal: MalConfig,
+
+    #[command(flatten)]
+    library: LibraryConfig,
+
+    #[command(flatten)]
+    stream: StreamConfig,
 }
 
 #[test]
@@ -0,0 +1,496 @@
+use std::collections::HashSet;
+
+use anyhow::{anyhow, bail, Context, Result};
+use log::{debug, trace, warn};
+use migration::{Migrator, MigratorTrait};
+use sea_orm::{
+    prelude::*,
+    sea_query::{value::sea_value_to_json_value, OnConflict},
+    ActiveValue, ColumnType, DatabaseTransaction, IntoActiveModel, Iterable, PrimaryKeyToColumn,
+    QueryOrder, SqlErr, TransactionTrait,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value as Json};
+use utoipa::ToSchema;
+
+#[cfg(test)]
+mod tests;
+
+/// Version of the backup document format itself.
+///
+/// The layout of the tables is versioned separately through [`Backup::schema_version`].
+pub const BACKUP_FORMAT_VERSION: u32 = 1;
+
+/// Oldest schema backups can be restored from.
+///
+/// Before it, list progress was kept in columns of `series` that don't exist anymore,
+/// and there were no users, so restoring would silently lose data.
+const OLDEST_SUPPORTED_SCHEMA: &str = "m20231024_151203_add_users";
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Backup {
+    pub version: u32,
+    /// Name of the last migration the database had applied when the backup was made
+    pub schema_version: String,
+    pub created_at: String,
+    /// Rows of every table, keyed by column name
+    #[schema(value_type = Object)]
+    pub tables: Map<String, Json>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum RestoreMode {
+    /// Keep existing rows, overwriting those with the same primary key as a row in the backup.
+    ///
+    /// Rows that clash with an existing row on any other unique constraint are skipped.
+    Merge,
+    /// Delete everything before loading the backup
+    Replace,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TableRestoreReport {
+    pub table: String,
+    pub deleted: u64,
+    pub inserted: u64,
+    pub updated: u64,
+    /// Rows of a merge that clashed with a unique constraint of an existing row, left out
+    pub skipped: Vec<SkippedRow>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SkippedRow {
+    /// Index of the row in the table of the backup
+    pub row: usize,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RestoreReport {
+    pub mode: RestoreMode,
+    pub schema_version: String,
+    pub tables: Vec<TableRestoreReport>,
+}
+
+fn current_schema_version() -> String {
+    Migrator::migrations()
+        .last()
+        .map(|x| x.name().to_string())
+        .unwrap_or_default()
+}
+
+pub async fn create_backup(db: &DatabaseConnection) -> Result<Backup> {
+    let mut tables = Map::new();
+
+    // Keep in dependency order, parents before children.
+    // Sessions are left out on purpose, restoring a backup logs everyone out.
+    dump_table::<entity::users::Entity, entity::users::ActiveModel>(db, &mut tables).await?;
+    dump_table::<entity::series::Entity, entity::series::ActiveModel>(db, &mut tables).await?;
+    dump_table::<entity::series_sources::Entity, entity::series_sources::ActiveModel>(
+        db,
+        &mut tables,
+    )
+    .await?;
+    dump_table::<entity::user_settings::Entity, entity::user_settings::ActiveModel>(
+        db,
+        &mut tables,
+    )
+    .await?;
+    dump_table::<entity::api_tokens::Entity, entity::api_tokens::ActiveModel>(db, &mut tables)
+        .await?;
+    dump_table::<entity::library_entries::Entity, entity::library_entries::ActiveModel>(
+        db,
+        &mut tables,
+    )
+    .await?;
+    dump_table::<entity::mal_auth::Entity, entity::mal_auth::ActiveModel>(db, &mut tables).await?;
+    dump_table::<entity::skip_markers::Entity, entity::skip_markers::ActiveModel>(db, &mut tables)
+        .await?;
+
+    Ok(Backup {
+        version: BACKUP_FORMAT_VERSION,
+        schema_version: current_schema_version(),
+        created_at: chrono::Utc::now().to_rfc3339(),
+        tables,
+    })
+}
+
+/// Check that a backup can be loaded into the database as it is now
+pub async fn validate_backup(
+    db: &DatabaseConnection,
+    backup: &Backup,
+    mode: RestoreMode,
+) -> Result<()> {
+    if backup.version != BACKUP_FORMAT_VERSION {
+        bail!(
+            "Unsupported backup format version {} (expected {})",
+            backup.version,
+            BACKUP_FORMAT_VERSION
+        );
+    }
+
+    let pending = Migrator::get_pending_migrations(db).await?;
+    if !pending.is_empty() {
+        bail!("Database has pending migrations, refusing to restore");
+    }
+
+    let known_versions = Migrator::migrations()
+        .iter()
+        .map(|x| x.name().to_string())
+        .collect::<Vec<_>>();
+    let Some(backup_version) = known_versions
+        .iter()
+        .position(|x| x == &backup.schema_version)
+    else {
+        bail!(
+            "Backup was made with an unknown schema version {:?}, the current one is {:?}",
+            backup.schema_version,
+            current_schema_version(),
+        );
+    };
+    let oldest_version = known_versions
+        .iter()
+        .position(|x| x == OLDEST_SUPPORTED_SCHEMA)
+        .unwrap_or_default();
+    if backup_version < oldest_version {
+        bail!(
+            "Backup was made with schema version {:?}, which is older than the oldest supported one {:?}",
+            backup.schema_version,
+            OLDEST_SUPPORTED_SCHEMA,
+        );
+    }
+
+    let has_users = backup
+        .tables
+        .get(&table_name::<entity::users::Entity>())
+        .and_then(Json::as_array)
+        .is_some_and(|x| !x.is_empty());
+    if mode == RestoreMode::Replace && !has_users {
+        bail!("Backup has no users, replacing the database with it would leave it without any");
+    }
+
+    Ok(())
+}
+
+/// Load a backup in a single transaction.
+///
+/// Backups made with an older schema are accepted, columns they don't have get their default values.
+/// Backups from a newer schema are rejected since there is no way to know what the extra data means.
+/// So are ones that would leave the database without users, which would let anyone register as admin.
+pub async fn restore_backup(
+    db: &DatabaseConnection,
+    backup: Backup,
+    mode: RestoreMode,
+) -> Result<RestoreReport> {
+    validate_backup(db, &backup, mode).await?;
+
+    let txn = db.begin().await?;
+
+    let cleared = match mode {
+        // Children before parents
+        RestoreMode::Replace => vec![
+            clear_table::<entity::skip_markers::Entity>(&txn).await?,
+            clear_table::<entity::mal_auth::Entity>(&txn).await?,
+            clear_table::<entity::library_entries::Entity>(&txn).await?,
+            clear_table::<entity::api_tokens::Entity>(&txn).await?,
+            clear_table::<entity::user_settings::Entity>(&txn).await?,
+            clear_table::<entity::series_sources::Entity>(&txn).await?,
+            clear_table::<entity::series::Entity>(&txn).await?,
+            clear_table::<entity::sessions::Entity>(&txn).await?,
+            clear_table::<entity::users::Entity>(&txn).await?,
+        ],
+        RestoreMode::Merge => vec![],
+    };
+
+    let mut reports = vec![
+        restore_table::<entity::users::Entity, entity::users::ActiveModel>(
+            &txn,
+            &backup.tables,
+            mode,
+        )
+        .await?,
+        restore_table::<entity::series::Entity, entity::series::ActiveModel>(
+            &txn,
+            &backup.tables,
+            mode,
+        )
+        .await?,
+        restore_table::<entity::series_sources::Entity, entity::series_sources::ActiveModel>(
+            &txn,
+            &backup.tables,
+            mode,
+        )
+        .await?,
+        restore_table::<entity::user_settings::Entity, entity::user_settings::ActiveModel>(
+            &txn,
+            &backup.tables,
+            mode,
+        )
+        .await?,
+        restore_table::<entity::api_tokens::Entity, entity::api_tokens::ActiveModel>(
+            &txn,
+            &backup.tables,
+            mode,
+        )
+        .await?,
+        restore_table::<entity::library_entries::Entity, entity::library_entries::ActiveModel>(
+            &txn,
+            &backup.tables,
+            mode,
+        )
+        .await?,
+        restore_table::<entity::mal_auth::Entity, entity::mal_auth::ActiveModel>(
+            &txn,
+            &backup.tables,
+            mode,
+        )
+        .await?,
+        restore_table::<entity::skip_markers::Entity, entity::skip_markers::ActiveModel>(
+            &txn,
+            &backup.tables,
+            mode,
+        )
+        .await?,
+    ];
+
+    for report in &mut reports {
+        if let Some(cleared) = cleared.iter().find(|x| x.table == report.table) {
+            report.deleted = cleared.deleted;
+        }
+    }
+
+    // Dropping the transaction rolls it back
+    if entity::users::Entity::find().count(&txn).await? == 0 {
+        bail!("Backup has no users, restoring it would leave the database without any");
+    }
+
+    txn.commit().await?;
+
+    debug!("Restored backup: {:?}", reports);
+
+    Ok(RestoreReport {
+        mode,
+        schema_version: backup.schema_version,
+        tables: reports,
+    })
+}
+
+fn table_name<E: EntityTrait>() -> String {
+    E::default().table_name().to_string()
+}
+
+async fn dump_table<E, A>(db: &DatabaseConnection, tables: &mut Map<String, Json>) -> Result<()>
+where
+    E: EntityTrait,
+    A: ActiveModelTrait<Entity = E>,
+    E::Model: IntoActiveModel<A>,
+{
+    let mut query = E::find();
+    for key in E::PrimaryKey::iter() {
+        query = query.order_by_asc(key.into_column());
+    }
+
+    let rows = query
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|model| {
+            let model = model.into_active_model();
+
+            E::Column::iter()
+                .filter_map(|column| {
+                    let value = model.get(column).into_value()?;
+
+                    Some((column.as_str().to_string(), sea_value_to_json_value(&value)))
+                })
+                .collect::<Map<_, _>>()
+        })
+        .map(Json::Object)
+        .collect::<Vec<_>>();
+
+    trace!("Dumped {} rows from {}", rows.len(), table_name::<E>());
+
+    tables.insert(table_name::<E>(), Json::Array(rows));
+
+    Ok(())
+}
+
+async fn clear_table<E: EntityTrait>(txn: &DatabaseTransaction) -> Result<TableRestoreReport> {
+    let res = E::delete_many().exec(txn).await?;
+
+    Ok(TableRestoreReport {
+        table: table_name::<E>(),
+        deleted: res.rows_affected,
+        ..Default::default()
+    })
+}
+
+async fn restore_table<E, A>(
+    txn: &DatabaseTransaction,
+    tables: &Map<String, Json>,
+    mode: RestoreMode,
+) -> Result<TableRestoreReport>
+where
+    E: EntityTrait,
+    A: ActiveModelTrait<Entity = E> + ActiveModelBehavior + Send,
+    E::Model: IntoActiveModel<A>,
+{
+    let table = table_name::<E>();
+    let mut report = TableRestoreReport {
+        table: table.clone(),
+        ..Default::default()
+    };
+
+    let Some(rows) = tables.get(&table) else {
+        warn!("Backup has no data for table {table:?}");
+        return Ok(report);
+    };
+    let rows = rows
+        .as_array()
+        .ok_or_else(|| anyhow!("Rows of table {table:?} are not a list"))?;
+
+    let primary_key = E::PrimaryKey::iter()
+        .map(PrimaryKeyToColumn::into_column)
+        .collect::<Vec<_>>();
+
+    let existing = match mode {
+        RestoreMode::Replace => HashSet::new(),
+        RestoreMode::Merge => E::find()
+            .all(txn)
+            .await?
+            .into_iter()
+            .map(|model| {
+                let model = model.into_active_model();
+                primary_key
+                    .iter()
+                    .map(|x| format!("{:?}", model.get(*x).into_value()))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<HashSet<_>>(),
+    };
+
+    for (i, row) in rows.iter().enumerate() {
+        let row = row
+            .as_object()
+            .ok_or_else(|| anyhow!("Row {i} of table {table:?} is not an object"))?;
+
+        let mut model = A::default();
+        let mut columns = vec![];
+        for column in E::Column::iter() {
+            let Some(json) = row.get(column.as_str()) else {
+                continue;
+            };
+
+            let value = json_to_value(column.def().get_column_type(), json).with_context(|| {
+                format!("Invalid value for {table}.{} in row {i}", column.as_str())
+            })?;
+
+            model.set(column, value);
+            columns.push(column);
+        }
+
+        if primary_key
+            .iter()
+            .any(|x| !matches!(model.get(*x), ActiveValue::Set(_)))
+        {
+            bail!("Row {i} of table {table:?} is missing its primary key");
+        }
+        let key = primary_key
+            .iter()
+            .map(|x| format!("{:?}", model.get(*x).into_value()))
+            .collect::<Vec<_>>();
+
+        let updated_columns = columns
+            .into_iter()
+            .filter(|x| !primary_key.iter().any(|key| key.as_str() == x.as_str()))
+            .collect::<Vec<_>>();
+
+        let mut insert = E::insert(model);
+        if mode == RestoreMode::Merge && !updated_columns.is_empty() {
+            insert = insert.on_conflict(
+                OnConflict::columns(primary_key.clone())
+                    .update_columns(updated_columns)
+                    .to_owned(),
+            );
+        }
+
+        if mode == RestoreMode::Merge {
+            // Each row in its own savepoint, so a conflicting row doesn't abort the whole restore
+            let savepoint = txn.begin().await?;
+            match insert.exec_without_returning(&savepoint).await {
+                Ok(_) => savepoint.commit().await?,
+                Err(e)
+                    if e.sql_err()
+                        .is_some_and(|x| matches!(x, SqlErr::UniqueConstraintViolation(_))) =>
+                {
+                    savepoint.rollback().await?;
+                    debug!("Skipping row {i} of table {table:?}: {e}");
+                    report.skipped.push(SkippedRow {
+                        row: i,
+                        reason: e.to_string(),
+                    });
+                    continue;
+                }
+                Err(e) => return Err(e.into()),
+            }
+        } else {
+            insert.exec_without_returning(txn).await?;
+        }
+
+        if existing.contains(&key) {
+            report.updated += 1;
+        } else {
+            report.inserted += 1;
+        }
+    }
+
+    Ok(report)
+}
+
+fn json_to_value(column_type: &ColumnType, json: &Json) -> Result<Value> {
+    let value = match column_type {
+        ColumnType::TinyInteger | ColumnType::SmallInteger | ColumnType::Integer => {
+            Value::Int(json_as(json, |x| {
+                x.as_i64().and_then(|x| i32::try_from(x).ok())
+            })?)
+        }
+        ColumnType::BigInteger => Value::BigInt(json_as(json, Json::as_i64)?),
+        ColumnType::Float | ColumnType::Double => Value::Double(json_as(json, Json::as_f64)?),
+        ColumnType::Boolean => Value::Bool(json_as(json, |x| {
+            x.as_bool().or_else(|| x.as_i64().map(|x| x != 0))
+        })?),
+        ColumnType::Char(_)
+        | ColumnType::String(_)
+        | ColumnType::Text
+        | ColumnType::Date
+        | ColumnType::DateTime
+        | ColumnType::Timestamp
+        | ColumnType::TimestampWithTimeZone => Value::String(json_as(json, |x| {
+            x.as_str().map(|x| Box::new(x.to_string()))
+        })?),
+        ColumnType::Json | ColumnType::JsonBinary => {
+            Value::Json((!json.is_null()).then(|| Box::new(json.clone())))
+        }
+        column_type => bail!("Unsupported column type {column_type:?}"),
+    };
+
+    Ok(value)
+}
+
+fn json_as<T, F>(json: &Json, f: F) -> Result<Option<T>>
+where
+    F: FnOnce(&Json) -> Option<T>,
+{
+    if json.is_null() {
+        return Ok(None);
+    }
+
+    f(json)
+        .map(Some)
+        .ok_or_else(|| anyhow!("Unexpected value {json}"))
+}
@@ -0,0 +1,221 @@
+//! Backing up and restoring a migrated in-memory database
+
+use migration::{Migrator, MigratorTrait};
+use sea_orm::{prelude::*, ActiveValue, DatabaseConnection};
+use serde_json::{json, Value as Json};
+
+use super::{
+    create_backup, restore_backup, validate_backup, Backup, RestoreMode, RestoreReport,
+    TableRestoreReport,
+};
+use crate::test_util::database;
+
+async fn user(db: &DatabaseConnection, username: &str) -> entity::users::Model {
+    entity::users::ActiveModel {
+        username: ActiveValue::Set(username.to_string()),
+        password_hash: ActiveValue::Set("hash".to_string()),
+        is_admin: ActiveValue::Set(true),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap()
+}
+
+async fn series(db: &DatabaseConnection, name: &str) -> entity::series::Model {
+    entity::series::ActiveModel {
+        name: ActiveValue::Set(name.to_string()),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap()
+}
+
+async fn source(
+    db: &DatabaseConnection,
+    series_id: i32,
+    series_site_id: &str,
+) -> entity::series_sources::Model {
+    entity::series_sources::ActiveModel {
+        for_series_id: ActiveValue::Set(series_id),
+        series_site: ActiveValue::Set("aniwatch".to_string()),
+        series_site_id: ActiveValue::Set(series_site_id.to_string()),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap()
+}
+
+/// A library of one user with one series that has one source
+async fn library() -> DatabaseConnection {
+    let db = database().await;
+
+    user(&db, "admin").await;
+    let series = series(&db, "Sousou no Frieren").await;
+    source(&db, series.id, "frieren-18542").await;
+
+    db
+}
+
+fn rows<'a>(backup: &'a Backup, table: &str) -> &'a Vec<Json> {
+    backup.tables[table].as_array().unwrap()
+}
+
+fn report<'a>(report: &'a RestoreReport, table: &str) -> &'a TableRestoreReport {
+    report.tables.iter().find(|x| x.table == table).unwrap()
+}
+
+#[tokio::test]
+async fn round_trips_through_an_empty_database() {
+    let db = library().await;
+    let backup = create_backup(&db).await.unwrap();
+    assert_eq!(rows(&backup, "series").len(), 1);
+
+    let restored = database().await;
+    let restore_report = restore_backup(&restored, backup.clone(), RestoreMode::Replace)
+        .await
+        .unwrap();
+
+    assert_eq!(report(&restore_report, "users").inserted, 1);
+    assert_eq!(report(&restore_report, "series_sources").inserted, 1);
+    // Every column of every row, timestamps included
+    let again = create_backup(&restored).await.unwrap();
+    assert_eq!(again.tables, backup.tables);
+}
+
+#[tokio::test]
+async fn replaces_everything_that_was_there() {
+    let db = library().await;
+    let backup = create_backup(&db).await.unwrap();
+    series(&db, "Mahoutsukai no Yome").await;
+
+    let restore_report = restore_backup(&db, backup, RestoreMode::Replace)
+        .await
+        .unwrap();
+
+    assert_eq!(report(&restore_report, "series").deleted, 2);
+    assert_eq!(report(&restore_report, "series").inserted, 1);
+    let names = entity::series::Entity::find()
+        .all(&db)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|x| x.name)
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["Sousou no Frieren"]);
+}
+
+#[tokio::test]
+async fn merges_around_unique_conflicts() {
+    let db = library().await;
+    let mut backup = create_backup(&db).await.unwrap();
+
+    let sources = backup.tables["series_sources"].as_array_mut().unwrap();
+    let mut existing = sources[0].clone();
+    existing["verified_name"] = json!("Sousou no Frieren");
+    // Same site and id as the existing source, under another primary key
+    let mut conflicting = sources[0].clone();
+    conflicting["id"] = json!(100);
+    let mut new = sources[0].clone();
+    new["id"] = json!(101);
+    new["series_site_id"] = json!("frieren-2");
+    *sources = vec![existing, conflicting, new];
+
+    let restore_report = restore_backup(&db, backup, RestoreMode::Merge)
+        .await
+        .unwrap();
+
+    let sources_report = report(&restore_report, "series_sources");
+    assert_eq!(sources_report.updated, 1);
+    assert_eq!(sources_report.inserted, 1);
+    assert_eq!(sources_report.skipped.len(), 1);
+    assert_eq!(sources_report.skipped[0].row, 1);
+    assert!(sources_report.skipped[0].reason.contains("UNIQUE"));
+
+    let sources = entity::series_sources::Entity::find()
+        .all(&db)
+        .await
+        .unwrap();
+    let ids = sources.iter().map(|x| x.id).collect::<Vec<_>>();
+    assert_eq!(ids, [1, 101]);
+    assert_eq!(
+        sources[0].verified_name.as_deref(),
+        Some("Sousou no Frieren")
+    );
+}
+
+#[tokio::test]
+async fn refuses_backups_from_before_users() {
+    let db = library().await;
+    let mut backup = create_backup(&db).await.unwrap();
+    backup.schema_version = Migrator::migrations()[0].name().to_string();
+
+    for mode in [RestoreMode::Merge, RestoreMode::Replace] {
+        let error = restore_backup(&db, backup.clone(), mode).await.unwrap_err();
+
+        assert!(
+            error
+                .to_string()
+                .contains("older than the oldest supported"),
+            "{error}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn refuses_unknown_schemas_and_formats() {
+    let db = library().await;
+    let backup = create_backup(&db).await.unwrap();
+
+    let mut newer = backup.clone();
+    newer.schema_version = "m29991231_000000_from_the_future".to_string();
+    assert!(validate_backup(&db, &newer, RestoreMode::Merge)
+        .await
+        .is_err());
+
+    let mut other_format = backup;
+    other_format.version += 1;
+    assert!(validate_backup(&db, &other_format, RestoreMode::Merge)
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn refuses_replacing_with_a_backup_without_users() {
+    let db = library().await;
+    let mut backup = create_backup(&db).await.unwrap();
+    backup.tables["users"] = json!([]);
+
+    let error = restore_backup(&db, backup, RestoreMode::Replace)
+        .await
+        .unwrap_err();
+
+    assert!(error.to_string().contains("no users"), "{error}");
+    // Nothing was deleted
+    assert_eq!(entity::users::Entity::find().count(&db).await.unwrap(), 1);
+    assert_eq!(entity::series::Entity::find().count(&db).await.unwrap(), 1);
+}
+
+#[tokio::test]
+async fn refuses_merges_that_leave_no_users() {
+    let backup = create_backup(&library().await).await.unwrap();
+    let mut without_users = backup.clone();
+    without_users.tables["users"] = json!([]);
+    let db = database().await;
+
+    let error = restore_backup(&db, without_users, RestoreMode::Merge)
+        .await
+        .unwrap_err();
+
+    assert!(error.to_string().contains("no users"), "{error}");
+    // Rolled back as a whole
+    assert_eq!(entity::series::Entity::find().count(&db).await.unwrap(), 0);
+
+    // Fine once the backup brings its users along
+    restore_backup(&db, backup, RestoreMode::Merge)
+        .await
+        .unwrap();
+    assert_eq!(entity::users::Entity::find().count(&db).await.unwrap(), 1);
+}
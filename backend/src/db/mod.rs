@@ -6,6 +6,9 @@ use sea_orm::{ConnectOptions, Database, DatabaseConnection};
 
 use crate::config::CONFIG;
 
+pub mod backup;
+pub mod snapshot;
+
 #[derive(Debug, Clone)]
 pub struct AppDb {
     conn: DatabaseConnection,
@@ -37,6 +40,12 @@ impl AppDb {
         self.conn.clone()
     }
 
+    /// An already connected database, like the in-memory ones of tests
+    #[cfg(test)]
+    pub fn from_connection(conn: DatabaseConnection) -> Self {
+        Self { conn }
+    }
+
     pub async fn init(&self) -> anyhow::Result<()> {
         trace!("Initializing database");
 
@@ -0,0 +1,192 @@
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveDateTime, SubsecRound, Utc};
+use log::{debug, error, info, warn};
+use sea_orm::{ConnectionTrait, DatabaseConnection, DbBackend, Statement};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::config::CONFIG;
+
+const TIMESTAMP_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+const EXTENSION: &str = "sqlite";
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Snapshot {
+    pub file_name: String,
+    #[schema(value_type = String)]
+    pub path: PathBuf,
+    pub size: u64,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Directory snapshots are written to, if they are enabled
+pub fn snapshot_dir() -> Option<&'static Path> {
+    CONFIG.database.snapshot_dir.as_deref()
+}
+
+fn file_name(created_at: DateTime<Utc>) -> String {
+    format!(
+        "{}-{}.{}",
+        CONFIG.database.snapshot_prefix,
+        created_at.format(TIMESTAMP_FORMAT),
+        EXTENSION,
+    )
+}
+
+/// Get the time a snapshot was made from its file name.
+///
+/// Files that don't follow the naming scheme are not snapshots and are never touched.
+fn parse_file_name(file_name: &str) -> Option<DateTime<Utc>> {
+    let timestamp = file_name
+        .strip_prefix(&CONFIG.database.snapshot_prefix)?
+        .strip_prefix('-')?
+        .strip_suffix(EXTENSION)?
+        .strip_suffix('.')?;
+
+    NaiveDateTime::parse_from_str(timestamp, TIMESTAMP_FORMAT)
+        .ok()
+        .map(|x| x.and_utc())
+}
+
+/// List snapshots in the snapshot directory, newest first
+pub async fn list_snapshots(dir: &Path) -> Result<Vec<Snapshot>> {
+    let mut snapshots = vec![];
+
+    let mut entries = match tokio::fs::read_dir(dir).await {
+        Ok(x) => x,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(snapshots),
+        Err(e) => return Err(e).context("Failed to read snapshot directory"),
+    };
+
+    while let Some(entry) = entries.next_entry().await? {
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        let Some(created_at) = parse_file_name(&file_name) else {
+            continue;
+        };
+        let metadata = entry.metadata().await?;
+        if !metadata.is_file() {
+            continue;
+        }
+
+        snapshots.push(Snapshot {
+            file_name,
+            path: entry.path(),
+            size: metadata.len(),
+            created_at,
+        });
+    }
+
+    snapshots.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+
+    Ok(snapshots)
+}
+
+/// Write a consistent copy of the database into the snapshot directory.
+///
+/// The copy is made under a temporary name and renamed once complete,
+/// so a crash mid-way never leaves a broken file that looks like a snapshot.
+pub async fn create_snapshot(db: &DatabaseConnection, dir: &Path) -> Result<Snapshot> {
+    tokio::fs::create_dir_all(dir)
+        .await
+        .context("Failed to create snapshot directory")?;
+
+    // File names only have second precision
+    let created_at = Utc::now().trunc_subsecs(0);
+    let file_name = file_name(created_at);
+    let path = dir.join(&file_name);
+    let tmp_path = dir.join(format!(".{file_name}.tmp"));
+
+    // `VACUUM INTO` refuses to overwrite files
+    if tokio::fs::try_exists(&tmp_path).await.unwrap_or(false) {
+        tokio::fs::remove_file(&tmp_path).await?;
+    }
+
+    db.execute(Statement::from_sql_and_values(
+        DbBackend::Sqlite,
+        "VACUUM INTO ?",
+        [tmp_path.to_string_lossy().to_string().into()],
+    ))
+    .await
+    .context("Failed to write snapshot")?;
+
+    tokio::fs::rename(&tmp_path, &path).await?;
+
+    let size = tokio::fs::metadata(&path).await?.len();
+
+    info!("Wrote database snapshot to {path:?}");
+
+    Ok(Snapshot {
+        file_name,
+        path,
+        size,
+        created_at,
+    })
+}
+
+/// Delete the oldest snapshots so that at most the configured amount is left
+pub async fn rotate_snapshots(dir: &Path) -> Result<Vec<Snapshot>> {
+    let keep = CONFIG.database.snapshot_keep;
+    if keep == 0 {
+        return Ok(vec![]);
+    }
+
+    let mut removed = vec![];
+    for snapshot in list_snapshots(dir).await?.into_iter().skip(keep) {
+        debug!("Removing old snapshot {:?}", snapshot.path);
+        tokio::fs::remove_file(&snapshot.path)
+            .await
+            .with_context(|| format!("Failed to remove snapshot {:?}", snapshot.path))?;
+        removed.push(snapshot);
+    }
+
+    Ok(removed)
+}
+
+/// Make a snapshot every configured interval, if snapshots are enabled.
+///
+/// The schedule carries over restarts: the first snapshot is made when the newest existing one gets too old.
+pub fn spawn_periodic_snapshots(db: DatabaseConnection) {
+    let Some(dir) = snapshot_dir() else {
+        debug!("No snapshot directory configured, not making snapshots");
+        return;
+    };
+    let interval = CONFIG.database.snapshot_interval;
+
+    info!("Making database snapshots in {dir:?} every {interval:?}");
+
+    tokio::spawn(async move {
+        let newest = match list_snapshots(dir).await {
+            Ok(x) => x.into_iter().next(),
+            Err(e) => {
+                warn!("Failed to list existing snapshots: {e:?}");
+                None
+            }
+        };
+        let since_newest = newest
+            .and_then(|x| (Utc::now() - x.created_at).to_std().ok())
+            .unwrap_or(interval);
+
+        tokio::time::sleep(interval.saturating_sub(since_newest)).await;
+
+        let mut ticker = tokio::time::interval(interval.max(Duration::from_secs(1)));
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+
+            if let Err(e) = create_snapshot(&db, dir).await {
+                error!("Failed to make database snapshot: {e:?}");
+                continue;
+            }
+
+            if let Err(e) = rotate_snapshots(dir).await {
+                error!("Failed to remove old database snapshots: {e:?}");
+            }
+        }
+    });
+}
@@ -0,0 +1,479 @@
+//! Checks that the sources of each series actually point at that series.
+//!
+//! Sources get linked by slug or id, so it's easy to end up with one that points at a
+//! different season or a different show with a similar name. Each source's info is
+//! fetched and compared with the series and with the other sources.
+//!
+//! Series are checked a page at a time, since fetching the sources of the whole
+//! library takes longer than a request is allowed to.
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    time::Duration,
+};
+
+use anyhow::Result;
+use chrono::Utc;
+use futures::StreamExt;
+use log::{debug, trace};
+use sea_orm::{
+    prelude::*, DatabaseConnection, LoaderTrait, PaginatorTrait, QueryOrder, QuerySelect,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::{
+    episodes,
+    health::{self, SourceError, SourceErrorKind},
+};
+use crate::metadata::AnimeInfo;
+
+#[cfg(test)]
+mod tests;
+
+/// How many series are checked at once when the caller doesn't say
+pub const DEFAULT_PAGE_SIZE: u64 = 20;
+
+/// How many sources to fetch at the same time
+const CONCURRENCY: usize = 8;
+
+/// How long to spend fetching sources, well within the minute the server gives a request
+const FETCH_BUDGET: Duration = Duration::from_secs(40);
+
+/// Sources of an airing series can be an episode or two apart while they catch up
+const EPISODE_COUNT_TOLERANCE: usize = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum IssueKind {
+    /// A source reports a different MAL id than the series has
+    MalIdMismatch,
+    /// The series has no MAL id and its sources don't agree on one
+    MalIdConflict,
+    /// The series has no MAL id but its sources agree on one
+    MalIdMissing,
+    /// Several series have the same MAL id
+    DuplicateMalId,
+    /// A source has a lot more or fewer episodes than the others
+    EpisodeCountMismatch,
+    /// None of a source's names look like the series'
+    TitleMismatch,
+    /// A source's info couldn't be fetched
+    SourceUnreachable,
+    /// The series has no sources, or none of them could be fetched
+    NoWorkingSources,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ConsistencyIssue {
+    pub kind: IssueKind,
+    pub series_ids: Vec<i32>,
+    /// Sources the issue is about, if any
+    pub source_ids: Vec<i32>,
+    pub message: String,
+    /// What would most likely fix it
+    pub suggestion: String,
+}
+
+/// What a source reported about its series
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SourceCheck {
+    pub source_id: i32,
+    pub series_id: i32,
+    pub site: String,
+    pub series_site_id: String,
+    pub name: Option<String>,
+    pub mal_id: Option<u32>,
+    /// Number of distinct episode numbers across all translations, once the episode mapping is applied
+    pub episode_count: Option<usize>,
+    /// Why the source's info couldn't be fetched
+    pub error: Option<String>,
+    pub error_kind: Option<SourceErrorKind>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ConsistencyReport {
+    pub checked_at: String,
+    pub series_checked: usize,
+    pub sources: Vec<SourceCheck>,
+    pub issues: Vec<ConsistencyIssue>,
+}
+
+/// Check a page of the series in the library, or just the one given.
+///
+/// Returns the report and how many series there are to check in total.
+/// Sources that weren't fetched before the time ran out are reported as timed out.
+pub async fn check(
+    db: &DatabaseConnection,
+    series_id: Option<i32>,
+    offset: u64,
+    limit: u64,
+) -> Result<(ConsistencyReport, u64)> {
+    let mut select = entity::series::Entity::find().order_by_asc(entity::series::Column::Id);
+    if let Some(series_id) = series_id {
+        select = select.filter(entity::series::Column::Id.eq(series_id));
+    }
+    let total = select.clone().count(db).await?;
+    let series = select.offset(offset).limit(limit).all(db).await?;
+    // Loaded separately, a limit on a join would count sources instead of series
+    let sources = series.load_many(entity::series_sources::Entity, db).await?;
+    let series = series.into_iter().zip(sources).collect::<Vec<_>>();
+
+    trace!(
+        "Checking consistency of {} series from {}",
+        series.len(),
+        offset
+    );
+
+    let all_sources = series
+        .iter()
+        .flat_map(|(_, sources)| sources.iter().cloned())
+        .collect::<Vec<_>>();
+    let mut infos = futures::stream::iter(all_sources)
+        .map(|source| async move {
+            let info = health::fetch_info_cached(db, &source).await.map(|x| x.info);
+
+            (source.id, info)
+        })
+        .buffer_unordered(CONCURRENCY)
+        .take_until(tokio::time::sleep(FETCH_BUDGET))
+        .collect::<HashMap<_, _>>()
+        .await;
+
+    let mut report = ConsistencyReport {
+        checked_at: Utc::now().to_rfc3339(),
+        series_checked: series.len(),
+        sources: vec![],
+        issues: vec![],
+    };
+
+    for (series, series_sources) in &series {
+        let checked = series_sources
+            .iter()
+            .map(|source| {
+                let info = infos.remove(&source.id).unwrap_or_else(|| {
+                    Err(SourceError {
+                        kind: SourceErrorKind::Timeout,
+                        error: anyhow::anyhow!(
+                            "Ran out of time before the source was fetched, check fewer series at once"
+                        ),
+                    })
+                });
+
+                (source, info)
+            })
+            .collect::<Vec<_>>();
+
+        check_series(&mut report, series, &checked);
+    }
+
+    let page_ids = series.iter().map(|(x, _)| x.id).collect::<BTreeSet<_>>();
+    report
+        .issues
+        .extend(duplicate_mal_ids(db, series_id, &page_ids).await?);
+
+    debug!(
+        "Found {} consistency issues in {} series",
+        report.issues.len(),
+        report.series_checked
+    );
+
+    Ok((report, total))
+}
+
+fn check_series(
+    report: &mut ConsistencyReport,
+    series: &entity::series::Model,
+    checked: &[(
+        &entity::series_sources::Model,
+        Result<AnimeInfo, SourceError>,
+    )],
+) {
+    let mut working = vec![];
+
+    for (source, info) in checked {
+        let mut check = SourceCheck {
+            source_id: source.id,
+            series_id: series.id,
+            site: source.series_site.clone(),
+            series_site_id: source.series_site_id.clone(),
+            name: None,
+            mal_id: None,
+            episode_count: None,
+            error: None,
+            error_kind: None,
+        };
+
+        match info {
+            Ok(info) => {
+                check.name = Some(info.name.clone());
+                check.mal_id = info.mal_id;
+                check.episode_count = Some(episode_count(source, info));
+                working.push((*source, info));
+            }
+            Err(e) => {
+                check.error = Some(e.to_string());
+                check.error_kind = Some(e.kind);
+                report.issues.push(ConsistencyIssue {
+                    kind: IssueKind::SourceUnreachable,
+                    series_ids: vec![series.id],
+                    source_ids: vec![source.id],
+                    message: format!(
+                        "Info of {} source {:?} couldn't be fetched: {}",
+                        source.series_site, source.series_site_id, e
+                    ),
+                    suggestion: "Check that the series still exists on the site, and remove the source if it doesn't".to_string(),
+                });
+            }
+        }
+
+        report.sources.push(check);
+    }
+
+    if working.is_empty() {
+        let suggestion = if checked.is_empty() {
+            "Add a source, eg. from a link to the series on a supported site"
+        } else {
+            "Remove the broken sources and add working ones"
+        };
+
+        report.issues.push(ConsistencyIssue {
+            kind: IssueKind::NoWorkingSources,
+            series_ids: vec![series.id],
+            source_ids: checked.iter().map(|(source, _)| source.id).collect(),
+            message: format!("{:?} has no working sources", series.name),
+            suggestion: suggestion.to_string(),
+        });
+
+        return;
+    }
+
+    report.issues.extend(check_mal_ids(series, &working));
+    report.issues.extend(check_episode_counts(series, &working));
+    report.issues.extend(check_titles(series, &working));
+}
+
+fn check_mal_ids(
+    series: &entity::series::Model,
+    working: &[(&entity::series_sources::Model, &AnimeInfo)],
+) -> Vec<ConsistencyIssue> {
+    let reported = working
+        .iter()
+        .filter_map(|(source, info)| Some((*source, info.mal_id?)))
+        .collect::<Vec<_>>();
+
+    if let Some(expected) = series.mal_id {
+        return reported
+            .into_iter()
+            .filter(|(_, mal_id)| i64::from(*mal_id) != i64::from(expected))
+            .map(|(source, mal_id)| ConsistencyIssue {
+                kind: IssueKind::MalIdMismatch,
+                series_ids: vec![series.id],
+                source_ids: vec![source.id],
+                message: format!(
+                    "{} source {:?} reports MAL id {mal_id}, but {:?} has MAL id {expected}",
+                    source.series_site, source.series_site_id, series.name
+                ),
+                suggestion: format!(
+                    "Remove source {} if it's a different season or show, otherwise fix the anime's MAL id",
+                    source.id
+                ),
+            })
+            .collect();
+    }
+
+    let distinct = reported
+        .iter()
+        .map(|(_, mal_id)| *mal_id)
+        .collect::<BTreeSet<_>>();
+
+    match distinct.len() {
+        0 => vec![],
+        1 => vec![ConsistencyIssue {
+            kind: IssueKind::MalIdMissing,
+            series_ids: vec![series.id],
+            source_ids: reported.iter().map(|(source, _)| source.id).collect(),
+            message: format!(
+                "{:?} has no MAL id, but its sources report {}",
+                series.name,
+                distinct.first().expect("there is exactly one")
+            ),
+            suggestion: format!("Fill it in with POST /v1/anime/{}/enrich", series.id),
+        }],
+        _ => vec![ConsistencyIssue {
+            kind: IssueKind::MalIdConflict,
+            series_ids: vec![series.id],
+            source_ids: reported.iter().map(|(source, _)| source.id).collect(),
+            message: format!(
+                "Sources of {:?} report different MAL ids: {}",
+                series.name,
+                reported
+                    .iter()
+                    .map(|(source, mal_id)| format!("{mal_id} (source {})", source.id))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            suggestion: "Set the anime's MAL id and remove the sources that report a different one"
+                .to_string(),
+        }],
+    }
+}
+
+/// Counted after the episode mapping of the source is applied
+fn episode_count(source: &entity::series_sources::Model, info: &AnimeInfo) -> usize {
+    episodes::mapped(source, info)
+        .iter()
+        .map(|x| x.episode_number.to_bits())
+        .collect::<BTreeSet<_>>()
+        .len()
+}
+
+fn check_episode_counts(
+    series: &entity::series::Model,
+    working: &[(&entity::series_sources::Model, &AnimeInfo)],
+) -> Vec<ConsistencyIssue> {
+    let counts = working
+        .iter()
+        .map(|(source, info)| (*source, episode_count(source, info)))
+        .collect::<Vec<_>>();
+
+    let Some(most) = counts.iter().map(|(_, count)| *count).max() else {
+        return vec![];
+    };
+
+    counts
+        .iter()
+        .filter(|(_, count)| most - count > EPISODE_COUNT_TOLERANCE)
+        .map(|(source, count)| ConsistencyIssue {
+            kind: IssueKind::EpisodeCountMismatch,
+            series_ids: vec![series.id],
+            source_ids: vec![source.id],
+            message: format!(
+                "{} source {:?} has {count} episodes of {:?}, while another source has {most}",
+                source.series_site, source.series_site_id, series.name
+            ),
+            suggestion: format!(
+                "Check whether source {} points at a different season or cour, or needs an episode mapping",
+                source.id
+            ),
+        })
+        .collect()
+}
+
+fn check_titles(
+    series: &entity::series::Model,
+    working: &[(&entity::series_sources::Model, &AnimeInfo)],
+) -> Vec<ConsistencyIssue> {
+    working
+        .iter()
+        .filter(|(source, info)| {
+            let names = source_names(info);
+            let references = std::iter::once(series.name.as_str()).chain(
+                working
+                    .iter()
+                    .filter(|(other, _)| other.id != source.id)
+                    .map(|(_, other)| other.name.as_str()),
+            );
+
+            !references
+                .into_iter()
+                .any(|reference| names.iter().any(|name| names_match(name, reference)))
+        })
+        .map(|(source, info)| ConsistencyIssue {
+            kind: IssueKind::TitleMismatch,
+            series_ids: vec![series.id],
+            source_ids: vec![source.id],
+            message: format!(
+                "{} source {:?} is titled {:?}, which doesn't look like {:?}",
+                source.series_site, source.series_site_id, info.name, series.name
+            ),
+            suggestion: format!(
+                "Remove source {} if it's a different season or show",
+                source.id
+            ),
+        })
+        .collect()
+}
+
+fn source_names(info: &AnimeInfo) -> Vec<&str> {
+    std::iter::once(info.name.as_str())
+        .chain(info.alt_names.iter().map(|x| x.name.as_str()))
+        .collect()
+}
+
+/// Lowercased words of a title, split into numbers and everything else
+fn title_words(title: &str) -> (BTreeSet<String>, BTreeSet<String>) {
+    title
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|x| !x.is_empty())
+        .map(str::to_lowercase)
+        .partition(|x| !x.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// Whether two titles are probably of the same season of the same show.
+///
+/// They have to share at least half of their words and mention the same numbers, so that
+/// "Show 2nd Season" doesn't match "Show" but "Show: Subtitle" does.
+fn names_match(a: &str, b: &str) -> bool {
+    let (words_a, numbers_a) = title_words(a);
+    let (words_b, numbers_b) = title_words(b);
+
+    if numbers_a != numbers_b {
+        return false;
+    }
+    if words_a.is_empty() || words_b.is_empty() {
+        return words_a == words_b;
+    }
+
+    let shared = words_a.intersection(&words_b).count();
+
+    shared * 2 >= words_a.len().max(words_b.len())
+}
+
+/// Duplicates are looked for across the whole library, but each group is only reported once:
+/// with the series it involves when checking one, otherwise on the page of its first series
+async fn duplicate_mal_ids(
+    db: &DatabaseConnection,
+    series_id: Option<i32>,
+    page_ids: &BTreeSet<i32>,
+) -> Result<Vec<ConsistencyIssue>> {
+    let mut by_mal_id = BTreeMap::<i32, Vec<entity::series::Model>>::new();
+    for series in entity::series::Entity::find()
+        .filter(entity::series::Column::MalId.is_not_null())
+        .order_by_asc(entity::series::Column::Id)
+        .all(db)
+        .await?
+    {
+        if let Some(mal_id) = series.mal_id {
+            by_mal_id.entry(mal_id).or_default().push(series);
+        }
+    }
+
+    let issues = by_mal_id
+        .into_iter()
+        .filter(|(_, series)| series.len() > 1)
+        .filter(|(_, series)| match series_id {
+            Some(id) => series.iter().any(|x| x.id == id),
+            None => page_ids.contains(&series[0].id),
+        })
+        .map(|(mal_id, series)| ConsistencyIssue {
+            kind: IssueKind::DuplicateMalId,
+            series_ids: series.iter().map(|x| x.id).collect(),
+            source_ids: vec![],
+            message: format!(
+                "MAL id {mal_id} is used by {}",
+                series
+                    .iter()
+                    .map(|x| format!("{:?} ({})", x.name, x.id))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            suggestion: "Move the sources of the duplicates to one of them and remove the rest, or fix the MAL id of the one that's wrong".to_string(),
+        })
+        .collect();
+
+    Ok(issues)
+}
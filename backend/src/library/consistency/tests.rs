@@ -0,0 +1,150 @@
+//! Comparing what sources report with their series
+
+use super::{check_episode_counts, check_mal_ids, names_match, IssueKind};
+use crate::{
+    metadata::{AnimeInfo, EpisodeInfo, SeriesTranslation},
+    test_util::{test_series, test_source},
+};
+
+fn series(mal_id: Option<i32>) -> entity::series::Model {
+    entity::series::Model {
+        mal_id,
+        ..test_series()
+    }
+}
+
+fn source(id: i32) -> entity::series_sources::Model {
+    entity::series_sources::Model {
+        id,
+        series_site_id: format!("frieren-{id}"),
+        ..test_source()
+    }
+}
+
+fn info(mal_id: Option<u32>, episodes: u32) -> AnimeInfo {
+    AnimeInfo {
+        mal_id,
+        name: "Sousou no Frieren".to_string(),
+        episodes: (1..=episodes)
+            .flat_map(|number| {
+                // Both translations of an episode count once
+                [SeriesTranslation::Sub, SeriesTranslation::Dub].map(|translation| EpisodeInfo {
+                    id: format!("{number}"),
+                    title: format!("Episode {number}"),
+                    translation,
+                    episode_number: f64::from(number),
+                    url: String::new(),
+                    description: None,
+                    thumbnail: None,
+                    available_at: None,
+                    is_filler: false,
+                })
+            })
+            .collect(),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn matches_names_of_the_same_season() {
+    assert!(names_match("Sousou no Frieren", "sousou no frieren"));
+    // Subtitles are fine as long as they're short
+    assert!(names_match("Frieren", "Frieren: Remnants"));
+    assert!(!names_match("Frieren", "Frieren: Beyond Journey's End"));
+    assert!(names_match(
+        "Kaguya-sama wa Kokurasetai 2",
+        "Kaguya-sama: Kokurasetai 2"
+    ));
+    // Nothing but numbers
+    assert!(names_match("86", "86"));
+}
+
+#[test]
+fn tells_apart_other_seasons_and_shows() {
+    assert!(!names_match("Spy x Family", "Spy x Family Season 2"));
+    assert!(!names_match(
+        "Mob Psycho 100 Season 2",
+        "Mob Psycho 100 Season 3"
+    ));
+    assert!(!names_match("Kaguya-sama 2", "Kaguya-sama 3"));
+    assert!(!names_match("Sousou no Frieren", "Mahoutsukai no Yome"));
+    assert!(!names_match("86", "Eighty Six"));
+}
+
+#[test]
+fn flags_sources_with_another_mal_id() {
+    let series = series(Some(52991));
+    let (a, b, c) = (source(1), source(2), source(3));
+    let (right, wrong, unknown) = (info(Some(52991), 0), info(Some(56885), 0), info(None, 0));
+
+    let issues = check_mal_ids(&series, &[(&a, &right), (&b, &wrong), (&c, &unknown)]);
+
+    assert_eq!(issues.len(), 1);
+    assert_eq!(issues[0].kind, IssueKind::MalIdMismatch);
+    assert_eq!(issues[0].source_ids, [2]);
+}
+
+#[test]
+fn suggests_the_mal_id_sources_agree_on() {
+    let series = series(None);
+    let (a, b, c) = (source(1), source(2), source(3));
+    let (known, unknown) = (info(Some(52991), 0), info(None, 0));
+
+    let issues = check_mal_ids(&series, &[(&a, &known), (&b, &known), (&c, &unknown)]);
+
+    assert_eq!(issues.len(), 1);
+    assert_eq!(issues[0].kind, IssueKind::MalIdMissing);
+    assert_eq!(issues[0].source_ids, [1, 2]);
+    assert!(issues[0].message.contains("52991"));
+
+    // Nothing to go by
+    assert!(check_mal_ids(&series, &[(&c, &unknown)]).is_empty());
+}
+
+#[test]
+fn flags_sources_that_disagree_on_the_mal_id() {
+    let series = series(None);
+    let (a, b) = (source(1), source(2));
+    let (first, second) = (info(Some(52991), 0), info(Some(56885), 0));
+
+    let issues = check_mal_ids(&series, &[(&a, &first), (&b, &second)]);
+
+    assert_eq!(issues.len(), 1);
+    assert_eq!(issues[0].kind, IssueKind::MalIdConflict);
+    assert_eq!(issues[0].source_ids, [1, 2]);
+}
+
+#[test]
+fn tolerates_sources_catching_up() {
+    let series = series(None);
+    let (a, b) = (source(1), source(2));
+    let (latest, behind) = (info(None, 12), info(None, 10));
+
+    assert!(check_episode_counts(&series, &[(&a, &latest), (&b, &behind)]).is_empty());
+}
+
+#[test]
+fn flags_sources_with_a_lot_fewer_episodes() {
+    let series = series(None);
+    let (a, b, c) = (source(1), source(2), source(3));
+    let (full, half, almost) = (info(None, 28), info(None, 12), info(None, 27));
+
+    let issues = check_episode_counts(&series, &[(&a, &full), (&b, &half), (&c, &almost)]);
+
+    assert_eq!(issues.len(), 1);
+    assert_eq!(issues[0].kind, IssueKind::EpisodeCountMismatch);
+    assert_eq!(issues[0].source_ids, [2]);
+    assert!(issues[0].message.contains("has 12 episodes"));
+    assert!(issues[0].message.contains("has 28"));
+}
+
+#[test]
+fn counts_episodes_after_the_mapping() {
+    let series = series(None);
+    let (a, mut b) = (source(1), source(2));
+    // Only the second cour of a site that lists both
+    b.episode_mapping = Some(serde_json::json!({ "offset": -12.0, "min": 13.0 }));
+    let (first, second) = (info(None, 12), info(None, 24));
+
+    assert!(check_episode_counts(&series, &[(&a, &first), (&b, &second)]).is_empty());
+}
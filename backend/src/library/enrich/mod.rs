@@ -0,0 +1,505 @@
+//! Filling in the details of series from the metadata their sources report.
+//!
+//! Only empty fields get filled, anything already set (eg. by hand) is left alone.
+//! Where each filled value came from is kept in the series' `provenance`.
+
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use chrono::Utc;
+use log::{debug, error, info, trace, warn};
+use sea_orm::{
+    prelude::*,
+    sea_query::{Alias, Expr, Func, IntoCondition, Query as SelectQuery},
+    Condition, DatabaseConnection, QueryOrder,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::health::{self, SourceErrorKind};
+use crate::{config::CONFIG, metadata::AnimeInfo};
+
+#[cfg(test)]
+mod tests;
+
+/// The source a value was taken from
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldProvenance {
+    pub source_id: i32,
+    pub site: String,
+    pub series_site_id: String,
+    pub filled_at: String,
+}
+
+impl FieldProvenance {
+    fn new(source: &entity::series_sources::Model) -> Self {
+        Self {
+            source_id: source.id,
+            site: source.series_site.clone(),
+            series_site_id: source.series_site_id.clone(),
+            filled_at: Utc::now().to_rfc3339(),
+        }
+    }
+}
+
+/// Where the fields of a series that were filled in from its sources came from.
+///
+/// Fields that aren't listed were set by hand.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+#[schema(as = SeriesProvenance)]
+pub struct Provenance {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<FieldProvenance>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<FieldProvenance>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mal_id: Option<FieldProvenance>,
+    /// Keyed by the alternative name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub alt_names: BTreeMap<String, FieldProvenance>,
+}
+
+impl Provenance {
+    pub fn of(series: &entity::series::Model) -> Self {
+        series
+            .provenance
+            .clone()
+            .and_then(|x| serde_json::from_value(x).ok())
+            .unwrap_or_default()
+    }
+
+    /// How it's stored in `series.provenance`, where no provenance at all is `NULL`
+    pub fn to_json(&self) -> Option<Json> {
+        if self == &Self::default() {
+            return None;
+        }
+
+        serde_json::to_value(self).ok()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum EnrichedField {
+    Description,
+    MalId,
+    AltNames,
+    Genres,
+}
+
+/// A MAL id one of the sources reported
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MalIdCandidate {
+    pub source_id: i32,
+    pub site: String,
+    pub mal_id: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FailedSource {
+    pub source_id: i32,
+    pub site: String,
+    pub kind: SourceErrorKind,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct EnrichReport {
+    #[schema(value_type = Series)]
+    pub series: entity::series::Model,
+    pub filled: Vec<EnrichedField>,
+    /// What the sources reported when they didn't agree on a MAL id, in which case none was filled in
+    pub mal_id_conflicts: Vec<MalIdCandidate>,
+    /// Sources whose info couldn't be fetched
+    pub failed_sources: Vec<FailedSource>,
+}
+
+/// Fill in the empty fields of a series from the info of its sources.
+///
+/// Alternative names and genres of all the sources are added to the ones the series already has.
+pub async fn fill<C>(
+    db: &C,
+    series: entity::series::Model,
+    infos: &[(entity::series_sources::Model, AnimeInfo)],
+) -> Result<EnrichReport>
+where
+    C: ConnectionTrait,
+{
+    fill_with(db, series, None, infos).await
+}
+
+/// Like [`fill`], for a series that was just created with the name of `source`
+pub async fn fill_created<C>(
+    db: &C,
+    series: entity::series::Model,
+    source: &entity::series_sources::Model,
+    info: &AnimeInfo,
+) -> Result<EnrichReport>
+where
+    C: ConnectionTrait,
+{
+    let name_source = (series.name == info.name).then_some(source);
+
+    fill_with(db, series, name_source, &[(source.clone(), info.clone())]).await
+}
+
+/// Work out what to fill in from `series` as it was read, then write only those fields,
+/// and only if they are still as they were read, so edits made in the meantime aren't overwritten
+#[allow(clippy::too_many_lines)]
+async fn fill_with<C>(
+    db: &C,
+    mut series: entity::series::Model,
+    name_source: Option<&entity::series_sources::Model>,
+    infos: &[(entity::series_sources::Model, AnimeInfo)],
+) -> Result<EnrichReport>
+where
+    C: ConnectionTrait,
+{
+    let read = series.clone();
+    // Where the values that get filled in come from
+    let mut provenance = Provenance::default();
+    let mut filled = vec![];
+    let mut mal_id_conflicts = vec![];
+
+    if series.mal_id.is_none() {
+        let candidates = infos
+            .iter()
+            .filter_map(|(source, info)| {
+                Some(MalIdCandidate {
+                    source_id: source.id,
+                    site: source.series_site.clone(),
+                    mal_id: info.mal_id?,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        match candidates.first() {
+            Some(first) if candidates.iter().all(|x| x.mal_id == first.mal_id) => {
+                #[allow(clippy::cast_possible_wrap)]
+                let mal_id = first.mal_id as i32;
+                let (source, _) = infos
+                    .iter()
+                    .find(|(source, _)| source.id == first.source_id)
+                    .expect("candidate should come from one of the sources");
+
+                series.mal_id = Some(mal_id);
+                provenance.mal_id = Some(FieldProvenance::new(source));
+                filled.push(EnrichedField::MalId);
+            }
+            Some(_) => {
+                debug!(
+                    "Sources of series {} disagree on the MAL id: {:?}",
+                    series.id, candidates
+                );
+                mal_id_conflicts = candidates;
+            }
+            None => {}
+        }
+    }
+
+    if series
+        .description
+        .as_deref()
+        .map_or(true, |x| x.trim().is_empty())
+    {
+        let description = infos.iter().find_map(|(source, info)| {
+            info.description
+                .as_deref()
+                .map(str::trim)
+                .filter(|x| !x.is_empty())
+                .map(|x| (source, x))
+        });
+
+        if let Some((source, description)) = description {
+            series.description = Some(description.to_string());
+            provenance.description = Some(FieldProvenance::new(source));
+            filled.push(EnrichedField::Description);
+        }
+    }
+
+    let mut alt_names = lines(series.alt_names.as_deref());
+    let mut genres = lines(series.genres.as_deref());
+    let mut added_alt_names = false;
+    let mut added_genres = false;
+
+    for (source, info) in infos {
+        let names = std::iter::once(info.name.as_str())
+            .chain(info.alt_names.iter().map(|x| x.name.as_str()));
+
+        for name in names {
+            let name = name.trim();
+            let known = name.eq_ignore_ascii_case(&series.name)
+                || alt_names.iter().any(|x| x.eq_ignore_ascii_case(name));
+
+            if !name.is_empty() && !known {
+                alt_names.push(name.to_string());
+                provenance
+                    .alt_names
+                    .insert(name.to_string(), FieldProvenance::new(source));
+                added_alt_names = true;
+            }
+        }
+
+        for genre in &info.genres {
+            let genre = genre.trim();
+
+            if !genre.is_empty() && !genres.iter().any(|x| x.eq_ignore_ascii_case(genre)) {
+                genres.push(genre.to_string());
+                added_genres = true;
+            }
+        }
+    }
+
+    if added_alt_names {
+        series.alt_names = Some(alt_names.join("\n"));
+        filled.push(EnrichedField::AltNames);
+    }
+    if added_genres {
+        series.genres = Some(genres.join("\n"));
+        filled.push(EnrichedField::Genres);
+    }
+
+    if filled.is_empty() && name_source.is_none() {
+        return Ok(EnrichReport {
+            series,
+            filled,
+            mal_id_conflicts,
+            failed_sources: vec![],
+        });
+    }
+
+    trace!(
+        "Filling series {} from its sources: {:?}",
+        series.id,
+        filled
+    );
+
+    let mut written = vec![];
+    for field in filled {
+        if write_field(db, &read, &series, field).await? {
+            written.push(field);
+        } else {
+            debug!(
+                "{:?} of series {} changed while filling it in, leaving it",
+                field, series.id
+            );
+        }
+    }
+
+    let Some(mut series) = entity::series::Entity::find_by_id(series.id)
+        .one(db)
+        .await?
+    else {
+        anyhow::bail!("Series {} was removed while filling it in", read.id);
+    };
+
+    // Only add to what's there now, fields might have been edited in the meantime
+    let current = Provenance::of(&series);
+    let mut merged = current.clone();
+    for field in &written {
+        match field {
+            EnrichedField::MalId => merged.mal_id = provenance.mal_id.clone(),
+            EnrichedField::Description => merged.description = provenance.description.clone(),
+            EnrichedField::AltNames => merged.alt_names.append(&mut provenance.alt_names),
+            EnrichedField::Genres => {}
+        }
+    }
+    if let Some(source) = name_source.filter(|_| series.name == read.name) {
+        merged
+            .name
+            .get_or_insert_with(|| FieldProvenance::new(source));
+    }
+
+    if merged != current {
+        series.provenance = merged.to_json();
+        entity::series::Entity::update_many()
+            .col_expr(
+                entity::series::Column::Provenance,
+                Expr::value(series.provenance.clone()),
+            )
+            .filter(entity::series::Column::Id.eq(series.id))
+            .exec(db)
+            .await?;
+    }
+
+    Ok(EnrichReport {
+        series,
+        filled: written,
+        mal_id_conflicts,
+        failed_sources: vec![],
+    })
+}
+
+/// Write a field filled in on `filled`, if it's still what it was in `read`.
+///
+/// Doesn't touch `updated_at` since nobody edited the series.
+/// Returns whether it was written.
+async fn write_field<C>(
+    db: &C,
+    read: &entity::series::Model,
+    filled: &entity::series::Model,
+    field: EnrichedField,
+) -> Result<bool>
+where
+    C: ConnectionTrait,
+{
+    use entity::series::Column;
+
+    let (column, value, unchanged) = match field {
+        EnrichedField::MalId => (
+            Column::MalId,
+            Expr::value(filled.mal_id),
+            Column::MalId.is_null().into_condition(),
+        ),
+        EnrichedField::Description => (
+            Column::Description,
+            Expr::value(filled.description.clone()),
+            Condition::any().add(Column::Description.is_null()).add(
+                Expr::expr(Func::cust(Alias::new("trim")).arg(Expr::col(Column::Description)))
+                    .eq(""),
+            ),
+        ),
+        // Lists are added to, so they have to be the same as when they were read
+        EnrichedField::AltNames => (
+            Column::AltNames,
+            Expr::value(filled.alt_names.clone()),
+            Expr::col(Column::AltNames)
+                .is(read.alt_names.clone())
+                .into_condition(),
+        ),
+        EnrichedField::Genres => (
+            Column::Genres,
+            Expr::value(filled.genres.clone()),
+            Expr::col(Column::Genres)
+                .is(read.genres.clone())
+                .into_condition(),
+        ),
+    };
+
+    let result = entity::series::Entity::update_many()
+        .col_expr(column, value)
+        .filter(Column::Id.eq(filled.id))
+        .filter(unchanged)
+        .exec(db)
+        .await?;
+
+    Ok(result.rows_affected > 0)
+}
+
+fn lines(value: Option<&str>) -> Vec<String> {
+    value
+        .unwrap_or_default()
+        .lines()
+        .map(str::trim)
+        .filter(|x| !x.is_empty())
+        .map(ToString::to_string)
+        .collect()
+}
+
+/// Fetch the info of every source of a series and fill in the series from it.
+///
+/// Broken sources are only tried if `include_broken` is set.
+/// Returns `None` if the series doesn't exist.
+pub async fn enrich(
+    db: &DatabaseConnection,
+    series_id: i32,
+    include_broken: bool,
+) -> Result<Option<EnrichReport>> {
+    let Some(series) = entity::series::Entity::find_by_id(series_id)
+        .one(db)
+        .await?
+    else {
+        return Ok(None);
+    };
+
+    let mut select = entity::series_sources::Entity::find()
+        .filter(entity::series_sources::Column::ForSeriesId.eq(series_id))
+        .order_by_asc(entity::series_sources::Column::Id);
+    if !include_broken {
+        select = select.filter(entity::series_sources::Column::Broken.eq(false));
+    }
+    let series_sources = select.all(db).await?;
+
+    let results = futures::future::join_all(series_sources.into_iter().map(|source| async move {
+        let info = health::fetch_info(db, &source).await;
+
+        (source, info)
+    }))
+    .await;
+
+    let mut infos = vec![];
+    let mut failed_sources = vec![];
+    for (source, info) in results {
+        match info {
+            Ok(info) => infos.push((source, info)),
+            Err(e) => {
+                debug!("Failed to get info of source {}: {:?}", source.id, e);
+                failed_sources.push(FailedSource {
+                    source_id: source.id,
+                    site: source.series_site,
+                    kind: e.kind,
+                    error: e.to_string(),
+                });
+            }
+        }
+    }
+
+    let mut report = fill(db, series, &infos).await?;
+    report.failed_sources = failed_sources;
+
+    Ok(Some(report))
+}
+
+/// Periodically try to fill in the MAL id of series that have working sources but no MAL id yet
+pub fn spawn_periodic_backfill(db: DatabaseConnection) {
+    let interval = CONFIG.library.enrich_interval;
+
+    info!("Filling in missing MAL ids from sources every {interval:?}");
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval.max(std::time::Duration::from_secs(60)));
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+
+            let missing = entity::series::Entity::find()
+                .filter(entity::series::Column::MalId.is_null())
+                .filter(
+                    entity::series::Column::Id.in_subquery(
+                        SelectQuery::select()
+                            .column(entity::series_sources::Column::ForSeriesId)
+                            .from(entity::series_sources::Entity)
+                            .and_where(entity::series_sources::Column::Broken.eq(false))
+                            .to_owned(),
+                    ),
+                )
+                .all(&db)
+                .await;
+            let missing = match missing {
+                Ok(x) => x,
+                Err(e) => {
+                    warn!("Failed to get series without a MAL id: {e:?}");
+                    continue;
+                }
+            };
+
+            for series in missing {
+                match enrich(&db, series.id, false).await {
+                    Ok(Some(report)) if !report.filled.is_empty() => {
+                        info!(
+                            "Filled in series {} from its sources: {:?}",
+                            series.id, report.filled
+                        );
+                    }
+                    Ok(_) => {}
+                    Err(e) => error!("Failed to fill in series {}: {e:?}", series.id),
+                }
+            }
+        }
+    });
+}
@@ -0,0 +1,165 @@
+//! Filling in series against a migrated in-memory database
+
+use migration::{Migrator, MigratorTrait};
+use sea_orm::{prelude::*, ActiveValue, ConnectOptions, Database};
+
+use super::{fill, fill_created, EnrichedField, Provenance};
+use crate::metadata::AnimeInfo;
+
+async fn database() -> DatabaseConnection {
+    let mut opt = ConnectOptions::new("sqlite::memory:".to_string());
+    // Every connection to an in-memory database gets its own database
+    opt.max_connections(1).sqlx_logging(false);
+
+    let db = Database::connect(opt).await.unwrap();
+    Migrator::up(&db, None).await.unwrap();
+
+    db
+}
+
+async fn series(db: &DatabaseConnection, name: &str) -> entity::series::Model {
+    entity::series::ActiveModel {
+        name: ActiveValue::Set(name.to_string()),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap()
+}
+
+async fn source(
+    db: &DatabaseConnection,
+    series_id: i32,
+    series_site_id: &str,
+) -> entity::series_sources::Model {
+    entity::series_sources::ActiveModel {
+        for_series_id: ActiveValue::Set(series_id),
+        series_site: ActiveValue::Set("allanime".to_string()),
+        series_site_id: ActiveValue::Set(series_site_id.to_string()),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap()
+}
+
+async fn reload(db: &DatabaseConnection, id: i32) -> entity::series::Model {
+    entity::series::Entity::find_by_id(id)
+        .one(db)
+        .await
+        .unwrap()
+        .unwrap()
+}
+
+fn info() -> AnimeInfo {
+    AnimeInfo {
+        name: "Sousou no Frieren".to_string(),
+        mal_id: Some(52991),
+        description: Some("An elf mage looks back on her journey.".to_string()),
+        genres: vec!["Adventure".to_string(), "Fantasy".to_string()],
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn fills_empty_fields() {
+    let db = database().await;
+    let series = series(&db, "Frieren").await;
+    let source = source(&db, series.id, "ReooPAxPMsHM4KPMY").await;
+
+    let report = fill(&db, series.clone(), &[(source.clone(), info())])
+        .await
+        .unwrap();
+
+    assert_eq!(
+        report.filled,
+        [
+            EnrichedField::MalId,
+            EnrichedField::Description,
+            EnrichedField::AltNames,
+            EnrichedField::Genres,
+        ]
+    );
+    let stored = reload(&db, series.id).await;
+    assert_eq!(stored, report.series);
+    assert_eq!(stored.mal_id, Some(52991));
+    assert_eq!(stored.alt_names.as_deref(), Some("Sousou no Frieren"));
+    assert_eq!(stored.genres.as_deref(), Some("Adventure\nFantasy"));
+    // Nobody edited it
+    assert_eq!(stored.updated_at, series.updated_at);
+
+    let provenance = Provenance::of(&stored);
+    assert_eq!(provenance.name, None);
+    assert_eq!(provenance.mal_id.map(|x| x.source_id), Some(source.id));
+    assert_eq!(provenance.description.map(|x| x.source_id), Some(source.id));
+    assert!(provenance.alt_names.contains_key("Sousou no Frieren"));
+}
+
+#[tokio::test]
+async fn keeps_fields_edited_after_reading() {
+    let db = database().await;
+    let stale = series(&db, "Frieren").await;
+    let source = source(&db, stale.id, "ReooPAxPMsHM4KPMY").await;
+
+    // Edited by hand while the sources were being fetched
+    let mut edited = entity::series::ActiveModel::from(stale.clone());
+    edited.mal_id = ActiveValue::Set(Some(1));
+    edited.description = ActiveValue::Set(Some("Written by hand".to_string()));
+    edited.genres = ActiveValue::Set(Some("Drama".to_string()));
+    edited.update(&db).await.unwrap();
+
+    let report = fill(&db, stale.clone(), &[(source, info())]).await.unwrap();
+
+    assert_eq!(report.filled, [EnrichedField::AltNames]);
+    let stored = reload(&db, stale.id).await;
+    assert_eq!(stored, report.series);
+    assert_eq!(stored.mal_id, Some(1));
+    assert_eq!(stored.description.as_deref(), Some("Written by hand"));
+    assert_eq!(stored.genres.as_deref(), Some("Drama"));
+    assert_eq!(stored.alt_names.as_deref(), Some("Sousou no Frieren"));
+
+    let provenance = Provenance::of(&stored);
+    assert_eq!(provenance.mal_id, None);
+    assert_eq!(provenance.description, None);
+    assert_eq!(provenance.alt_names.len(), 1);
+}
+
+#[tokio::test]
+async fn keeps_provenance_of_other_fields() {
+    let db = database().await;
+    let series = series(&db, "Sousou no Frieren").await;
+    let source = source(&db, series.id, "ReooPAxPMsHM4KPMY").await;
+
+    let created = fill_created(&db, series.clone(), &source, &info())
+        .await
+        .unwrap();
+    assert!(Provenance::of(&created.series).name.is_some());
+
+    // Filling again from a row read before the first fill adds nothing and forgets nothing
+    let report = fill(&db, series.clone(), &[(source, info())])
+        .await
+        .unwrap();
+
+    assert!(report.filled.is_empty());
+    assert_eq!(reload(&db, series.id).await, created.series);
+}
+
+#[tokio::test]
+async fn leaves_mal_id_alone_when_sources_disagree() {
+    let db = database().await;
+    let series = series(&db, "Frieren").await;
+    let first = source(&db, series.id, "ReooPAxPMsHM4KPMY").await;
+    let second = source(&db, series.id, "2mdEefHbZrBEE9Bbo").await;
+
+    let other = AnimeInfo {
+        mal_id: Some(1),
+        ..info()
+    };
+    let report = fill(&db, series.clone(), &[(first, info()), (second, other)])
+        .await
+        .unwrap();
+
+    assert!(!report.filled.contains(&EnrichedField::MalId));
+    assert_eq!(report.mal_id_conflicts.len(), 2);
+    assert_eq!(reload(&db, series.id).await.mal_id, None);
+}
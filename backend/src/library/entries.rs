@@ -0,0 +1,116 @@
+use anyhow::Result;
+use sea_orm::{prelude::*, ActiveValue, IntoActiveModel, QueryOrder, TryIntoModel};
+
+use super::ListStatus;
+
+/// A user's entry for a series, if the series is on their list
+pub async fn find<C>(
+    db: &C,
+    user_id: i32,
+    series_id: i32,
+) -> Result<Option<entity::library_entries::Model>>
+where
+    C: ConnectionTrait,
+{
+    let entry = entity::library_entries::Entity::find()
+        .filter(entity::library_entries::Column::UserId.eq(user_id))
+        .filter(entity::library_entries::Column::SeriesId.eq(series_id))
+        .one(db)
+        .await?;
+
+    Ok(entry)
+}
+
+/// A user's entry for a series, or a new one that still needs to be saved
+pub async fn find_or_new<C>(
+    db: &C,
+    user_id: i32,
+    series_id: i32,
+) -> Result<entity::library_entries::ActiveModel>
+where
+    C: ConnectionTrait,
+{
+    let entry = match find(db, user_id, series_id).await? {
+        Some(entry) => entry.into_active_model(),
+        None => entity::library_entries::ActiveModel {
+            user_id: ActiveValue::Set(Some(user_id)),
+            series_id: ActiveValue::Set(series_id),
+            ..Default::default()
+        },
+    };
+
+    Ok(entry)
+}
+
+/// Every entry on a user's list along with its series
+pub async fn for_user<C>(
+    db: &C,
+    user_id: i32,
+) -> Result<Vec<(entity::library_entries::Model, entity::series::Model)>>
+where
+    C: ConnectionTrait,
+{
+    let entries = entity::library_entries::Entity::find()
+        .filter(entity::library_entries::Column::UserId.eq(user_id))
+        .find_also_related(entity::series::Entity)
+        .order_by_asc(entity::series::Column::Name)
+        .all(db)
+        .await?
+        .into_iter()
+        .filter_map(|(entry, series)| Some((entry, series?)))
+        .collect();
+
+    Ok(entries)
+}
+
+/// Record that a user watched an episode of a series.
+///
+/// Progress only ever moves forward, so rewatching an older episode doesn't change anything.
+/// Series that weren't being watched yet are moved to watching.
+pub async fn mark_watched<C>(
+    db: &C,
+    user_id: i32,
+    series_id: i32,
+    episode: u32,
+) -> Result<entity::library_entries::Model>
+where
+    C: ConnectionTrait,
+{
+    #[allow(clippy::cast_possible_wrap)]
+    let episode = episode as i32;
+
+    let existing = find(db, user_id, series_id).await?;
+    if let Some(entry) = &existing {
+        if entry.watched_episodes.unwrap_or_default() >= episode {
+            return Ok(entry.clone());
+        }
+    }
+
+    let now = chrono::Utc::now();
+    let (status, started_watching_at) = existing
+        .as_ref()
+        .map(|x| (x.list_status.clone(), x.started_watching_at.clone()))
+        .unwrap_or_default();
+    let mut entry = match existing {
+        Some(entry) => entry.into_active_model(),
+        None => entity::library_entries::ActiveModel {
+            user_id: ActiveValue::Set(Some(user_id)),
+            series_id: ActiveValue::Set(series_id),
+            ..Default::default()
+        },
+    };
+
+    let status = status.and_then(|x| x.parse::<ListStatus>().ok());
+    if matches!(status, None | Some(ListStatus::PlanToWatch)) {
+        entry.list_status = ActiveValue::Set(Some(ListStatus::Watching.to_string()));
+    }
+    if started_watching_at.is_none() {
+        entry.started_watching_at = ActiveValue::Set(Some(now.date_naive().to_string()));
+    }
+    entry.watched_episodes = ActiveValue::Set(Some(episode));
+    entry.list_updated_at = ActiveValue::Set(Some(now.to_rfc3339()));
+
+    let entry = entry.save(db).await?.try_into_model()?;
+
+    Ok(entry)
+}
@@ -0,0 +1,330 @@
+//! Combining the episode lists of all the sources of a series into one timeline.
+//!
+//! Episodes are matched up by their episode number, so the same episode from different
+//! sites ends up in one entry with a link to each of them. Sites don't always number episodes
+//! the same way, so each source can have an [`EpisodeMapping`] that translates its numbering
+//! into the one of the series.
+
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use log::debug;
+use sea_orm::{prelude::*, QueryOrder};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::{enrich::FailedSource, entries, health, merge};
+use crate::{
+    config::CONFIG,
+    metadata::{self, AnimeInfo, SeriesTranslation},
+};
+
+#[cfg(test)]
+mod tests;
+
+/// A site episode that gets a number of its own instead of following the rest of the mapping
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct EpisodeRemap {
+    /// Episode number on the site
+    pub from: f64,
+    /// Episode number in the series, or `null` to leave the episode out
+    pub to: Option<f64>,
+}
+
+/// How the episode numbers of a source line up with the episodes of its series.
+///
+/// Eg. for a second cour that a site numbers 13-24, an offset of `-12` makes them 1-12.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct EpisodeMapping {
+    /// Added to the episode numbers of the site
+    #[serde(default)]
+    pub offset: f64,
+    /// Site episodes numbered lower than this are left out
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min: Option<f64>,
+    /// Site episodes numbered higher than this are left out
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max: Option<f64>,
+    /// Applied before everything else, eg. to move a special listed as 12.5 elsewhere
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub remap: Vec<EpisodeRemap>,
+}
+
+impl EpisodeMapping {
+    pub fn of(source: &entity::series_sources::Model) -> Self {
+        source
+            .episode_mapping
+            .clone()
+            .and_then(|x| serde_json::from_value(x).ok())
+            .unwrap_or_default()
+    }
+
+    /// Number of a site episode in the series, `None` if it's left out
+    pub fn map(&self, episode_number: f64) -> Option<f64> {
+        if let Some(remap) = self
+            .remap
+            .iter()
+            .find(|x| x.from.to_bits() == episode_number.to_bits())
+        {
+            return remap.to;
+        }
+
+        if self.min.is_some_and(|min| episode_number < min)
+            || self.max.is_some_and(|max| episode_number > max)
+        {
+            return None;
+        }
+
+        Some(episode_number + self.offset)
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        let numbers = [Some(self.offset), self.min, self.max]
+            .into_iter()
+            .chain(self.remap.iter().flat_map(|x| [Some(x.from), x.to]))
+            .flatten();
+        for number in numbers {
+            if !number.is_finite() {
+                anyhow::bail!("Episode numbers have to be finite, got {number}");
+            }
+        }
+
+        if let (Some(min), Some(max)) = (self.min, self.max) {
+            if min > max {
+                anyhow::bail!("The minimum episode ({min}) is higher than the maximum ({max})");
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The episodes of a source, numbered the way its series numbers them
+pub fn mapped(
+    source: &entity::series_sources::Model,
+    info: &AnimeInfo,
+) -> Vec<metadata::EpisodeInfo> {
+    let mapping = EpisodeMapping::of(source);
+
+    info.episodes
+        .iter()
+        .filter_map(|episode| {
+            Some(metadata::EpisodeInfo {
+                episode_number: mapping.map(episode.episode_number)?,
+                ..episode.clone()
+            })
+        })
+        .collect()
+}
+
+/// An episode on one of the sites
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct EpisodeLink {
+    pub source_id: i32,
+    pub site: String,
+    pub url: String,
+    pub available_at: Option<DateTime<Utc>>,
+}
+
+/// Where an episode can be watched in one translation
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TranslationAvailability {
+    /// Earliest time any of the sites says the episode became available
+    pub available_at: Option<DateTime<Utc>>,
+    /// Sites that have the episode, in the order of the provider precedence
+    pub sites: Vec<String>,
+    pub links: Vec<EpisodeLink>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TimelineEpisode {
+    pub episode_number: f64,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub thumbnail: Option<String>,
+    /// Any of the sites marks the episode as filler
+    pub is_filler: bool,
+    /// Keyed by `sub`, `dub` or `raw`.
+    ///
+    /// Episodes of sites that don't say which translation they are end up under `unknown`.
+    #[schema(value_type = HashMap<String, TranslationAvailability>)]
+    pub translations: BTreeMap<SeriesTranslation, TranslationAvailability>,
+}
+
+/// How far a user got through the episodes of a series
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Progress {
+    pub watched_episodes: u32,
+    pub skip_fillers: bool,
+    /// First episode after the watched ones, not counting fillers if they're skipped
+    pub up_next: Option<f64>,
+    /// Episodes after the watched ones, not counting fillers if they're skipped
+    pub unwatched: usize,
+}
+
+impl Progress {
+    pub fn new(episodes: &[TimelineEpisode], entry: &entity::library_entries::Model) -> Self {
+        let watched_episodes =
+            u32::try_from(entry.watched_episodes.unwrap_or_default()).unwrap_or(0);
+
+        // Specials like 12.5 count as the episode before them, same as when marking them watched
+        let unwatched = episodes
+            .iter()
+            .filter(|x| x.episode_number.floor() > f64::from(watched_episodes))
+            .filter(|x| !(entry.skip_fillers && x.is_filler))
+            .collect::<Vec<_>>();
+
+        Self {
+            watched_episodes,
+            skip_fillers: entry.skip_fillers,
+            up_next: unwatched.first().map(|x| x.episode_number),
+            unwatched: unwatched.len(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Timeline {
+    /// Ordered by episode number
+    pub episodes: Vec<TimelineEpisode>,
+    /// Not set if the series isn't in the user's library
+    pub progress: Option<Progress>,
+    /// Sources whose episodes couldn't be fetched
+    pub failed_sources: Vec<FailedSource>,
+}
+
+/// Merge the episode lists of the sources of a series into one timeline.
+///
+/// The episode mapping of each source is applied first.
+pub fn merge(sources: &[(entity::series_sources::Model, AnimeInfo)]) -> Vec<TimelineEpisode> {
+    merge_with(sources, &CONFIG.library.provider_precedence)
+}
+
+/// [`merge`] with the sites in the given order instead of the configured one
+fn merge_with(
+    sources: &[(entity::series_sources::Model, AnimeInfo)],
+    order: &[String],
+) -> Vec<TimelineEpisode> {
+    let mut sources = sources.iter().collect::<Vec<_>>();
+    // Stable, so sources of the same site stay in the order they were added
+    sources.sort_by_key(|(source, _)| merge::precedence(order, &source.series_site));
+
+    let mut episodes: Vec<TimelineEpisode> = vec![];
+    for (source, info) in sources {
+        for episode in &mapped(source, info) {
+            let index = match episodes
+                .iter()
+                .position(|x| x.episode_number.to_bits() == episode.episode_number.to_bits())
+            {
+                Some(index) => index,
+                None => {
+                    episodes.push(TimelineEpisode {
+                        episode_number: episode.episode_number,
+                        title: None,
+                        description: None,
+                        thumbnail: None,
+                        is_filler: false,
+                        translations: BTreeMap::new(),
+                    });
+                    episodes.len() - 1
+                }
+            };
+            let entry = &mut episodes[index];
+
+            if entry.title.is_none() && !episode.title.trim().is_empty() {
+                entry.title = Some(episode.title.trim().to_string());
+            }
+            if entry.description.is_none() {
+                entry.description = episode.description.clone();
+            }
+            if entry.thumbnail.is_none() {
+                entry.thumbnail = episode.thumbnail.clone();
+            }
+            entry.is_filler |= episode.is_filler;
+
+            let availability = entry.translations.entry(episode.translation).or_default();
+            availability.available_at = match (availability.available_at, episode.available_at) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            };
+            if !availability.sites.contains(&source.series_site) {
+                availability.sites.push(source.series_site.clone());
+            }
+            availability.links.push(EpisodeLink {
+                source_id: source.id,
+                site: source.series_site.clone(),
+                url: episode.url.clone(),
+                available_at: episode.available_at,
+            });
+        }
+    }
+
+    episodes.sort_by(|a, b| a.episode_number.total_cmp(&b.episode_number));
+
+    episodes
+}
+
+/// Fetch the episodes of every source of a series and merge them into one timeline,
+/// along with how far the user got through them.
+///
+/// Returns `None` if the series doesn't exist.
+pub async fn timeline<C>(db: &C, user_id: i32, series_id: i32) -> Result<Option<Timeline>>
+where
+    C: ConnectionTrait,
+{
+    let Some(series) = entity::series::Entity::find_by_id(series_id)
+        .one(db)
+        .await?
+    else {
+        return Ok(None);
+    };
+
+    let series_sources = entity::series_sources::Entity::find()
+        .filter(entity::series_sources::Column::ForSeriesId.eq(series.id))
+        .order_by_asc(entity::series_sources::Column::Id)
+        .all(db)
+        .await?;
+
+    let results = futures::future::join_all(series_sources.into_iter().map(|source| async move {
+        let fetched = health::fetch_info_cached(db, &source).await;
+
+        (source, fetched)
+    }))
+    .await;
+
+    let mut infos = vec![];
+    let mut failed_sources = vec![];
+    for (source, fetched) in results {
+        match fetched {
+            Ok(fetched) => infos.push((source, fetched.info)),
+            Err(e) => {
+                debug!("Failed to get episodes of source {}: {:?}", source.id, e);
+                failed_sources.push(FailedSource {
+                    source_id: source.id,
+                    site: source.series_site,
+                    kind: e.kind,
+                    error: e.to_string(),
+                });
+            }
+        }
+    }
+
+    let episodes = merge(&infos);
+    let progress = entries::find(db, user_id, series.id)
+        .await?
+        .map(|entry| Progress::new(&episodes, &entry));
+
+    Ok(Some(Timeline {
+        episodes,
+        progress,
+        failed_sources,
+    }))
+}
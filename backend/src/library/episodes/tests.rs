@@ -0,0 +1,394 @@
+//! Translating the episode numbers of a site into the ones of the series, merging the
+//! episodes of every source into one timeline and how far a user got through it
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, TimeZone, Utc};
+
+use super::{merge_with, EpisodeMapping, EpisodeRemap, Progress, TimelineEpisode};
+use crate::{
+    metadata::{AnimeInfo, EpisodeInfo, SeriesTranslation},
+    test_util::test_source,
+};
+
+fn mapping(json: serde_json::Value) -> EpisodeMapping {
+    serde_json::from_value(json).unwrap()
+}
+
+#[test]
+fn keeps_numbers_without_a_mapping() {
+    let mapping = EpisodeMapping::default();
+
+    assert_eq!(mapping.map(1.0), Some(1.0));
+    assert_eq!(mapping.map(12.5), Some(12.5));
+    assert_eq!(mapping.map(0.0), Some(0.0));
+}
+
+#[test]
+fn offsets_a_second_cour() {
+    // Numbered 13-24 on the site, 1-12 in the series
+    let mapping = mapping(serde_json::json!({ "offset": -12.0, "min": 13.0, "max": 24.0 }));
+
+    assert_eq!(mapping.map(13.0), Some(1.0));
+    assert_eq!(mapping.map(24.0), Some(12.0));
+    // The first cour and whatever comes after are left out
+    assert_eq!(mapping.map(12.0), None);
+    assert_eq!(mapping.map(25.0), None);
+}
+
+#[test]
+fn remaps_before_everything_else() {
+    let mapping = EpisodeMapping {
+        offset: -12.0,
+        min: Some(13.0),
+        max: None,
+        remap: vec![
+            // A special the site lists between two episodes
+            EpisodeRemap {
+                from: 12.5,
+                to: Some(0.0),
+            },
+            // A recap that isn't worth watching
+            EpisodeRemap {
+                from: 20.0,
+                to: None,
+            },
+        ],
+    };
+
+    // Neither the minimum nor the offset apply to it
+    assert_eq!(mapping.map(12.5), Some(0.0));
+    assert_eq!(mapping.map(20.0), None);
+    // Only the exact number is remapped
+    assert_eq!(mapping.map(12.0), None);
+    assert_eq!(mapping.map(19.0), Some(7.0));
+    assert_eq!(mapping.map(21.0), Some(9.0));
+}
+
+#[test]
+fn keeps_numbers_from_the_bounds() {
+    let mapping = mapping(serde_json::json!({ "min": 1.0, "max": 12.0 }));
+
+    assert_eq!(mapping.map(1.0), Some(1.0));
+    assert_eq!(mapping.map(12.0), Some(12.0));
+    assert_eq!(mapping.map(0.0), None);
+    assert_eq!(mapping.map(12.5), None);
+}
+
+#[test]
+fn accepts_sensible_mappings() {
+    for json in [
+        serde_json::json!({}),
+        serde_json::json!({ "offset": -12.0, "min": 13.0, "max": 24.0 }),
+        serde_json::json!({ "min": 5.0, "max": 5.0 }),
+        serde_json::json!({ "remap": [{ "from": 12.5, "to": null }, { "from": 13.0, "to": 12.5 }] }),
+    ] {
+        assert!(mapping(json.clone()).validate().is_ok(), "{json}");
+    }
+}
+
+#[test]
+fn rejects_a_minimum_above_the_maximum() {
+    let error = mapping(serde_json::json!({ "min": 13.0, "max": 12.0 }))
+        .validate()
+        .unwrap_err();
+
+    assert!(
+        error.to_string().contains("higher than the maximum"),
+        "{error}"
+    );
+}
+
+#[test]
+fn rejects_numbers_that_are_not_finite() {
+    for mapping in [
+        EpisodeMapping {
+            offset: f64::NAN,
+            ..Default::default()
+        },
+        EpisodeMapping {
+            max: Some(f64::INFINITY),
+            ..Default::default()
+        },
+        EpisodeMapping {
+            remap: vec![EpisodeRemap {
+                from: 1.0,
+                to: Some(f64::NEG_INFINITY),
+            }],
+            ..Default::default()
+        },
+    ] {
+        assert!(mapping.validate().is_err(), "{mapping:?}");
+    }
+}
+
+#[test]
+fn reads_mappings_stored_on_sources() {
+    let mut source = test_source();
+    assert_eq!(EpisodeMapping::of(&source), EpisodeMapping::default());
+
+    source.episode_mapping = Some(serde_json::json!({ "offset": -12.0 }));
+    assert_eq!(EpisodeMapping::of(&source).map(13.0), Some(1.0));
+
+    // Anything that isn't a mapping counts as none
+    source.episode_mapping = Some(serde_json::json!("-12"));
+    assert_eq!(EpisodeMapping::of(&source), EpisodeMapping::default());
+}
+
+fn source(id: i32, site: &str) -> entity::series_sources::Model {
+    entity::series_sources::Model {
+        id,
+        series_site: site.to_string(),
+        series_site_id: format!("frieren-{id}"),
+        ..test_source()
+    }
+}
+
+fn episode(
+    number: f64,
+    translation: SeriesTranslation,
+    title: &str,
+    available_at: Option<DateTime<Utc>>,
+) -> EpisodeInfo {
+    EpisodeInfo {
+        id: format!("{number}"),
+        title: title.to_string(),
+        translation,
+        episode_number: number,
+        url: format!("https://example.com/{title}"),
+        description: None,
+        thumbnail: None,
+        available_at,
+        is_filler: false,
+    }
+}
+
+fn info(episodes: Vec<EpisodeInfo>) -> AnimeInfo {
+    AnimeInfo {
+        name: "Sousou no Frieren".to_string(),
+        episodes,
+        ..Default::default()
+    }
+}
+
+fn order(sites: &[&str]) -> Vec<String> {
+    sites.iter().copied().map(String::from).collect()
+}
+
+fn day(day: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2023, 10, day, 16, 0, 0).unwrap()
+}
+
+#[test]
+fn takes_episode_details_from_the_first_site_in_the_order() {
+    let sources = [
+        (
+            source(1, "aniwatch"),
+            info(vec![
+                episode(1.0, SeriesTranslation::Sub, "The Journey's End", None),
+                episode(2.0, SeriesTranslation::Sub, " ", None),
+            ]),
+        ),
+        (
+            source(2, "allanime"),
+            info(vec![
+                episode(1.0, SeriesTranslation::Sub, "Episode 1", None),
+                episode(
+                    2.0,
+                    SeriesTranslation::Sub,
+                    "It Didn't Have to Be Magic...",
+                    None,
+                ),
+            ]),
+        ),
+    ];
+
+    let episodes = merge_with(&sources, &order(&["aniwatch", "allanime"]));
+
+    assert_eq!(episodes.len(), 2);
+    assert_eq!(episodes[0].title.as_deref(), Some("The Journey's End"));
+    // Blank titles don't count
+    assert_eq!(
+        episodes[1].title.as_deref(),
+        Some("It Didn't Have to Be Magic...")
+    );
+    let sites = &episodes[0].translations[&SeriesTranslation::Sub].sites;
+    assert_eq!(sites, &["aniwatch", "allanime"]);
+
+    let episodes = merge_with(&sources, &order(&["allanime", "aniwatch"]));
+
+    assert_eq!(episodes[0].title.as_deref(), Some("Episode 1"));
+    let links = &episodes[0].translations[&SeriesTranslation::Sub].links;
+    let link_sources = links.iter().map(|x| x.source_id).collect::<Vec<_>>();
+    assert_eq!(link_sources, [2, 1]);
+}
+
+#[test]
+fn keeps_the_earliest_release_of_each_translation() {
+    let sources = [
+        (
+            source(1, "aniwatch"),
+            info(vec![
+                episode(1.0, SeriesTranslation::Sub, "Episode 1", Some(day(2))),
+                episode(1.0, SeriesTranslation::Dub, "Episode 1", None),
+            ]),
+        ),
+        (
+            source(2, "allanime"),
+            info(vec![
+                episode(1.0, SeriesTranslation::Sub, "Episode 1", Some(day(1))),
+                episode(1.0, SeriesTranslation::Dub, "Episode 1", Some(day(20))),
+            ]),
+        ),
+        (
+            source(3, "aniwave"),
+            info(vec![episode(
+                1.0,
+                SeriesTranslation::Sub,
+                "Episode 1",
+                Some(day(3)),
+            )]),
+        ),
+    ];
+
+    let episodes = merge_with(&sources, &order(&["aniwatch", "allanime", "aniwave"]));
+
+    assert_eq!(episodes.len(), 1);
+    let translations = &episodes[0].translations;
+    assert_eq!(
+        translations[&SeriesTranslation::Sub].available_at,
+        Some(day(1))
+    );
+    // A site that doesn't know when it came out doesn't hide one that does
+    assert_eq!(
+        translations[&SeriesTranslation::Dub].available_at,
+        Some(day(20))
+    );
+    // Every link keeps its own date
+    let dates = translations[&SeriesTranslation::Sub]
+        .links
+        .iter()
+        .map(|x| x.available_at)
+        .collect::<Vec<_>>();
+    assert_eq!(dates, [Some(day(2)), Some(day(1)), Some(day(3))]);
+}
+
+#[test]
+fn lists_each_site_once() {
+    // Both cours of a series the site lists separately
+    let mut second_cour = source(2, "aniwatch");
+    second_cour.episode_mapping = Some(serde_json::json!({ "offset": 12.0 }));
+    let sources = [
+        (
+            source(1, "aniwatch"),
+            info(vec![
+                episode(1.0, SeriesTranslation::Sub, "Episode 1", None),
+                episode(13.0, SeriesTranslation::Sub, "Episode 13", None),
+            ]),
+        ),
+        (
+            second_cour,
+            info(vec![episode(
+                1.0,
+                SeriesTranslation::Sub,
+                "Episode 13",
+                None,
+            )]),
+        ),
+        (
+            source(3, "allanime"),
+            info(vec![episode(
+                13.0,
+                SeriesTranslation::Sub,
+                "Episode 13",
+                None,
+            )]),
+        ),
+    ];
+
+    let episodes = merge_with(&sources, &order(&["aniwatch", "allanime"]));
+
+    let numbers = episodes
+        .iter()
+        .map(|x| x.episode_number)
+        .collect::<Vec<_>>();
+    assert_eq!(numbers, [1.0, 13.0]);
+    let sub = &episodes[1].translations[&SeriesTranslation::Sub];
+    assert_eq!(sub.sites, ["aniwatch", "allanime"]);
+    // Still a link to each of them
+    let link_sources = sub.links.iter().map(|x| x.source_id).collect::<Vec<_>>();
+    assert_eq!(link_sources, [1, 2, 3]);
+}
+
+fn timeline(numbers: &[f64], fillers: &[f64]) -> Vec<TimelineEpisode> {
+    numbers
+        .iter()
+        .map(|&episode_number| TimelineEpisode {
+            episode_number,
+            title: None,
+            description: None,
+            thumbnail: None,
+            is_filler: fillers.contains(&episode_number),
+            translations: BTreeMap::new(),
+        })
+        .collect()
+}
+
+fn entry(watched_episodes: i32, skip_fillers: bool) -> entity::library_entries::Model {
+    entity::library_entries::Model {
+        id: 1,
+        user_id: Some(1),
+        series_id: 1,
+        list_status: Some("watching".to_string()),
+        score: None,
+        watched_episodes: Some(watched_episodes),
+        started_watching_at: None,
+        finished_watching_at: None,
+        list_updated_at: None,
+        mal_synced_at: None,
+        mal_list_updated_at: None,
+        created_at: None,
+        updated_at: None,
+        skip_fillers,
+    }
+}
+
+#[test]
+fn counts_fillers_unless_they_are_skipped() {
+    let episodes = timeline(&[1.0, 2.0, 3.0, 4.0, 5.0], &[3.0, 4.0]);
+
+    let progress = Progress::new(&episodes, &entry(2, false));
+    assert_eq!(progress.up_next, Some(3.0));
+    assert_eq!(progress.unwatched, 3);
+
+    let progress = Progress::new(&episodes, &entry(2, true));
+    assert!(progress.skip_fillers);
+    assert_eq!(progress.up_next, Some(5.0));
+    assert_eq!(progress.unwatched, 1);
+}
+
+#[test]
+fn has_nothing_up_next_when_only_skipped_fillers_are_left() {
+    let episodes = timeline(&[1.0, 2.0, 3.0], &[3.0]);
+
+    let progress = Progress::new(&episodes, &entry(2, true));
+    assert_eq!(progress.up_next, None);
+    assert_eq!(progress.unwatched, 0);
+
+    let progress = Progress::new(&episodes, &entry(2, false));
+    assert_eq!(progress.up_next, Some(3.0));
+    assert_eq!(progress.unwatched, 1);
+}
+
+#[test]
+fn counts_specials_as_the_episode_before_them() {
+    let episodes = timeline(&[1.0, 2.0, 2.5, 3.0], &[]);
+
+    let progress = Progress::new(&episodes, &entry(2, false));
+    assert_eq!(progress.up_next, Some(3.0));
+    assert_eq!(progress.unwatched, 1);
+
+    let progress = Progress::new(&episodes, &entry(1, false));
+    assert_eq!(progress.up_next, Some(2.0));
+    assert_eq!(progress.unwatched, 3);
+}
@@ -0,0 +1,211 @@
+use anyhow::Result;
+use chrono::{Datelike, NaiveDate};
+use sea_orm::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use utoipa::ToSchema;
+
+use super::{entries, mal_xml, ListStatus};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum ExportFormat {
+    MalXml,
+    AnilistJson,
+    Csv,
+}
+
+impl ExportFormat {
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            Self::MalXml => "application/xml",
+            Self::AnilistJson => "application/json",
+            Self::Csv => "text/csv",
+        }
+    }
+
+    pub fn file_name(self) -> &'static str {
+        match self {
+            Self::MalXml => "animelist.xml",
+            Self::AnilistJson => "animelist.anilist.json",
+            Self::Csv => "animelist.csv",
+        }
+    }
+}
+
+/// A library entry in the shape every export format is written from
+#[derive(Debug, Clone)]
+pub struct ExportEntry {
+    pub series_id: i32,
+    pub name: String,
+    pub mal_id: Option<u32>,
+    pub status: ListStatus,
+    pub score: Option<u8>,
+    pub watched_episodes: u32,
+    pub started_at: Option<NaiveDate>,
+    pub finished_at: Option<NaiveDate>,
+}
+
+impl From<(entity::library_entries::Model, entity::series::Model)> for ExportEntry {
+    fn from((entry, series): (entity::library_entries::Model, entity::series::Model)) -> Self {
+        let date =
+            |x: Option<String>| x.and_then(|x| NaiveDate::parse_from_str(&x, "%Y-%m-%d").ok());
+
+        Self {
+            series_id: series.id,
+            mal_id: series.mal_id.and_then(|x| u32::try_from(x).ok()),
+            // Anything on the list without a status is something that is going to be watched
+            status: entry
+                .list_status
+                .and_then(|x| x.parse().ok())
+                .unwrap_or(ListStatus::PlanToWatch),
+            score: entry.score.and_then(|x| u8::try_from(x).ok()),
+            watched_episodes: entry
+                .watched_episodes
+                .and_then(|x| u32::try_from(x).ok())
+                .unwrap_or_default(),
+            started_at: date(entry.started_watching_at),
+            finished_at: date(entry.finished_watching_at),
+            name: series.name,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportWarning {
+    pub series_id: i32,
+    pub name: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Export {
+    pub format: ExportFormat,
+    pub file_name: String,
+    pub mime_type: String,
+    pub content: String,
+    pub warnings: Vec<ExportWarning>,
+}
+
+/// Export the list of a single user
+pub async fn export_library(
+    db: &DatabaseConnection,
+    user_id: i32,
+    format: ExportFormat,
+) -> Result<Export> {
+    let entries = entries::for_user(db, user_id)
+        .await?
+        .into_iter()
+        .map(ExportEntry::from)
+        .collect::<Vec<_>>();
+
+    let warnings = entries
+        .iter()
+        .filter(|x| x.mal_id.is_none())
+        .map(|x| ExportWarning {
+            series_id: x.series_id,
+            name: x.name.clone(),
+            message: match format {
+                ExportFormat::MalXml => "Series has no MyAnimeList id and was left out".to_string(),
+                _ => "Series has no MyAnimeList id and will have to be matched by name".to_string(),
+            },
+        })
+        .collect();
+
+    let content = match format {
+        ExportFormat::MalXml => mal_xml::write(&entries),
+        ExportFormat::AnilistJson => write_anilist_json(&entries)?,
+        ExportFormat::Csv => write_csv(&entries),
+    };
+
+    Ok(Export {
+        format,
+        file_name: format.file_name().to_string(),
+        mime_type: format.mime_type().to_string(),
+        content,
+        warnings,
+    })
+}
+
+/// Write entries grouped the same way as the `AniList` API's `MediaListCollection`
+fn write_anilist_json(entries: &[ExportEntry]) -> Result<String> {
+    let date = |x: Option<NaiveDate>| {
+        x.map_or_else(
+            || json!({ "year": null, "month": null, "day": null }),
+            |x| json!({ "year": x.year(), "month": x.month(), "day": x.day() }),
+        )
+    };
+
+    let lists = [
+        ListStatus::Watching,
+        ListStatus::Completed,
+        ListStatus::OnHold,
+        ListStatus::Dropped,
+        ListStatus::PlanToWatch,
+    ]
+    .into_iter()
+    .map(|status| {
+        let list_entries = entries
+            .iter()
+            .filter(|x| x.status == status)
+            .map(|x| {
+                json!({
+                    "status": status.as_anilist(),
+                    "score": x.score.unwrap_or_default(),
+                    "progress": x.watched_episodes,
+                    "startedAt": date(x.started_at),
+                    "completedAt": date(x.finished_at),
+                    "media": {
+                        "idMal": x.mal_id,
+                        "title": {
+                            "userPreferred": x.name,
+                        },
+                    },
+                })
+            })
+            .collect::<Vec<_>>();
+
+        json!({
+            "name": status.as_mal_export(),
+            "status": status.as_anilist(),
+            "entries": list_entries,
+        })
+    })
+    .collect::<Vec<_>>();
+
+    Ok(serde_json::to_string_pretty(&json!({ "lists": lists }))?)
+}
+
+fn write_csv(entries: &[ExportEntry]) -> String {
+    fn field(value: &str) -> String {
+        if value.contains([',', '"', '\n', '\r']) {
+            format!("\"{}\"", value.replace('"', "\"\""))
+        } else {
+            value.to_string()
+        }
+    }
+
+    let mut lines =
+        vec!["name,mal_id,status,score,watched_episodes,started_at,finished_at".to_string()];
+
+    for entry in entries {
+        let opt = |x: Option<String>| x.unwrap_or_default();
+
+        lines.push(
+            [
+                field(&entry.name),
+                opt(entry.mal_id.map(|x| x.to_string())),
+                entry.status.to_string(),
+                opt(entry.score.map(|x| x.to_string())),
+                entry.watched_episodes.to_string(),
+                opt(entry.started_at.map(|x| x.to_string())),
+                opt(entry.finished_at.map(|x| x.to_string())),
+            ]
+            .join(","),
+        );
+    }
+
+    lines.join("\n") + "\n"
+}
@@ -0,0 +1,230 @@
+//! Tracking whether fetching the info of a source works.
+//!
+//! Every fetch of a source's info is recorded on its `series_sources` row. Sources that keep
+//! failing get marked as broken so background jobs stop hammering them, until a fetch of them
+//! succeeds again.
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use log::{debug, warn};
+use sea_orm::{prelude::*, sea_query::Expr};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::sources;
+use crate::{
+    config::CONFIG,
+    metadata::{self, AnimeInfo},
+};
+
+/// Why fetching a source failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum SourceErrorKind {
+    /// The site couldn't be reached or responded with an error
+    Upstream,
+    /// The site responded, but not with what was expected
+    Parse,
+    /// The site took too long to respond
+    Timeout,
+    /// The source is for a site that isn't supported
+    UnsupportedSite,
+}
+
+impl SourceErrorKind {
+    pub fn of(error: &anyhow::Error) -> Self {
+        if let Some(e) = error
+            .chain()
+            .find_map(|x| x.downcast_ref::<reqwest::Error>())
+        {
+            return if e.is_timeout() {
+                Self::Timeout
+            } else if e.is_decode() {
+                Self::Parse
+            } else {
+                Self::Upstream
+            };
+        }
+
+        if error
+            .chain()
+            .any(|x| x.downcast_ref::<tokio::time::error::Elapsed>().is_some())
+        {
+            return Self::Timeout;
+        }
+
+        Self::Parse
+    }
+
+    /// Same as the serialized name, so stored kinds read the same as the ones in responses
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Upstream => "upstream",
+            Self::Parse => "parse",
+            Self::Timeout => "timeout",
+            Self::UnsupportedSite => "unsupportedSite",
+        }
+    }
+}
+
+/// A failed fetch of a source's info
+#[derive(Debug)]
+pub struct SourceError {
+    pub kind: SourceErrorKind,
+    pub error: anyhow::Error,
+}
+
+impl std::fmt::Display for SourceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.error.fmt(f)
+    }
+}
+
+/// Series info and whether it was fetched just now
+#[derive(Debug)]
+pub struct Fetched {
+    pub info: AnimeInfo,
+    pub from_cache: bool,
+}
+
+/// Like [`fetch_info`], but reuses info fetched recently
+pub async fn fetch_info_cached<C>(
+    db: &C,
+    source: &entity::series_sources::Model,
+) -> Result<Fetched, SourceError>
+where
+    C: ConnectionTrait,
+{
+    let cached = sources::meta(source)
+        .ok()
+        .and_then(|meta| metadata::cache::series_info(&meta));
+
+    if let Some(info) = cached {
+        return Ok(Fetched {
+            info,
+            from_cache: true,
+        });
+    }
+
+    fetch_info(db, source).await.map(|info| Fetched {
+        info,
+        from_cache: false,
+    })
+}
+
+/// Fetch the info of a source and record how it went.
+///
+/// Gives up once the configured source timeout passes.
+pub async fn fetch_info<C>(
+    db: &C,
+    source: &entity::series_sources::Model,
+) -> Result<AnimeInfo, SourceError>
+where
+    C: ConnectionTrait,
+{
+    let meta = match sources::meta(source) {
+        Ok(meta) => meta,
+        Err(error) => {
+            return Err(SourceError {
+                kind: SourceErrorKind::UnsupportedSite,
+                error,
+            });
+        }
+    };
+
+    let timeout = CONFIG.library.source_timeout;
+    let result = tokio::time::timeout(timeout, metadata::series_info(meta.clone()))
+        .await
+        .map_err(|e| anyhow::Error::from(e).context(format!("No response within {timeout:?}")))
+        .and_then(|x| x)
+        .map_err(|error| SourceError {
+            kind: SourceErrorKind::of(&error),
+            error,
+        });
+
+    if let Ok(info) = &result {
+        metadata::cache::put_series_info(&meta, info);
+    }
+
+    let recorded = match &result {
+        Ok(info) => record_success(db, source.id, info.next_release_estimate).await,
+        Err(e) => record_failure(db, source.id, e).await,
+    };
+    if let Err(e) = recorded {
+        warn!("Failed to record health of source {}: {e:?}", source.id);
+    }
+
+    result
+}
+
+/// Doesn't touch `updated_at` since the source itself didn't change.
+///
+/// The next release the site expects is kept too, for sorting the library by it.
+pub async fn record_success<C>(
+    db: &C,
+    source_id: i32,
+    next_release_at: Option<DateTime<Utc>>,
+) -> Result<()>
+where
+    C: ConnectionTrait,
+{
+    entity::series_sources::Entity::update_many()
+        .col_expr(
+            entity::series_sources::Column::NextReleaseAt,
+            Expr::value(next_release_at.map(|x| x.to_rfc3339())),
+        )
+        .col_expr(
+            entity::series_sources::Column::LastSuccessAt,
+            Expr::value(Utc::now().to_rfc3339()),
+        )
+        .col_expr(
+            entity::series_sources::Column::ConsecutiveFailures,
+            Expr::value(0),
+        )
+        .col_expr(entity::series_sources::Column::Broken, Expr::value(false))
+        .filter(entity::series_sources::Column::Id.eq(source_id))
+        .exec(db)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn record_failure<C>(db: &C, source_id: i32, error: &SourceError) -> Result<()>
+where
+    C: ConnectionTrait,
+{
+    debug!(
+        "Fetching source {source_id} failed ({}): {:?}",
+        error.kind.as_str(),
+        error.error
+    );
+
+    let failures = Expr::col(entity::series_sources::Column::ConsecutiveFailures).add(1);
+
+    entity::series_sources::Entity::update_many()
+        .col_expr(
+            entity::series_sources::Column::LastFailureAt,
+            Expr::value(Utc::now().to_rfc3339()),
+        )
+        .col_expr(
+            entity::series_sources::Column::ConsecutiveFailures,
+            failures.clone(),
+        )
+        .col_expr(
+            entity::series_sources::Column::LastErrorKind,
+            Expr::value(error.kind.as_str()),
+        )
+        .col_expr(
+            entity::series_sources::Column::LastError,
+            Expr::value(error.to_string()),
+        )
+        .col_expr(
+            entity::series_sources::Column::Broken,
+            Expr::expr(failures).gte(CONFIG.library.source_broken_after.max(1)),
+        )
+        .filter(entity::series_sources::Column::Id.eq(source_id))
+        .exec(db)
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,255 @@
+use anyhow::Result;
+use log::{debug, trace, warn};
+use sea_orm::{prelude::*, ActiveValue, IntoActiveModel, TransactionTrait};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::{
+    entries,
+    mal_xml::{MalExport, MalExportEntry},
+    sources,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum ImportOutcome {
+    Created,
+    Updated,
+    Skipped,
+    Conflicting,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportEntryReport {
+    pub mal_id: u32,
+    pub title: String,
+    pub outcome: ImportOutcome,
+    pub series_id: Option<i32>,
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportSummary {
+    pub created: usize,
+    pub updated: usize,
+    pub skipped: usize,
+    pub conflicting: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportReport {
+    pub summary: ImportSummary,
+    /// Whether source discovery was started for the created and updated series.
+    /// Discovery runs in the background, so newly found sources show up on the series later.
+    pub discovery_queued: bool,
+    pub entries: Vec<ImportEntryReport>,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MalImportOptions {
+    pub discover_sources: bool,
+}
+
+/// Create or update a user's library entries from a MAL export.
+///
+/// Series are matched on `mal_id`, so importing the same file twice is a no-op.
+/// A series that has no `mal_id` yet but has the same name as an entry gets linked to it.
+/// Series that don't exist yet are created and shared with everyone else.
+pub async fn import_mal_export(
+    db: &DatabaseConnection,
+    user_id: i32,
+    export: MalExport,
+    options: MalImportOptions,
+) -> Result<ImportReport> {
+    let txn = db.begin().await?;
+
+    let mut entries = Vec::with_capacity(export.entries.len());
+    for entry in &export.entries {
+        let report = import_entry(&txn, user_id, entry).await?;
+        trace!("Import result for {:?}: {:?}", entry.title, report.outcome);
+        entries.push(report);
+    }
+
+    txn.commit().await?;
+
+    let mut summary = ImportSummary::default();
+    for entry in &entries {
+        match entry.outcome {
+            ImportOutcome::Created => summary.created += 1,
+            ImportOutcome::Updated => summary.updated += 1,
+            ImportOutcome::Skipped => summary.skipped += 1,
+            ImportOutcome::Conflicting => summary.conflicting += 1,
+        }
+    }
+    debug!("Imported MAL export: {:?}", summary);
+
+    if options.discover_sources {
+        let to_discover = entries
+            .iter()
+            .filter(|x| matches!(x.outcome, ImportOutcome::Created | ImportOutcome::Updated))
+            .filter_map(|x| Some((x.series_id?, x.title.clone(), x.mal_id)))
+            .collect::<Vec<_>>();
+
+        let db = db.clone();
+        tokio::spawn(async move {
+            for (series_id, title, mal_id) in to_discover {
+                if let Err(e) = sources::discover_and_attach(&db, series_id, &title, mal_id).await {
+                    warn!("Failed to discover sources for {title:?}: {e:?}");
+                }
+            }
+        });
+    }
+
+    Ok(ImportReport {
+        summary,
+        discovery_queued: options.discover_sources,
+        entries,
+    })
+}
+
+async fn import_entry<C>(db: &C, user_id: i32, entry: &MalExportEntry) -> Result<ImportEntryReport>
+where
+    C: ConnectionTrait,
+{
+    let report = |outcome, series_id, message: Option<&str>| ImportEntryReport {
+        mal_id: entry.mal_id,
+        title: entry.title.clone(),
+        outcome,
+        series_id,
+        message: message.map(ToString::to_string),
+    };
+
+    if entry.mal_id == 0 {
+        return Ok(report(
+            ImportOutcome::Skipped,
+            None,
+            Some("Entry has no MyAnimeList id"),
+        ));
+    }
+
+    #[allow(clippy::cast_possible_wrap)]
+    let mal_id = entry.mal_id as i32;
+
+    let existing = entity::series::Entity::find()
+        .filter(entity::series::Column::MalId.eq(mal_id))
+        .one(db)
+        .await?;
+
+    let existing = match existing {
+        Some(existing) => Some(existing),
+        None => {
+            let by_name = entity::series::Entity::find()
+                .filter(entity::series::Column::Name.eq(entry.title.as_str()))
+                .one(db)
+                .await?;
+
+            match by_name {
+                Some(series) if series.mal_id.is_some() => {
+                    return Ok(report(
+                        ImportOutcome::Conflicting,
+                        Some(series.id),
+                        Some(&format!(
+                            "A series with this name is already linked to MyAnimeList id {}",
+                            series.mal_id.unwrap_or_default()
+                        )),
+                    ));
+                }
+                x => x,
+            }
+        }
+    };
+
+    let list_status = entry.list_status().map(|x| x.to_string());
+    let score = entry.score().map(i32::from);
+    #[allow(clippy::cast_possible_wrap)]
+    let watched_episodes = Some(entry.watched_episodes as i32);
+    let started_watching_at = entry.started_at().map(|x| x.to_string());
+    let finished_watching_at = entry.finished_at().map(|x| x.to_string());
+    let list_updated_at = Some(chrono::Utc::now().to_rfc3339());
+
+    let (series_id, outcome) = link_series(db, entry, mal_id, existing).await?;
+
+    let existing_entry = entries::find(db, user_id, series_id).await?;
+
+    let unchanged = existing_entry.as_ref().is_some_and(|existing| {
+        existing.list_status == list_status
+            && existing.score == score
+            && existing.watched_episodes == watched_episodes
+            && existing.started_watching_at == started_watching_at
+            && existing.finished_watching_at == finished_watching_at
+    });
+
+    if unchanged && outcome.is_none() {
+        return Ok(report(
+            ImportOutcome::Skipped,
+            Some(series_id),
+            Some("Already up to date"),
+        ));
+    }
+
+    if !unchanged {
+        let mut model = match existing_entry.clone() {
+            Some(existing) => existing.into_active_model(),
+            None => entries::find_or_new(db, user_id, series_id).await?,
+        };
+        model.list_status = ActiveValue::Set(list_status);
+        model.score = ActiveValue::Set(score);
+        model.watched_episodes = ActiveValue::Set(watched_episodes);
+        model.started_watching_at = ActiveValue::Set(started_watching_at);
+        model.finished_watching_at = ActiveValue::Set(finished_watching_at);
+        model.list_updated_at = ActiveValue::Set(list_updated_at);
+        model.save(db).await?;
+    }
+
+    let outcome = match (outcome, existing_entry) {
+        (Some(outcome), _) => outcome,
+        (None, Some(_)) => ImportOutcome::Updated,
+        (None, None) => ImportOutcome::Created,
+    };
+
+    Ok(report(outcome, Some(series_id), None))
+}
+
+/// Make sure the series of an entry exists and is linked to its MAL id.
+///
+/// Returns the series and whether it had to be created or linked.
+async fn link_series<C>(
+    db: &C,
+    entry: &MalExportEntry,
+    mal_id: i32,
+    existing: Option<entity::series::Model>,
+) -> Result<(i32, Option<ImportOutcome>)>
+where
+    C: ConnectionTrait,
+{
+    let linked = match existing {
+        Some(existing) if existing.mal_id == Some(mal_id) => (existing.id, None),
+        Some(existing) => {
+            entity::series::ActiveModel {
+                id: ActiveValue::Unchanged(existing.id),
+                mal_id: ActiveValue::Set(Some(mal_id)),
+                ..Default::default()
+            }
+            .update(db)
+            .await?;
+
+            (existing.id, Some(ImportOutcome::Updated))
+        }
+        None => {
+            let created = entity::series::ActiveModel {
+                name: ActiveValue::Set(entry.title.clone()),
+                mal_id: ActiveValue::Set(Some(mal_id)),
+                ..Default::default()
+            }
+            .insert(db)
+            .await?;
+
+            (created.id, Some(ImportOutcome::Created))
+        }
+    };
+
+    Ok(linked)
+}
@@ -0,0 +1,230 @@
+use anyhow::{anyhow, Context, Result};
+use reqwest::{Client, RequestBuilder, Response};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::config::CONFIG;
+
+/// How many list entries to ask for per page. This is the most MAL allows.
+const LIST_PAGE_SIZE: u32 = 1000;
+
+#[derive(Debug, Clone)]
+pub struct MalClientOptions {
+    pub client_id: String,
+    pub client_secret: Option<String>,
+    pub redirect_url: Option<String>,
+    pub api_url: String,
+    pub auth_url: String,
+}
+
+/// Client for the parts of the MAL API that act on behalf of a user
+#[derive(Debug, Clone)]
+pub struct MalClient {
+    http: Client,
+    options: MalClientOptions,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TokenResponse {
+    pub token_type: String,
+    /// Seconds until the access token expires
+    pub expires_in: i64,
+    pub access_token: String,
+    pub refresh_token: String,
+}
+
+/// The `my_list_status` of an anime as returned by the API
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemoteListStatus {
+    pub status: Option<String>,
+    /// Score out of 10, 0 if not scored
+    #[serde(default)]
+    pub score: u8,
+    #[serde(default)]
+    pub num_episodes_watched: u32,
+    pub start_date: Option<String>,
+    pub finish_date: Option<String>,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemoteListNode {
+    pub id: u32,
+    pub title: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemoteListEntry {
+    pub node: RemoteListNode,
+    pub list_status: RemoteListStatus,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Paging {
+    next: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct ListPage {
+    data: Vec<RemoteListEntry>,
+    paging: Option<Paging>,
+}
+
+/// Fields sent to `PATCH /anime/{id}/my_list_status`
+#[derive(Debug, Clone, Serialize)]
+pub struct ListStatusUpdate {
+    pub status: &'static str,
+    pub score: u8,
+    pub num_watched_episodes: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_date: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub finish_date: Option<String>,
+}
+
+impl MalClient {
+    pub fn new(options: MalClientOptions) -> Result<Self> {
+        let http = Client::builder().deflate(true).gzip(true).build()?;
+
+        Ok(Self { http, options })
+    }
+
+    pub fn from_config() -> Result<Self> {
+        Self::new(MalClientOptions {
+            client_id: CONFIG.mal.client_id.clone(),
+            client_secret: CONFIG.mal.client_secret.clone(),
+            redirect_url: CONFIG.mal.redirect_url.clone(),
+            api_url: CONFIG.mal.api_url.clone(),
+            auth_url: CONFIG.mal.auth_url.clone(),
+        })
+    }
+
+    /// URL to send the user to so they can allow access to their list.
+    ///
+    /// MAL only supports the `plain` PKCE method, so the challenge is the verifier itself.
+    pub fn authorization_url(&self, state: &str, code_verifier: &str) -> Result<String> {
+        let mut url = Url::parse(&format!("{}/authorize", self.options.auth_url))?;
+
+        {
+            let mut query = url.query_pairs_mut();
+            query
+                .append_pair("response_type", "code")
+                .append_pair("client_id", &self.options.client_id)
+                .append_pair("state", state)
+                .append_pair("code_challenge", code_verifier)
+                .append_pair("code_challenge_method", "plain");
+            if let Some(redirect_url) = &self.options.redirect_url {
+                query.append_pair("redirect_uri", redirect_url);
+            }
+        }
+
+        Ok(url.to_string())
+    }
+
+    pub async fn exchange_code(&self, code: &str, code_verifier: &str) -> Result<TokenResponse> {
+        let mut form = vec![
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("code_verifier", code_verifier),
+        ];
+        if let Some(redirect_url) = &self.options.redirect_url {
+            form.push(("redirect_uri", redirect_url));
+        }
+
+        self.token_request(&form).await
+    }
+
+    pub async fn refresh_token(&self, refresh_token: &str) -> Result<TokenResponse> {
+        self.token_request(&[
+            ("grant_type", "refresh_token"),
+            ("refresh_token", refresh_token),
+        ])
+        .await
+    }
+
+    async fn token_request(&self, form: &[(&str, &str)]) -> Result<TokenResponse> {
+        let mut form = form.to_vec();
+        form.push(("client_id", &self.options.client_id));
+        if let Some(client_secret) = &self.options.client_secret {
+            form.push(("client_secret", client_secret));
+        }
+
+        let resp = self
+            .http
+            .post(format!("{}/token", self.options.auth_url))
+            .form(&form)
+            .send()
+            .await?;
+
+        parse_response(resp)
+            .await
+            .context("Failed to get token from MAL")
+    }
+
+    /// Get every entry of the authorized user's list
+    pub async fn list(&self, access_token: &str) -> Result<Vec<RemoteListEntry>> {
+        let mut entries = vec![];
+        let mut url = Some(format!(
+            "{}/users/@me/animelist?fields=list_status&nsfw=true&limit={}",
+            self.options.api_url, LIST_PAGE_SIZE,
+        ));
+
+        while let Some(page_url) = url {
+            let resp = self
+                .authorized(self.http.get(page_url), access_token)
+                .send()
+                .await?;
+            let page: ListPage = parse_response(resp)
+                .await
+                .context("Failed to get list from MAL")?;
+
+            entries.extend(page.data);
+            url = page.paging.and_then(|x| x.next);
+        }
+
+        Ok(entries)
+    }
+
+    pub async fn update_list_status(
+        &self,
+        access_token: &str,
+        mal_id: u32,
+        update: &ListStatusUpdate,
+    ) -> Result<RemoteListStatus> {
+        let resp = self
+            .authorized(
+                self.http.patch(format!(
+                    "{}/anime/{}/my_list_status",
+                    self.options.api_url, mal_id
+                )),
+                access_token,
+            )
+            .form(update)
+            .send()
+            .await?;
+
+        parse_response(resp)
+            .await
+            .with_context(|| format!("Failed to update list status of {mal_id} on MAL"))
+    }
+
+    fn authorized(&self, request: RequestBuilder, access_token: &str) -> RequestBuilder {
+        request
+            .bearer_auth(access_token)
+            .header("X-Mal-Client-Id", &self.options.client_id)
+    }
+}
+
+async fn parse_response<T>(resp: Response) -> Result<T>
+where
+    T: for<'de> Deserialize<'de>,
+{
+    let status = resp.status();
+    let body = resp.text().await?;
+
+    if !status.is_success() {
+        return Err(anyhow!("MAL responded with {status}: {body}"));
+    }
+
+    serde_json::from_str(&body).map_err(|e| anyhow!("Failed to parse MAL response: {e}"))
+}
@@ -0,0 +1,708 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use log::{debug, error, info, trace, warn};
+use rand::{distributions::Alphanumeric, Rng};
+use sea_orm::{prelude::*, ActiveValue, QueryOrder, TryIntoModel};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use self::client::{ListStatusUpdate, MalClient, RemoteListEntry, RemoteListStatus, TokenResponse};
+use super::{entries, ListStatus};
+use crate::config::CONFIG;
+
+pub mod client;
+
+/// Length of the PKCE code verifier. MAL accepts 43 to 128 characters.
+const CODE_VERIFIER_LENGTH: usize = 96;
+/// How long a started authorization can take to finish
+const AUTHORIZATION_TIMEOUT_MINUTES: i64 = 30;
+/// Refresh access tokens this long before they actually expire
+const TOKEN_EXPIRY_MARGIN_MINUTES: i64 = 5;
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MalSyncStatus {
+    pub authorized: bool,
+    pub expires_at: Option<String>,
+    pub last_pulled_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum SyncAction {
+    /// The local entry was sent to MAL
+    Pushed,
+    /// The local entry was overwritten with the one from MAL
+    Pulled,
+    /// A series was created for an entry that only exists on MAL
+    Created,
+    Failed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum SyncSide {
+    Local,
+    Remote,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncEntryReport {
+    pub series_id: Option<i32>,
+    pub mal_id: u32,
+    pub name: String,
+    pub action: SyncAction,
+    /// Set when both sides changed since the last sync, to the side whose change was kept
+    pub conflict_winner: Option<SyncSide>,
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncSummary {
+    pub pushed: usize,
+    pub pulled: usize,
+    pub created: usize,
+    pub unchanged: usize,
+    pub failed: usize,
+    pub conflicts: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncReport {
+    pub summary: SyncSummary,
+    /// Only entries that were acted on, unchanged ones are left out
+    pub entries: Vec<SyncEntryReport>,
+}
+
+/// The parts of a list entry that are synced, in a form both sides can be compared in
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ListFields {
+    status: Option<ListStatus>,
+    score: Option<u8>,
+    watched_episodes: u32,
+    started_at: Option<NaiveDate>,
+    finished_at: Option<NaiveDate>,
+}
+
+impl ListFields {
+    fn from_local(entry: &entity::library_entries::Model) -> Self {
+        Self {
+            status: entry.list_status.as_deref().and_then(|x| x.parse().ok()),
+            score: entry.score.and_then(|x| u8::try_from(x).ok()),
+            watched_episodes: entry
+                .watched_episodes
+                .and_then(|x| u32::try_from(x).ok())
+                .unwrap_or_default(),
+            started_at: parse_date(entry.started_watching_at.as_deref()),
+            finished_at: parse_date(entry.finished_watching_at.as_deref()),
+        }
+    }
+
+    fn from_remote(status: &RemoteListStatus) -> Self {
+        Self {
+            status: status.status.as_deref().and_then(ListStatus::from_mal_api),
+            score: Some(status.score).filter(|x| *x > 0),
+            watched_episodes: status.num_episodes_watched,
+            started_at: parse_date(status.start_date.as_deref()),
+            finished_at: parse_date(status.finish_date.as_deref()),
+        }
+    }
+
+    fn to_update(&self) -> Option<ListStatusUpdate> {
+        Some(ListStatusUpdate {
+            status: self.status?.as_mal_api(),
+            score: self.score.unwrap_or_default(),
+            num_watched_episodes: self.watched_episodes,
+            start_date: self.started_at.map(|x| x.to_string()),
+            finish_date: self.finished_at.map(|x| x.to_string()),
+        })
+    }
+
+    fn apply(&self, model: &mut entity::library_entries::ActiveModel) {
+        #[allow(clippy::cast_possible_wrap)]
+        let watched_episodes = self.watched_episodes as i32;
+
+        model.list_status = ActiveValue::Set(self.status.map(|x| x.to_string()));
+        model.score = ActiveValue::Set(self.score.map(i32::from));
+        model.watched_episodes = ActiveValue::Set(Some(watched_episodes));
+        model.started_watching_at = ActiveValue::Set(self.started_at.map(|x| x.to_string()));
+        model.finished_watching_at = ActiveValue::Set(self.finished_at.map(|x| x.to_string()));
+    }
+}
+
+/// MAL returns partial dates (eg. `2020-01`) for entries where only part of the date was filled in.
+/// Those are treated as not set.
+fn parse_date(date: Option<&str>) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(date?, "%Y-%m-%d").ok()
+}
+
+fn parse_timestamp(timestamp: Option<&str>) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(timestamp?)
+        .ok()
+        .map(|x| x.with_timezone(&Utc))
+}
+
+fn random_string(length: usize) -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(length)
+        .map(char::from)
+        .collect()
+}
+
+pub async fn status(db: &DatabaseConnection, user_id: i32) -> Result<MalSyncStatus> {
+    let auth = find_auth(db, user_id).await?;
+
+    Ok(MalSyncStatus {
+        authorized: auth.is_some(),
+        expires_at: auth.as_ref().map(|x| x.expires_at.clone()),
+        last_pulled_at: auth.and_then(|x| x.last_pulled_at),
+    })
+}
+
+async fn find_auth(
+    db: &DatabaseConnection,
+    user_id: i32,
+) -> Result<Option<entity::mal_auth::Model>> {
+    let auth = entity::mal_auth::Entity::find()
+        .filter(entity::mal_auth::Column::UserId.eq(user_id))
+        .one(db)
+        .await?;
+
+    Ok(auth)
+}
+
+/// Start the authorization code flow, returning the URL the user needs to visit
+pub async fn start_authorization(
+    db: &DatabaseConnection,
+    client: &MalClient,
+    user_id: i32,
+) -> Result<String> {
+    let expired_before = Utc::now() - Duration::minutes(AUTHORIZATION_TIMEOUT_MINUTES);
+    entity::mal_oauth_states::Entity::delete_many()
+        .filter(entity::mal_oauth_states::Column::CreatedAt.lt(expired_before.to_rfc3339()))
+        .exec(db)
+        .await?;
+
+    let state = random_string(32);
+    let code_verifier = random_string(CODE_VERIFIER_LENGTH);
+
+    let url = client.authorization_url(&state, &code_verifier)?;
+
+    entity::mal_oauth_states::ActiveModel {
+        state: ActiveValue::Set(state),
+        code_verifier: ActiveValue::Set(code_verifier),
+        user_id: ActiveValue::Set(Some(user_id)),
+        ..Default::default()
+    }
+    .insert(db)
+    .await?;
+
+    Ok(url)
+}
+
+/// A started authorization, waiting for the user to come back from MAL
+#[derive(Debug, Clone)]
+pub struct PendingAuthorization {
+    pub user_id: i32,
+    pub code_verifier: String,
+}
+
+/// Look up and forget an authorization the user started.
+///
+/// Returns `None` for states that were never handed out, have expired or were handed out to
+/// someone else. Otherwise anyone could get a victim to approve the authorization they started
+/// and have the victim's MAL account linked to their own user.
+pub async fn take_pending_authorization(
+    db: &DatabaseConnection,
+    state: &str,
+    user_id: i32,
+) -> Result<Option<PendingAuthorization>> {
+    let Some(pending) = entity::mal_oauth_states::Entity::find_by_id(state)
+        .filter(entity::mal_oauth_states::Column::UserId.eq(user_id))
+        .one(db)
+        .await?
+    else {
+        return Ok(None);
+    };
+
+    entity::mal_oauth_states::Entity::delete_by_id(state)
+        .exec(db)
+        .await?;
+
+    let expired = parse_timestamp(pending.created_at.as_deref()).map_or(true, |x| {
+        x < Utc::now() - Duration::minutes(AUTHORIZATION_TIMEOUT_MINUTES)
+    });
+    if expired {
+        return Ok(None);
+    }
+
+    Ok(pending.user_id.map(|user_id| PendingAuthorization {
+        user_id,
+        code_verifier: pending.code_verifier,
+    }))
+}
+
+/// Finish the authorization code flow by exchanging the code for tokens
+pub async fn finish_authorization(
+    db: &DatabaseConnection,
+    client: &MalClient,
+    pending: &PendingAuthorization,
+    code: &str,
+) -> Result<()> {
+    let token = client.exchange_code(code, &pending.code_verifier).await?;
+
+    // Only one MAL account can be linked per user, authorizing again replaces it
+    unlink(db, pending.user_id).await?;
+    save_token(db, pending.user_id, None, &token).await?;
+
+    info!("Linked MAL account of user {}", pending.user_id);
+
+    Ok(())
+}
+
+pub async fn unlink(db: &DatabaseConnection, user_id: i32) -> Result<()> {
+    entity::mal_auth::Entity::delete_many()
+        .filter(entity::mal_auth::Column::UserId.eq(user_id))
+        .exec(db)
+        .await?;
+
+    Ok(())
+}
+
+async fn save_token(
+    db: &DatabaseConnection,
+    user_id: i32,
+    existing: Option<i32>,
+    token: &TokenResponse,
+) -> Result<entity::mal_auth::Model> {
+    let expires_at = Utc::now() + Duration::seconds(token.expires_in);
+
+    let model = entity::mal_auth::ActiveModel {
+        id: existing.map_or(ActiveValue::NotSet, ActiveValue::Unchanged),
+        user_id: ActiveValue::Set(Some(user_id)),
+        access_token: ActiveValue::Set(token.access_token.clone()),
+        refresh_token: ActiveValue::Set(token.refresh_token.clone()),
+        expires_at: ActiveValue::Set(expires_at.to_rfc3339()),
+        ..Default::default()
+    };
+
+    let saved = if existing.is_some() {
+        model.update(db).await?
+    } else {
+        model.insert(db).await?
+    };
+
+    Ok(saved)
+}
+
+/// Get a usable access token, refreshing it if it is about to expire.
+///
+/// Returns `None` if the user has no MAL account linked.
+async fn access_token(
+    db: &DatabaseConnection,
+    client: &MalClient,
+    user_id: i32,
+) -> Result<Option<entity::mal_auth::Model>> {
+    let Some(auth) = find_auth(db, user_id).await? else {
+        return Ok(None);
+    };
+
+    let expires_soon = parse_timestamp(Some(&auth.expires_at)).map_or(true, |x| {
+        x < Utc::now() + Duration::minutes(TOKEN_EXPIRY_MARGIN_MINUTES)
+    });
+    if !expires_soon {
+        return Ok(Some(auth));
+    }
+
+    debug!("Refreshing MAL access token of user {user_id}");
+    let token = client.refresh_token(&auth.refresh_token).await?;
+    let auth = save_token(db, user_id, Some(auth.id), &token).await?;
+
+    Ok(Some(auth))
+}
+
+/// Whether the local list entry changed since it was last synced, and when
+fn local_change(entry: &entity::library_entries::Model) -> Option<DateTime<Utc>> {
+    entry.list_status.as_ref()?;
+
+    let synced_at = parse_timestamp(entry.mal_synced_at.as_deref());
+    let changed_at = match (parse_timestamp(entry.list_updated_at.as_deref()), synced_at) {
+        (Some(changed_at), _) => changed_at,
+        // Entries from before syncing existed have no change time of their own
+        (None, None) => parse_timestamp(entry.updated_at.as_deref())?,
+        (None, Some(_)) => return None,
+    };
+
+    match synced_at {
+        Some(synced_at) if synced_at >= changed_at => None,
+        _ => Some(changed_at),
+    }
+}
+
+/// Whether the remote list entry changed since it was last synced, and when
+fn remote_change(
+    entry: &entity::library_entries::Model,
+    remote: &RemoteListStatus,
+) -> Option<DateTime<Utc>> {
+    if entry.mal_list_updated_at.as_deref() == Some(remote.updated_at.as_str()) {
+        return None;
+    }
+
+    Some(parse_timestamp(Some(&remote.updated_at)).unwrap_or_else(Utc::now))
+}
+
+async fn mark_synced<C>(db: &C, entry_id: i32, remote_updated_at: &str) -> Result<()>
+where
+    C: ConnectionTrait,
+{
+    entity::library_entries::ActiveModel {
+        id: ActiveValue::Unchanged(entry_id),
+        mal_synced_at: ActiveValue::Set(Some(Utc::now().to_rfc3339())),
+        mal_list_updated_at: ActiveValue::Set(Some(remote_updated_at.to_string())),
+        ..Default::default()
+    }
+    .update(db)
+    .await?;
+
+    Ok(())
+}
+
+async fn push(
+    db: &DatabaseConnection,
+    client: &MalClient,
+    access_token: &str,
+    entry: &entity::library_entries::Model,
+    mal_id: u32,
+) -> Result<()> {
+    let Some(update) = ListFields::from_local(entry).to_update() else {
+        return Err(anyhow!("Entry has no list status to send"));
+    };
+
+    let remote = client
+        .update_list_status(access_token, mal_id, &update)
+        .await?;
+    mark_synced(db, entry.id, &remote.updated_at).await
+}
+
+async fn pull(
+    db: &DatabaseConnection,
+    entry: entity::library_entries::ActiveModel,
+    remote: &RemoteListStatus,
+) -> Result<entity::library_entries::Model> {
+    let mut model = entry;
+    model.mal_synced_at = ActiveValue::Set(Some(Utc::now().to_rfc3339()));
+    model.mal_list_updated_at = ActiveValue::Set(Some(remote.updated_at.clone()));
+    ListFields::from_remote(remote).apply(&mut model);
+
+    Ok(model.save(db).await?.try_into_model()?)
+}
+
+/// Send a user's entry for a series to MAL, if they have an account linked
+pub async fn push_entry(
+    db: &DatabaseConnection,
+    client: &MalClient,
+    user_id: i32,
+    series_id: i32,
+) -> Result<()> {
+    let Some(auth) = access_token(db, client, user_id).await? else {
+        trace!("User {user_id} has no MAL account linked, not pushing");
+        return Ok(());
+    };
+
+    let Some(series) = entity::series::Entity::find_by_id(series_id)
+        .one(db)
+        .await?
+    else {
+        return Err(anyhow!("Series {series_id} not found"));
+    };
+    let Some(mal_id) = series.mal_id.and_then(|x| u32::try_from(x).ok()) else {
+        trace!("Series {series_id} has no MAL id, not pushing");
+        return Ok(());
+    };
+    let Some(entry) = entries::find(db, user_id, series_id).await? else {
+        return Err(anyhow!(
+            "Series {series_id} is not on the list of user {user_id}"
+        ));
+    };
+
+    push(db, client, &auth.access_token, &entry, mal_id).await
+}
+
+/// Bring a user's list and their MAL list in line with each other.
+///
+/// Entries that changed only on one side since the last sync are copied to the other side.
+/// If both sides changed, the most recent change wins, with ties going to MAL.
+/// Entries that only exist on MAL get added to the list, creating (or linking by name) a series if needed.
+/// Entries that are removed from the MAL list are left alone locally.
+pub async fn sync(db: &DatabaseConnection, client: &MalClient, user_id: i32) -> Result<SyncReport> {
+    let Some(auth) = access_token(db, client, user_id).await? else {
+        return Err(anyhow!("No MAL account is linked"));
+    };
+
+    let mut remote = client
+        .list(&auth.access_token)
+        .await?
+        .into_iter()
+        .map(|x| (x.node.id, x))
+        .collect::<HashMap<_, _>>();
+    trace!("Got {} entries from MAL", remote.len());
+
+    let local = entries::for_user(db, user_id).await?;
+
+    let mut summary = SyncSummary::default();
+    let mut reports = vec![];
+
+    for (entry, series) in local {
+        let Some(mal_id) = series.mal_id.and_then(|x| u32::try_from(x).ok()) else {
+            continue;
+        };
+        let remote_entry = remote.remove(&mal_id);
+
+        let report = sync_entry(
+            db,
+            client,
+            &auth.access_token,
+            &entry,
+            &series,
+            mal_id,
+            remote_entry.as_ref(),
+        )
+        .await;
+
+        match report {
+            Some(report) => reports.push(report),
+            None => summary.unchanged += 1,
+        }
+    }
+
+    let mut remote_only = remote.into_values().collect::<Vec<_>>();
+    remote_only.sort_by_key(|x| x.node.id);
+    for entry in remote_only {
+        reports.push(add_from_remote(db, user_id, &entry).await);
+    }
+
+    for report in &reports {
+        match report.action {
+            SyncAction::Pushed => summary.pushed += 1,
+            SyncAction::Pulled => summary.pulled += 1,
+            SyncAction::Created => summary.created += 1,
+            SyncAction::Failed => summary.failed += 1,
+        }
+        if report.conflict_winner.is_some() {
+            summary.conflicts += 1;
+        }
+    }
+
+    entity::mal_auth::ActiveModel {
+        id: ActiveValue::Unchanged(auth.id),
+        last_pulled_at: ActiveValue::Set(Some(Utc::now().to_rfc3339())),
+        ..Default::default()
+    }
+    .update(db)
+    .await?;
+
+    debug!("Synced user {user_id} with MAL: {:?}", summary);
+
+    Ok(SyncReport {
+        summary,
+        entries: reports,
+    })
+}
+
+async fn sync_entry(
+    db: &DatabaseConnection,
+    client: &MalClient,
+    access_token: &str,
+    entry: &entity::library_entries::Model,
+    series: &entity::series::Model,
+    mal_id: u32,
+    remote: Option<&RemoteListEntry>,
+) -> Option<SyncEntryReport> {
+    let report = |action, conflict_winner, message: Option<String>| SyncEntryReport {
+        series_id: Some(series.id),
+        mal_id,
+        name: series.name.clone(),
+        action,
+        conflict_winner,
+        message,
+    };
+
+    let local_changed_at = local_change(entry);
+
+    let Some(remote) = remote else {
+        local_changed_at?;
+
+        return Some(match push(db, client, access_token, entry, mal_id).await {
+            Ok(()) => report(SyncAction::Pushed, None, None),
+            Err(e) => report(SyncAction::Failed, None, Some(e.to_string())),
+        });
+    };
+    let remote_changed_at = remote_change(entry, &remote.list_status);
+
+    // Both sides already agree, only remember that they do
+    if ListFields::from_local(entry) == ListFields::from_remote(&remote.list_status) {
+        if local_changed_at.is_some() || remote_changed_at.is_some() {
+            if let Err(e) = mark_synced(db, entry.id, &remote.list_status.updated_at).await {
+                return Some(report(SyncAction::Failed, None, Some(e.to_string())));
+            }
+        }
+
+        return None;
+    }
+
+    let (winner, conflict_winner) = match (local_changed_at, remote_changed_at) {
+        (None, None) => return None,
+        (Some(_), None) => (SyncSide::Local, None),
+        (None, Some(_)) => (SyncSide::Remote, None),
+        (Some(local), Some(remote)) if local > remote => (SyncSide::Local, Some(SyncSide::Local)),
+        (Some(_), Some(_)) => (SyncSide::Remote, Some(SyncSide::Remote)),
+    };
+
+    let result = match winner {
+        SyncSide::Local => push(db, client, access_token, entry, mal_id)
+            .await
+            .map(|()| SyncAction::Pushed),
+        SyncSide::Remote => pull(db, entry.clone().into(), &remote.list_status)
+            .await
+            .map(|_| SyncAction::Pulled),
+    };
+
+    Some(match result {
+        Ok(action) => report(action, conflict_winner, None),
+        Err(e) => report(SyncAction::Failed, conflict_winner, Some(e.to_string())),
+    })
+}
+
+async fn add_from_remote(
+    db: &DatabaseConnection,
+    user_id: i32,
+    remote: &RemoteListEntry,
+) -> SyncEntryReport {
+    let mut report = SyncEntryReport {
+        series_id: None,
+        mal_id: remote.node.id,
+        name: remote.node.title.clone(),
+        action: SyncAction::Created,
+        conflict_winner: None,
+        message: None,
+    };
+
+    let added = async {
+        let series_id = find_or_create_series(db, remote).await?;
+        let entry = entries::find_or_new(db, user_id, series_id).await?;
+        pull(db, entry, &remote.list_status).await
+    };
+
+    match added.await {
+        Ok(entry) => report.series_id = Some(entry.series_id),
+        Err(e) => {
+            report.action = SyncAction::Failed;
+            report.message = Some(e.to_string());
+        }
+    }
+
+    report
+}
+
+async fn find_or_create_series(db: &DatabaseConnection, remote: &RemoteListEntry) -> Result<i32> {
+    #[allow(clippy::cast_possible_wrap)]
+    let mal_id = remote.node.id as i32;
+
+    let by_mal_id = entity::series::Entity::find()
+        .filter(entity::series::Column::MalId.eq(mal_id))
+        .order_by_asc(entity::series::Column::Id)
+        .one(db)
+        .await?;
+    if let Some(series) = by_mal_id {
+        return Ok(series.id);
+    }
+
+    let by_name = entity::series::Entity::find()
+        .filter(entity::series::Column::Name.eq(remote.node.title.as_str()))
+        .one(db)
+        .await?;
+
+    let mut model = match by_name {
+        Some(series) if series.mal_id.is_some() => {
+            return Err(anyhow!(
+                "A series with this name is already linked to MAL id {}",
+                series.mal_id.unwrap_or_default()
+            ));
+        }
+        Some(series) => entity::series::ActiveModel {
+            id: ActiveValue::Unchanged(series.id),
+            ..Default::default()
+        },
+        None => entity::series::ActiveModel {
+            name: ActiveValue::Set(remote.node.title.clone()),
+            ..Default::default()
+        },
+    };
+    model.mal_id = ActiveValue::Set(Some(mal_id));
+
+    let saved = model.save(db).await?.try_into_model()?;
+
+    Ok(saved.id)
+}
+
+/// Push an entry in the background after it was changed locally
+pub fn spawn_push(db: DatabaseConnection, user_id: i32, series_id: i32) {
+    tokio::spawn(async move {
+        let client = match MalClient::from_config() {
+            Ok(x) => x,
+            Err(e) => {
+                error!("Failed to create MAL client: {e:?}");
+                return;
+            }
+        };
+
+        if let Err(e) = push_entry(&db, &client, user_id, series_id).await {
+            warn!("Failed to push series {series_id} of user {user_id} to MAL: {e:?}");
+        }
+    });
+}
+
+/// Sync every user that has a MAL account linked, every configured interval
+pub fn spawn_periodic_sync(db: DatabaseConnection) {
+    let interval = CONFIG.mal.sync_interval;
+
+    tokio::spawn(async move {
+        let client = match MalClient::from_config() {
+            Ok(x) => x,
+            Err(e) => {
+                error!("Failed to create MAL client, not syncing: {e:?}");
+                return;
+            }
+        };
+
+        let mut ticker = tokio::time::interval(interval.max(std::time::Duration::from_secs(60)));
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+
+            let linked = match entity::mal_auth::Entity::find().all(&db).await {
+                Ok(x) => x,
+                Err(e) => {
+                    warn!("Failed to get linked MAL accounts: {e:?}");
+                    continue;
+                }
+            };
+
+            for user_id in linked.into_iter().filter_map(|x| x.user_id) {
+                match sync(&db, &client, user_id).await {
+                    Ok(report) => info!("Synced user {user_id} with MAL: {:?}", report.summary),
+                    Err(e) => error!("Failed to sync user {user_id} with MAL: {e:?}"),
+                }
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests;
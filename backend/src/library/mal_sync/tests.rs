@@ -0,0 +1,406 @@
+//! Sync against a local stand-in for the MAL API
+
+use std::{
+    collections::HashMap,
+    net::{SocketAddr, TcpListener},
+    sync::{Arc, Mutex},
+};
+
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    routing::{get, patch, post},
+    Form, Json, Router, Server,
+};
+use chrono::{Duration, Utc};
+use migration::{Migrator, MigratorTrait};
+use sea_orm::{prelude::*, ActiveValue, ConnectOptions, Database};
+use serde::Deserialize;
+use serde_json::json;
+
+use super::{
+    client::{MalClient, MalClientOptions, RemoteListEntry, RemoteListNode, RemoteListStatus},
+    SyncAction, SyncSide,
+};
+
+const ACCESS_TOKEN: &str = "access-token";
+
+#[derive(Debug, Default)]
+struct FakeMal {
+    code_challenge: Option<String>,
+    list: HashMap<u32, RemoteListEntry>,
+    updates: Vec<u32>,
+}
+
+type FakeMalState = Arc<Mutex<FakeMal>>;
+
+#[derive(Debug, Deserialize)]
+struct TokenForm {
+    grant_type: String,
+    code_verifier: Option<String>,
+}
+
+async fn token(
+    State(state): State<FakeMalState>,
+    Form(form): Form<TokenForm>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    if form.grant_type == "authorization_code"
+        && form.code_verifier != state.lock().unwrap().code_challenge
+    {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    Ok(Json(json!({
+        "token_type": "Bearer",
+        "expires_in": 3600,
+        "access_token": ACCESS_TOKEN,
+        "refresh_token": "refresh-token",
+    })))
+}
+
+fn check_auth(headers: &HeaderMap) -> Result<(), StatusCode> {
+    let expected = format!("Bearer {ACCESS_TOKEN}");
+    match headers.get("authorization") {
+        Some(x) if x.to_str().ok() == Some(expected.as_str()) => Ok(()),
+        _ => Err(StatusCode::UNAUTHORIZED),
+    }
+}
+
+async fn animelist(
+    State(state): State<FakeMalState>,
+    headers: HeaderMap,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    check_auth(&headers)?;
+
+    let data = state
+        .lock()
+        .unwrap()
+        .list
+        .values()
+        .cloned()
+        .collect::<Vec<_>>();
+
+    Ok(Json(json!({ "data": data, "paging": {} })))
+}
+
+#[derive(Debug, Deserialize)]
+struct UpdateForm {
+    status: String,
+    score: u8,
+    num_watched_episodes: u32,
+    start_date: Option<String>,
+    finish_date: Option<String>,
+}
+
+async fn update_list_status(
+    State(state): State<FakeMalState>,
+    Path(mal_id): Path<u32>,
+    headers: HeaderMap,
+    Form(form): Form<UpdateForm>,
+) -> Result<Json<RemoteListStatus>, StatusCode> {
+    check_auth(&headers)?;
+
+    let list_status = RemoteListStatus {
+        status: Some(form.status),
+        score: form.score,
+        num_episodes_watched: form.num_watched_episodes,
+        start_date: form.start_date,
+        finish_date: form.finish_date,
+        updated_at: Utc::now().to_rfc3339(),
+    };
+
+    let mut state = state.lock().unwrap();
+    state.updates.push(mal_id);
+    let entry = state.list.entry(mal_id).or_insert_with(|| RemoteListEntry {
+        node: RemoteListNode {
+            id: mal_id,
+            title: format!("Anime {mal_id}"),
+        },
+        list_status: list_status.clone(),
+    });
+    entry.list_status = list_status.clone();
+
+    Ok(Json(list_status))
+}
+
+fn start_fake_mal() -> (FakeMalState, MalClient) {
+    let state = FakeMalState::default();
+
+    let router = Router::new()
+        .route("/oauth/token", post(token))
+        .route("/api/users/@me/animelist", get(animelist))
+        .route(
+            "/api/anime/:mal_id/my_list_status",
+            patch(update_list_status),
+        )
+        .with_state(state.clone());
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr: SocketAddr = listener.local_addr().unwrap();
+    tokio::spawn(
+        Server::from_tcp(listener)
+            .unwrap()
+            .serve(router.into_make_service()),
+    );
+
+    let client = MalClient::new(MalClientOptions {
+        client_id: "client-id".to_string(),
+        client_secret: None,
+        redirect_url: None,
+        api_url: format!("http://{addr}/api"),
+        auth_url: format!("http://{addr}/oauth"),
+    })
+    .unwrap();
+
+    (state, client)
+}
+
+async fn database() -> DatabaseConnection {
+    let mut opt = ConnectOptions::new("sqlite::memory:".to_string());
+    // Every connection to an in-memory database gets its own database
+    opt.max_connections(1).sqlx_logging(false);
+
+    let db = Database::connect(opt).await.unwrap();
+    Migrator::up(&db, None).await.unwrap();
+
+    db
+}
+
+async fn user(db: &DatabaseConnection) -> i32 {
+    crate::auth::create_user(db, "user", "password", false)
+        .await
+        .unwrap()
+        .id
+}
+
+/// Go through the authorization flow the same way the callback handler does
+async fn authorize(
+    db: &DatabaseConnection,
+    client: &MalClient,
+    state: &FakeMalState,
+    user_id: i32,
+) {
+    let url = super::start_authorization(db, client, user_id)
+        .await
+        .unwrap();
+    let url = url::Url::parse(&url).unwrap();
+    let query = url.query_pairs().into_owned().collect::<HashMap<_, _>>();
+    assert_eq!(query["code_challenge_method"], "plain");
+    state.lock().unwrap().code_challenge = Some(query["code_challenge"].clone());
+
+    let pending = super::take_pending_authorization(db, &query["state"], user_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(pending.user_id, user_id);
+    super::finish_authorization(db, client, &pending, "code")
+        .await
+        .unwrap();
+
+    assert!(
+        super::take_pending_authorization(db, &query["state"], user_id)
+            .await
+            .unwrap()
+            .is_none()
+    );
+}
+
+fn remote_entry(mal_id: u32, status: &str, watched: u32, updated_at: &str) -> RemoteListEntry {
+    RemoteListEntry {
+        node: RemoteListNode {
+            id: mal_id,
+            title: format!("Anime {mal_id}"),
+        },
+        list_status: RemoteListStatus {
+            status: Some(status.to_string()),
+            score: 0,
+            num_episodes_watched: watched,
+            start_date: None,
+            finish_date: None,
+            updated_at: updated_at.to_string(),
+        },
+    }
+}
+
+async fn entry_by_mal_id(
+    db: &DatabaseConnection,
+    user_id: i32,
+    mal_id: i32,
+) -> entity::library_entries::Model {
+    let series = entity::series::Entity::find()
+        .filter(entity::series::Column::MalId.eq(mal_id))
+        .one(db)
+        .await
+        .unwrap()
+        .unwrap();
+
+    crate::library::entries::find(db, user_id, series.id)
+        .await
+        .unwrap()
+        .unwrap()
+}
+
+async fn watch_locally(db: &DatabaseConnection, entry_id: i32, watched: i32) {
+    entity::library_entries::ActiveModel {
+        id: ActiveValue::Unchanged(entry_id),
+        watched_episodes: ActiveValue::Set(Some(watched)),
+        list_updated_at: ActiveValue::Set(Some(Utc::now().to_rfc3339())),
+        ..Default::default()
+    }
+    .update(db)
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn sync_requires_authorization() {
+    let db = database().await;
+    let (_, client) = start_fake_mal();
+    let user_id = user(&db).await;
+
+    assert!(super::sync(&db, &client, user_id).await.is_err());
+}
+
+#[tokio::test]
+async fn only_the_user_who_started_an_authorization_can_finish_it() {
+    let db = database().await;
+    let (_, client) = start_fake_mal();
+    let attacker_id = user(&db).await;
+    let victim_id = crate::auth::create_user(&db, "victim", "password", false)
+        .await
+        .unwrap()
+        .id;
+
+    let url = super::start_authorization(&db, &client, attacker_id)
+        .await
+        .unwrap();
+    let url = url::Url::parse(&url).unwrap();
+    let query = url.query_pairs().into_owned().collect::<HashMap<_, _>>();
+
+    // The victim coming back from MAL with the attacker's state
+    assert!(
+        super::take_pending_authorization(&db, &query["state"], victim_id)
+            .await
+            .unwrap()
+            .is_none()
+    );
+    assert!(
+        super::take_pending_authorization(&db, &query["state"], attacker_id)
+            .await
+            .unwrap()
+            .is_some()
+    );
+}
+
+#[tokio::test]
+async fn pulls_and_pushes_changes() {
+    let db = database().await;
+    let (state, client) = start_fake_mal();
+    let user_id = user(&db).await;
+    authorize(&db, &client, &state, user_id).await;
+
+    let an_hour_ago = (Utc::now() - Duration::hours(1)).to_rfc3339();
+    state
+        .lock()
+        .unwrap()
+        .list
+        .insert(1, remote_entry(1, "watching", 3, &an_hour_ago));
+
+    // Entries only on MAL get created
+    let report = super::sync(&db, &client, user_id).await.unwrap();
+    assert_eq!(report.summary.created, 1);
+    let entry = entry_by_mal_id(&db, user_id, 1).await;
+    assert_eq!(entry.list_status.as_deref(), Some("watching"));
+    assert_eq!(entry.watched_episodes, Some(3));
+
+    // Nothing changed, nothing to do
+    let report = super::sync(&db, &client, user_id).await.unwrap();
+    assert_eq!(report.summary.unchanged, 1);
+    assert!(report.entries.is_empty());
+
+    // Local changes get pushed
+    watch_locally(&db, entry.id, 5).await;
+    let report = super::sync(&db, &client, user_id).await.unwrap();
+    assert_eq!(report.summary.pushed, 1);
+    assert_eq!(
+        state.lock().unwrap().list[&1]
+            .list_status
+            .num_episodes_watched,
+        5
+    );
+
+    // Remote changes get pulled
+    {
+        let mut state = state.lock().unwrap();
+        let entry = state.list.get_mut(&1).unwrap();
+        entry.list_status.status = Some("completed".to_string());
+        entry.list_status.num_episodes_watched = 12;
+        entry.list_status.updated_at = Utc::now().to_rfc3339();
+    }
+    let report = super::sync(&db, &client, user_id).await.unwrap();
+    assert_eq!(report.summary.pulled, 1);
+    let entry = entry_by_mal_id(&db, user_id, 1).await;
+    assert_eq!(entry.list_status.as_deref(), Some("completed"));
+    assert_eq!(entry.watched_episodes, Some(12));
+
+    let report = super::sync(&db, &client, user_id).await.unwrap();
+    assert!(report.entries.is_empty());
+}
+
+#[tokio::test]
+async fn most_recent_change_wins_conflicts() {
+    let db = database().await;
+    let (state, client) = start_fake_mal();
+    let user_id = user(&db).await;
+    authorize(&db, &client, &state, user_id).await;
+
+    let two_hours_ago = (Utc::now() - Duration::hours(2)).to_rfc3339();
+    {
+        let mut state = state.lock().unwrap();
+        state
+            .list
+            .insert(1, remote_entry(1, "watching", 1, &two_hours_ago));
+        state
+            .list
+            .insert(2, remote_entry(2, "watching", 1, &two_hours_ago));
+    }
+    super::sync(&db, &client, user_id).await.unwrap();
+
+    // Series 1 changed on MAL an hour ago, then locally
+    // Series 2 changed locally, then on MAL a minute from now
+    let an_hour_ago = (Utc::now() - Duration::hours(1)).to_rfc3339();
+    let in_a_minute = (Utc::now() + Duration::minutes(1)).to_rfc3339();
+    {
+        let mut state = state.lock().unwrap();
+        let entry = state.list.get_mut(&1).unwrap();
+        entry.list_status.num_episodes_watched = 2;
+        entry.list_status.updated_at = an_hour_ago;
+        let entry = state.list.get_mut(&2).unwrap();
+        entry.list_status.num_episodes_watched = 4;
+        entry.list_status.updated_at = in_a_minute;
+    }
+    watch_locally(&db, entry_by_mal_id(&db, user_id, 1).await.id, 3).await;
+    watch_locally(&db, entry_by_mal_id(&db, user_id, 2).await.id, 3).await;
+
+    let report = super::sync(&db, &client, user_id).await.unwrap();
+    assert_eq!(report.summary.conflicts, 2);
+
+    let first = report.entries.iter().find(|x| x.mal_id == 1).unwrap();
+    assert_eq!(first.action, SyncAction::Pushed);
+    assert_eq!(first.conflict_winner, Some(SyncSide::Local));
+    assert_eq!(
+        state.lock().unwrap().list[&1]
+            .list_status
+            .num_episodes_watched,
+        3
+    );
+
+    let second = report.entries.iter().find(|x| x.mal_id == 2).unwrap();
+    assert_eq!(second.action, SyncAction::Pulled);
+    assert_eq!(second.conflict_winner, Some(SyncSide::Remote));
+    assert_eq!(
+        entry_by_mal_id(&db, user_id, 2).await.watched_episodes,
+        Some(4)
+    );
+    assert_eq!(state.lock().unwrap().updates, vec![1]);
+}
@@ -0,0 +1,138 @@
+use std::{fmt::Write, io::Read};
+
+use anyhow::{Context, Result};
+use chrono::NaiveDate;
+use flate2::read::MultiGzDecoder;
+use serde::Deserialize;
+
+use super::{export::ExportEntry, ListStatus};
+
+#[cfg(test)]
+mod tests;
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// Largest export accepted once decompressed, a lot more than even huge lists take up
+const MAX_XML_BYTES: u64 = 64 * 1024 * 1024;
+
+/// The `animelist.xml` file as exported from <https://myanimelist.net/panel.php?go=export>
+#[derive(Debug, Clone, Deserialize)]
+pub struct MalExport {
+    #[serde(rename = "anime", default)]
+    pub entries: Vec<MalExportEntry>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct MalExportEntry {
+    #[serde(rename = "series_animedb_id")]
+    pub mal_id: u32,
+    #[serde(rename = "series_title")]
+    pub title: String,
+    #[serde(rename = "series_episodes", default)]
+    pub episodes: Option<u32>,
+    #[serde(rename = "my_watched_episodes", default)]
+    pub watched_episodes: u32,
+    #[serde(rename = "my_start_date", default)]
+    pub start_date: String,
+    #[serde(rename = "my_finish_date", default)]
+    pub finish_date: String,
+    #[serde(rename = "my_score", default)]
+    pub score: u8,
+    #[serde(rename = "my_status", default)]
+    pub status: String,
+}
+
+impl MalExportEntry {
+    pub fn list_status(&self) -> Option<ListStatus> {
+        ListStatus::from_mal_export(&self.status)
+    }
+
+    /// Score out of 10. MAL uses 0 for entries without a score.
+    pub fn score(&self) -> Option<u8> {
+        Some(self.score).filter(|x| *x > 0)
+    }
+
+    pub fn started_at(&self) -> Option<NaiveDate> {
+        parse_date(&self.start_date)
+    }
+
+    pub fn finished_at(&self) -> Option<NaiveDate> {
+        parse_date(&self.finish_date)
+    }
+}
+
+/// MAL uses `0000-00-00` for dates that are not set
+fn parse_date(date: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d").ok()
+}
+
+/// Parse an export file. Both the plain XML and the gzipped file MAL hands out are accepted.
+pub fn parse(data: &[u8]) -> Result<MalExport> {
+    let xml = if data.starts_with(&GZIP_MAGIC) {
+        let mut xml = vec![];
+        // Read one byte more than allowed to tell whether there was more
+        MultiGzDecoder::new(data)
+            .take(MAX_XML_BYTES + 1)
+            .read_to_end(&mut xml)
+            .context("Failed to decompress export file")?;
+        if xml.len() as u64 > MAX_XML_BYTES {
+            anyhow::bail!(
+                "Decompressed export file is larger than {} MB",
+                MAX_XML_BYTES / 1024 / 1024
+            );
+        }
+
+        String::from_utf8(xml).context("Export file is not valid UTF-8")?
+    } else {
+        String::from_utf8(data.to_vec()).context("Export file is not valid UTF-8")?
+    };
+
+    quick_xml::de::from_str(&xml).context("Failed to parse MyAnimeList export")
+}
+
+/// Write entries in the same format as the export, which MAL's import accepts.
+///
+/// Entries without a MAL id are left out since MAL has no way to match them.
+pub fn write(entries: &[ExportEntry]) -> String {
+    let mut xml = String::new();
+
+    xml.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\" ?>\n");
+    xml.push_str("<myanimelist>\n");
+    xml.push_str("\t<myinfo>\n\t\t<user_export_type>1</user_export_type>\n\t</myinfo>\n");
+
+    for entry in entries {
+        let Some(mal_id) = entry.mal_id else {
+            continue;
+        };
+
+        let date = |date: Option<NaiveDate>| {
+            date.map_or_else(|| "0000-00-00".to_string(), |x| x.to_string())
+        };
+
+        // Writing to a `String` can't fail
+        let _ = write!(
+            xml,
+            "\t<anime>\n\
+            \t\t<series_animedb_id>{mal_id}</series_animedb_id>\n\
+            \t\t<series_title>{title}</series_title>\n\
+            \t\t<my_watched_episodes>{watched}</my_watched_episodes>\n\
+            \t\t<my_start_date>{started}</my_start_date>\n\
+            \t\t<my_finish_date>{finished}</my_finish_date>\n\
+            \t\t<my_score>{score}</my_score>\n\
+            \t\t<my_status>{status}</my_status>\n\
+            \t\t<update_on_import>1</update_on_import>\n\
+            \t</anime>\n",
+            mal_id = mal_id,
+            title = quick_xml::escape::escape(entry.name.as_str()),
+            watched = entry.watched_episodes,
+            started = date(entry.started_at),
+            finished = date(entry.finished_at),
+            score = entry.score.unwrap_or_default(),
+            status = entry.status.as_mal_export(),
+        );
+    }
+
+    xml.push_str("</myanimelist>\n");
+
+    xml
+}
@@ -0,0 +1,57 @@
+//! Reading export files the way MAL hands them out
+
+use std::io::Write;
+
+use flate2::{write::GzEncoder, Compression};
+
+use super::{parse, MAX_XML_BYTES};
+
+const EXPORT: &str = r#"<?xml version="1.0" encoding="UTF-8" ?>
+<myanimelist>
+	<anime>
+		<series_animedb_id>52991</series_animedb_id>
+		<series_title><![CDATA[Sousou no Frieren]]></series_title>
+		<series_episodes>28</series_episodes>
+		<my_watched_episodes>12</my_watched_episodes>
+		<my_start_date>2023-09-29</my_start_date>
+		<my_finish_date>0000-00-00</my_finish_date>
+		<my_score>0</my_score>
+		<my_status>Watching</my_status>
+	</anime>
+</myanimelist>
+"#;
+
+fn gzip(data: &[u8]) -> Vec<u8> {
+    let mut encoder = GzEncoder::new(vec![], Compression::default());
+    encoder.write_all(data).unwrap();
+    encoder.finish().unwrap()
+}
+
+#[test]
+fn reads_plain_and_gzipped_exports() {
+    for data in [EXPORT.as_bytes().to_vec(), gzip(EXPORT.as_bytes())] {
+        let export = parse(&data).unwrap();
+
+        assert_eq!(export.entries.len(), 1);
+        let entry = &export.entries[0];
+        assert_eq!(entry.mal_id, 52991);
+        assert_eq!(entry.title, "Sousou no Frieren");
+        assert_eq!(entry.watched_episodes, 12);
+        assert_eq!(entry.score(), None);
+        assert!(entry.started_at().is_some());
+        assert_eq!(entry.finished_at(), None);
+    }
+}
+
+#[test]
+fn refuses_exports_that_decompress_into_too_much() {
+    // Compresses down to a tiny fraction of that
+    let mut xml = EXPORT.as_bytes().to_vec();
+    xml.resize(usize::try_from(MAX_XML_BYTES).unwrap() + 1, b' ');
+    let data = gzip(&xml);
+    assert!(data.len() < 1024 * 1024);
+
+    let error = parse(&data).unwrap_err();
+
+    assert!(error.to_string().contains("larger than"), "{error:?}");
+}
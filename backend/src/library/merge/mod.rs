@@ -0,0 +1,299 @@
+//! Merging the info of all the sources of a series into one canonical record.
+//!
+//! Each field is taken from the provider that comes first in the configured precedence
+//! and has a value for it. Lists like genres are combined from all of them instead.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::episodes;
+use crate::{
+    config::CONFIG,
+    metadata::{myanimelist::anime::details::AnimeDetails, AltName, AnimeInfo, AnimeStatus},
+};
+
+#[cfg(test)]
+mod tests;
+
+/// Name MAL goes by in the provider precedence
+pub const MAL_PROVIDER: &str = "myanimelist";
+
+/// Where a merged value came from
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldOrigin {
+    /// `myanimelist` or the site of the source
+    pub provider: String,
+    /// Not set for MAL, which isn't a source
+    pub source_id: Option<i32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MergedAnime {
+    pub name: Option<String>,
+    pub alt_names: Vec<AltName>,
+    pub description: Option<String>,
+    pub mal_id: Option<u32>,
+    pub status: AnimeStatus,
+    pub genres: Vec<String>,
+    /// Not set once the series is completed
+    pub next_release_estimate: Option<DateTime<Utc>>,
+    /// Most episodes any source has, once their episode mappings are applied
+    pub episodes_released: Option<usize>,
+    /// Number of episodes the series will have, if known
+    pub episodes_total: Option<u32>,
+    #[schema(value_type = Object)]
+    pub meta: HashMap<String, serde_json::Value>,
+    /// Providers each field came from, keyed by field name.
+    ///
+    /// Keys of the combined `meta` map are listed as `meta.<key>`.
+    pub provenance: BTreeMap<String, Vec<FieldOrigin>>,
+}
+
+/// What one provider reports about the series
+struct Candidate<'a> {
+    origin: FieldOrigin,
+    name: Option<&'a str>,
+    alt_names: Vec<AltName>,
+    description: Option<&'a str>,
+    mal_id: Option<u32>,
+    status: AnimeStatus,
+    genres: Vec<&'a str>,
+    next_release_estimate: Option<DateTime<Utc>>,
+    episodes_released: Option<usize>,
+    episodes_total: Option<u32>,
+    meta: Option<&'a HashMap<String, serde_json::Value>>,
+}
+
+impl<'a> Candidate<'a> {
+    fn from_source(source: &entity::series_sources::Model, info: &'a AnimeInfo) -> Self {
+        let episodes = episodes::mapped(source, info)
+            .iter()
+            .map(|x| x.episode_number.to_bits())
+            .collect::<BTreeSet<_>>()
+            .len();
+
+        Self {
+            origin: FieldOrigin {
+                provider: source.series_site.clone(),
+                source_id: Some(source.id),
+            },
+            name: Some(info.name.as_str()),
+            alt_names: info.alt_names.clone(),
+            description: info.description.as_deref(),
+            mal_id: info.mal_id,
+            status: info.status.clone(),
+            genres: info.genres.iter().map(String::as_str).collect(),
+            next_release_estimate: info.next_release_estimate,
+            episodes_released: Some(episodes).filter(|x| *x > 0),
+            episodes_total: None,
+            meta: Some(&info.meta),
+        }
+    }
+
+    fn from_mal(details: &'a AnimeDetails) -> Self {
+        let titles = details.alternative_titles.as_ref();
+        let alt_names = titles
+            .and_then(|x| x.en.as_ref())
+            .map(|x| ("en", x))
+            .into_iter()
+            .chain(titles.and_then(|x| x.ja.as_ref()).map(|x| ("ja", x)))
+            .chain(
+                titles
+                    .and_then(|x| x.synonyms.as_ref())
+                    .into_iter()
+                    .flatten()
+                    .map(|x| ("synonym", x)),
+            )
+            .map(|(language, name)| AltName {
+                name: name.clone(),
+                language: language.to_string(),
+            })
+            .collect();
+
+        Self {
+            origin: FieldOrigin {
+                provider: MAL_PROVIDER.to_string(),
+                source_id: None,
+            },
+            name: Some(details.title.as_str()),
+            alt_names,
+            description: details.synopsis.as_deref(),
+            mal_id: Some(details.id),
+            status: details.status.clone(),
+            genres: details
+                .genres
+                .iter()
+                .flatten()
+                .map(|x| x.name.as_str())
+                .collect(),
+            next_release_estimate: None,
+            episodes_released: None,
+            episodes_total: Some(details.num_episodes).filter(|x| *x > 0),
+            meta: None,
+        }
+    }
+}
+
+/// Where a provider is in `order`, like the configured precedence. Providers that aren't listed come last.
+pub fn precedence(order: &[String], provider: &str) -> usize {
+    order
+        .iter()
+        .position(|x| x.trim().eq_ignore_ascii_case(provider))
+        .unwrap_or(usize::MAX)
+}
+
+/// The value of the first candidate that has one, and where it came from
+fn first<'a, T>(
+    candidates: &'a [Candidate<'a>],
+    field: &str,
+    provenance: &mut BTreeMap<String, Vec<FieldOrigin>>,
+    get: impl Fn(&'a Candidate<'a>) -> Option<T>,
+) -> Option<T> {
+    let (candidate, value) = candidates.iter().find_map(|x| Some((x, get(x)?)))?;
+    provenance.insert(field.to_string(), vec![candidate.origin.clone()]);
+
+    Some(value)
+}
+
+/// Merge the info of a series' sources, and its MAL details if there are any
+pub fn merge(
+    sources: &[(entity::series_sources::Model, AnimeInfo)],
+    mal: Option<&AnimeDetails>,
+) -> MergedAnime {
+    merge_with(sources, mal, &CONFIG.library.provider_precedence)
+}
+
+/// [`merge`] with the providers in the given order instead of the configured one
+fn merge_with(
+    sources: &[(entity::series_sources::Model, AnimeInfo)],
+    mal: Option<&AnimeDetails>,
+    order: &[String],
+) -> MergedAnime {
+    let mut candidates = sources
+        .iter()
+        .map(|(source, info)| Candidate::from_source(source, info))
+        .chain(mal.map(Candidate::from_mal))
+        .collect::<Vec<_>>();
+    // Stable, so sources of the same site stay in the order they were added
+    candidates.sort_by_key(|x| precedence(order, &x.origin.provider));
+
+    let mut provenance = BTreeMap::new();
+
+    let name = first(&candidates, "name", &mut provenance, |x| {
+        x.name.filter(|x| !x.trim().is_empty()).map(str::to_string)
+    });
+    let description = first(&candidates, "description", &mut provenance, |x| {
+        x.description
+            .filter(|x| !x.trim().is_empty())
+            .map(str::to_string)
+    });
+    let mal_id = first(&candidates, "malId", &mut provenance, |x| x.mal_id);
+    let episodes_total = first(&candidates, "episodesTotal", &mut provenance, |x| {
+        x.episodes_total
+    });
+
+    // MAL is the authority on whether a series is finished, whatever the precedence says
+    let mal_completed = candidates
+        .iter()
+        .find(|x| x.origin.provider == MAL_PROVIDER && x.status == AnimeStatus::Completed);
+    let status = match mal_completed {
+        Some(x) => {
+            provenance.insert("status".to_string(), vec![x.origin.clone()]);
+            AnimeStatus::Completed
+        }
+        None => first(&candidates, "status", &mut provenance, |x| {
+            Some(x.status.clone()).filter(|x| *x != AnimeStatus::Unknown)
+        })
+        .unwrap_or_default(),
+    };
+
+    let next_release_estimate = if status == AnimeStatus::Completed {
+        None
+    } else {
+        first(&candidates, "nextReleaseEstimate", &mut provenance, |x| {
+            x.next_release_estimate
+        })
+    };
+
+    let episodes_released = candidates
+        .iter()
+        .filter_map(|x| Some((x, x.episodes_released?)))
+        .max_by_key(|(_, count)| *count)
+        .map(|(x, count)| {
+            provenance.insert("episodesReleased".to_string(), vec![x.origin.clone()]);
+            count
+        });
+
+    let mut alt_names: Vec<AltName> = vec![];
+    let mut genres: Vec<String> = vec![];
+    let mut meta = HashMap::new();
+    for candidate in &candidates {
+        let other_names = std::iter::once(AltName {
+            name: candidate.name.unwrap_or_default().to_string(),
+            language: String::new(),
+        })
+        .chain(candidate.alt_names.iter().cloned());
+
+        for alt_name in other_names {
+            let trimmed = alt_name.name.trim();
+            let known = name
+                .as_deref()
+                .is_some_and(|x| x.eq_ignore_ascii_case(trimmed))
+                || alt_names
+                    .iter()
+                    .any(|x| x.name.eq_ignore_ascii_case(trimmed));
+
+            if !trimmed.is_empty() && !known {
+                add_origin(&mut provenance, "altNames", &candidate.origin);
+                alt_names.push(alt_name);
+            }
+        }
+
+        for genre in &candidate.genres {
+            let genre = genre.trim();
+
+            if !genre.is_empty() && !genres.iter().any(|x| x.eq_ignore_ascii_case(genre)) {
+                add_origin(&mut provenance, "genres", &candidate.origin);
+                genres.push(genre.to_string());
+            }
+        }
+
+        for (key, value) in candidate.meta.into_iter().flatten() {
+            if !meta.contains_key(key) {
+                provenance.insert(format!("meta.{key}"), vec![candidate.origin.clone()]);
+                meta.insert(key.clone(), value.clone());
+            }
+        }
+    }
+
+    MergedAnime {
+        name,
+        alt_names,
+        description,
+        mal_id,
+        status,
+        genres,
+        next_release_estimate,
+        episodes_released,
+        episodes_total,
+        meta,
+        provenance,
+    }
+}
+
+fn add_origin(
+    provenance: &mut BTreeMap<String, Vec<FieldOrigin>>,
+    field: &str,
+    origin: &FieldOrigin,
+) {
+    let origins = provenance.entry(field.to_string()).or_default();
+
+    if !origins.contains(origin) {
+        origins.push(origin.clone());
+    }
+}
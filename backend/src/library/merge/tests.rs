@@ -0,0 +1,268 @@
+//! Merging what sources and MAL report, in a fixed provider order instead of the configured one
+
+use std::collections::HashMap;
+
+use chrono::{TimeZone, Utc};
+
+use super::{merge_with, FieldOrigin, MergedAnime, MAL_PROVIDER};
+use crate::{
+    metadata::{
+        myanimelist::anime::details::AnimeDetails, AltName, AnimeInfo, AnimeStatus, EpisodeInfo,
+        SeriesTranslation,
+    },
+    test_util::test_source,
+};
+
+fn source(id: i32, site: &str) -> entity::series_sources::Model {
+    entity::series_sources::Model {
+        id,
+        series_site: site.to_string(),
+        series_site_id: format!("frieren-{id}"),
+        ..test_source()
+    }
+}
+
+fn info(name: &str, description: Option<&str>, status: AnimeStatus) -> AnimeInfo {
+    AnimeInfo {
+        name: name.to_string(),
+        description: description.map(str::to_string),
+        status,
+        ..Default::default()
+    }
+}
+
+fn with_episodes(mut info: AnimeInfo, episodes: u32) -> AnimeInfo {
+    info.episodes = (1..=episodes)
+        .map(|number| EpisodeInfo {
+            id: format!("{number}"),
+            title: format!("Episode {number}"),
+            translation: SeriesTranslation::Sub,
+            episode_number: f64::from(number),
+            url: String::new(),
+            description: None,
+            thumbnail: None,
+            available_at: None,
+            is_filler: false,
+        })
+        .collect();
+    info
+}
+
+/// Details the way MAL's API returns them
+fn mal(status: &str) -> AnimeDetails {
+    serde_json::from_value(serde_json::json!({
+        "id": 52991,
+        "title": "Sousou no Frieren",
+        "alternative_titles": {
+            "en": "Frieren: Beyond Journey's End",
+            "ja": "葬送のフリーレン",
+            "synonyms": ["Frieren at the Funeral"],
+        },
+        "synopsis": "The demon king has been defeated.",
+        "num_list_users": 1,
+        "num_scoring_users": 1,
+        "genres": [{ "id": 2, "name": "Adventure" }, { "id": 10, "name": "Fantasy" }],
+        "media_type": "tv",
+        "status": status,
+        "num_episodes": 28,
+    }))
+    .unwrap()
+}
+
+fn order(providers: &[&str]) -> Vec<String> {
+    providers.iter().copied().map(String::from).collect()
+}
+
+fn origin(provider: &str, source_id: Option<i32>) -> FieldOrigin {
+    FieldOrigin {
+        provider: provider.to_string(),
+        source_id,
+    }
+}
+
+fn providers_of(merged: &MergedAnime, field: &str) -> Vec<String> {
+    merged.provenance[field]
+        .iter()
+        .map(|x| x.provider.clone())
+        .collect()
+}
+
+#[test]
+fn takes_fields_from_the_first_provider_that_has_them() {
+    let sources = [
+        (
+            source(1, "aniwatch"),
+            info("Frieren", Some("From aniwatch"), AnimeStatus::Airing),
+        ),
+        (
+            source(2, "allanime"),
+            info("Sousou no Frieren", None, AnimeStatus::Unknown),
+        ),
+    ];
+
+    let merged = merge_with(&sources, None, &order(&["allanime", "aniwatch"]));
+
+    assert_eq!(merged.name.as_deref(), Some("Sousou no Frieren"));
+    assert_eq!(merged.provenance["name"], [origin("allanime", Some(2))]);
+    // Allanime has neither, so they come from the next provider
+    assert_eq!(merged.description.as_deref(), Some("From aniwatch"));
+    assert_eq!(
+        merged.provenance["description"],
+        [origin("aniwatch", Some(1))]
+    );
+    assert_eq!(merged.status, AnimeStatus::Airing);
+    assert_eq!(providers_of(&merged, "status"), ["aniwatch"]);
+
+    let merged = merge_with(&sources, None, &order(&["aniwatch", "allanime"]));
+
+    assert_eq!(merged.name.as_deref(), Some("Frieren"));
+    assert_eq!(merged.provenance["name"], [origin("aniwatch", Some(1))]);
+}
+
+#[test]
+fn puts_unlisted_providers_last() {
+    let sources = [
+        (
+            source(1, "somewhere"),
+            info("Frieren", None, AnimeStatus::Unknown),
+        ),
+        (
+            source(2, "aniwave"),
+            info("Sousou no Frieren", None, AnimeStatus::Unknown),
+        ),
+    ];
+
+    let merged = merge_with(&sources, None, &order(&["aniwave"]));
+
+    assert_eq!(merged.name.as_deref(), Some("Sousou no Frieren"));
+    assert_eq!(merged.alt_names[0].name, "Frieren");
+}
+
+#[test]
+fn lets_mal_say_when_a_series_is_completed() {
+    let mut airing = info("Sousou no Frieren", None, AnimeStatus::Airing);
+    airing.next_release_estimate = Some(Utc.with_ymd_and_hms(2024, 3, 22, 16, 0, 0).unwrap());
+    let sources = [(source(1, "aniwatch"), airing)];
+    let details = mal("finished_airing");
+
+    // Even when MAL comes after the source
+    let merged = merge_with(
+        &sources,
+        Some(&details),
+        &order(&["aniwatch", MAL_PROVIDER]),
+    );
+
+    assert_eq!(merged.status, AnimeStatus::Completed);
+    assert_eq!(merged.provenance["status"], [origin(MAL_PROVIDER, None)]);
+    assert_eq!(merged.next_release_estimate, None);
+    assert!(!merged.provenance.contains_key("nextReleaseEstimate"));
+}
+
+#[test]
+fn follows_the_precedence_for_other_statuses() {
+    let mut airing = info("Sousou no Frieren", None, AnimeStatus::Airing);
+    let next_release = Utc.with_ymd_and_hms(2024, 3, 22, 16, 0, 0).unwrap();
+    airing.next_release_estimate = Some(next_release);
+    let sources = [(source(1, "aniwatch"), airing)];
+    let details = mal("not_yet_aired");
+
+    let merged = merge_with(
+        &sources,
+        Some(&details),
+        &order(&["aniwatch", MAL_PROVIDER]),
+    );
+
+    assert_eq!(merged.status, AnimeStatus::Airing);
+    assert_eq!(providers_of(&merged, "status"), ["aniwatch"]);
+    assert_eq!(merged.next_release_estimate, Some(next_release));
+
+    let merged = merge_with(
+        &sources,
+        Some(&details),
+        &order(&[MAL_PROVIDER, "aniwatch"]),
+    );
+
+    assert_eq!(merged.status, AnimeStatus::Unaired);
+    assert_eq!(providers_of(&merged, "status"), [MAL_PROVIDER]);
+}
+
+#[test]
+fn records_every_provider_of_combined_fields() {
+    let mut aniwatch = with_episodes(
+        info("Frieren", Some("From aniwatch"), AnimeStatus::Airing),
+        12,
+    );
+    aniwatch.genres = vec!["fantasy".to_string(), "Drama".to_string()];
+    aniwatch.alt_names = vec![AltName {
+        name: "frieren: beyond journey's end".to_string(),
+        language: "en".to_string(),
+    }];
+    aniwatch.meta = HashMap::from([
+        ("rating".to_string(), serde_json::json!("PG-13")),
+        ("quality".to_string(), serde_json::json!("HD")),
+    ]);
+    let mut allanime = with_episodes(info("Sousou no Frieren", None, AnimeStatus::Airing), 16);
+    allanime.mal_id = Some(52991);
+    allanime.meta = HashMap::from([("rating".to_string(), serde_json::json!("R"))]);
+    let sources = [
+        (source(1, "aniwatch"), aniwatch),
+        (source(2, "allanime"), allanime),
+    ];
+    let details = mal("currently_airing");
+
+    let merged = merge_with(
+        &sources,
+        Some(&details),
+        &order(&[MAL_PROVIDER, "allanime", "aniwatch"]),
+    );
+
+    assert_eq!(merged.name.as_deref(), Some("Sousou no Frieren"));
+    assert_eq!(providers_of(&merged, "name"), [MAL_PROVIDER]);
+    assert_eq!(merged.mal_id, Some(52991));
+    assert_eq!(merged.provenance["malId"], [origin(MAL_PROVIDER, None)]);
+    assert_eq!(merged.episodes_total, Some(28));
+    assert_eq!(providers_of(&merged, "episodesTotal"), [MAL_PROVIDER]);
+    // The most episodes, wherever they come from
+    assert_eq!(merged.episodes_released, Some(16));
+    assert_eq!(
+        merged.provenance["episodesReleased"],
+        [origin("allanime", Some(2))]
+    );
+
+    // Duplicates of names and genres are left out whatever their case
+    assert_eq!(merged.genres, ["Adventure", "Fantasy", "Drama"]);
+    assert_eq!(
+        merged.provenance["genres"],
+        [origin(MAL_PROVIDER, None), origin("aniwatch", Some(1))]
+    );
+    let alt_names = merged
+        .alt_names
+        .iter()
+        .map(|x| x.name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        alt_names,
+        [
+            "Frieren: Beyond Journey's End",
+            "葬送のフリーレン",
+            "Frieren at the Funeral",
+            "Frieren"
+        ]
+    );
+    assert_eq!(
+        merged.provenance["altNames"],
+        [origin(MAL_PROVIDER, None), origin("aniwatch", Some(1))]
+    );
+
+    // Meta keys are merged one by one
+    assert_eq!(merged.meta["rating"], "R");
+    assert_eq!(
+        merged.provenance["meta.rating"],
+        [origin("allanime", Some(2))]
+    );
+    assert_eq!(merged.meta["quality"], "HD");
+    assert_eq!(
+        merged.provenance["meta.quality"],
+        [origin("aniwatch", Some(1))]
+    );
+}
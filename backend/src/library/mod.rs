@@ -0,0 +1,105 @@
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+pub mod consistency;
+pub mod enrich;
+pub mod entries;
+pub mod episodes;
+pub mod export;
+pub mod health;
+pub mod import;
+pub mod mal_sync;
+pub mod mal_xml;
+pub mod merge;
+pub mod search;
+pub mod skip_markers;
+pub mod sources;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum ListStatus {
+    Watching,
+    Completed,
+    OnHold,
+    Dropped,
+    PlanToWatch,
+}
+
+impl ListStatus {
+    /// Parse the status as written in MAL's `animelist.xml` export
+    pub fn from_mal_export(s: &str) -> Option<Self> {
+        match s.trim() {
+            "Watching" => Some(Self::Watching),
+            "Completed" => Some(Self::Completed),
+            "On-Hold" => Some(Self::OnHold),
+            "Dropped" => Some(Self::Dropped),
+            "Plan to Watch" => Some(Self::PlanToWatch),
+            _ => None,
+        }
+    }
+
+    pub fn as_mal_export(self) -> &'static str {
+        match self {
+            Self::Watching => "Watching",
+            Self::Completed => "Completed",
+            Self::OnHold => "On-Hold",
+            Self::Dropped => "Dropped",
+            Self::PlanToWatch => "Plan to Watch",
+        }
+    }
+
+    /// Parse the status as used by the MAL API
+    pub fn from_mal_api(s: &str) -> Option<Self> {
+        match s {
+            "watching" => Some(Self::Watching),
+            "completed" => Some(Self::Completed),
+            "on_hold" => Some(Self::OnHold),
+            "dropped" => Some(Self::Dropped),
+            "plan_to_watch" => Some(Self::PlanToWatch),
+            _ => None,
+        }
+    }
+
+    pub fn as_mal_api(self) -> &'static str {
+        match self {
+            Self::Watching => "watching",
+            Self::Completed => "completed",
+            Self::OnHold => "on_hold",
+            Self::Dropped => "dropped",
+            Self::PlanToWatch => "plan_to_watch",
+        }
+    }
+
+    /// The matching `MediaListStatus` of the `AniList` API
+    pub fn as_anilist(self) -> &'static str {
+        match self {
+            Self::Watching => "CURRENT",
+            Self::Completed => "COMPLETED",
+            Self::OnHold => "PAUSED",
+            Self::Dropped => "DROPPED",
+            Self::PlanToWatch => "PLANNING",
+        }
+    }
+}
+
+impl ToString for ListStatus {
+    fn to_string(&self) -> String {
+        match self {
+            Self::Watching => "watching".to_string(),
+            Self::Completed => "completed".to_string(),
+            Self::OnHold => "on-hold".to_string(),
+            Self::Dropped => "dropped".to_string(),
+            Self::PlanToWatch => "plan-to-watch".to_string(),
+        }
+    }
+}
+
+impl FromStr for ListStatus {
+    type Err = serde_json::Error;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        serde_json::from_str(&format!("\"{}\"", s))
+    }
+}
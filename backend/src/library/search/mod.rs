@@ -0,0 +1,173 @@
+//! Full-text search over the series in the library.
+//!
+//! Backed by the `series_search` FTS5 table, which triggers keep in step with `series`.
+
+use std::collections::HashMap;
+
+use anyhow::Result;
+use log::trace;
+use sea_orm::{prelude::*, DbBackend, FromQueryResult, Statement, Value};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+#[cfg(test)]
+mod tests;
+
+/// The trigram tokenizer can't match anything shorter
+const MIN_MATCH_LENGTH: usize = 3;
+
+/// Put around matches in the query, and only turned into tags once the rest of the text is escaped
+const MARK_START: char = '\u{E000}';
+const MARK_END: char = '\u{E001}';
+
+/// Search columns as escaped HTML, with the matched parts wrapped in `<mark>` tags
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchHighlights {
+    pub name: String,
+    /// Only the part of the description around the match
+    pub description: Option<String>,
+    pub alt_names: Option<String>,
+    pub genres: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchHit {
+    #[schema(value_type = Series)]
+    pub series: entity::series::Model,
+    /// How well the series matches, higher is better
+    pub score: f64,
+    pub highlights: SearchHighlights,
+}
+
+#[derive(Debug, FromQueryResult)]
+struct SearchRow {
+    id: i32,
+    score: f64,
+    name: String,
+    description: Option<String>,
+    alt_names: Option<String>,
+    genres: Option<String>,
+}
+
+/// Find series by any part of their name, alternative names, description or genres.
+///
+/// Every whitespace separated term has to match. Results are ranked with matches in
+/// names weighted above matches in the description.
+pub async fn search<C>(db: &C, query: &str, limit: u64) -> Result<Vec<SearchHit>>
+where
+    C: ConnectionTrait,
+{
+    let (long, short): (Vec<_>, Vec<_>) = query
+        .split_whitespace()
+        .partition(|x| x.chars().count() >= MIN_MATCH_LENGTH);
+
+    if long.is_empty() && short.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let mut values: Vec<Value> = vec![];
+    let mut conditions = vec![];
+
+    if !long.is_empty() {
+        conditions.push("series_search MATCH ?".to_string());
+        values.push(
+            long.iter()
+                .map(|x| format!("\"{}\"", x.replace('"', "\"\"")))
+                .collect::<Vec<_>>()
+                .join(" ")
+                .into(),
+        );
+    }
+
+    // Terms too short for the index still narrow down the results, just without the index
+    for term in &short {
+        conditions.push(
+            "(name LIKE ? ESCAPE '\\'
+                OR description LIKE ? ESCAPE '\\'
+                OR alt_names LIKE ? ESCAPE '\\'
+                OR genres LIKE ? ESCAPE '\\')"
+                .to_string(),
+        );
+        let pattern = format!(
+            "%{}%",
+            term.replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_")
+        );
+        for _ in 0..4 {
+            values.push(pattern.clone().into());
+        }
+    }
+
+    let columns = if long.is_empty() {
+        "0.0 AS score, name, description, alt_names, genres"
+    } else {
+        "-bm25(series_search, 10.0, 1.0, 5.0, 2.0) AS score,
+            highlight(series_search, 0, '\u{E000}', '\u{E001}') AS name,
+            snippet(series_search, 1, '\u{E000}', '\u{E001}', '…', 24) AS description,
+            highlight(series_search, 2, '\u{E000}', '\u{E001}') AS alt_names,
+            highlight(series_search, 3, '\u{E000}', '\u{E001}') AS genres"
+    };
+    let sql = format!(
+        "SELECT rowid AS id, {columns}
+        FROM series_search
+        WHERE {conditions}
+        ORDER BY score DESC, name
+        LIMIT ?",
+        conditions = conditions.join(" AND "),
+    );
+    values.push(limit.into());
+
+    trace!("Searching series for {query:?}");
+    let rows = SearchRow::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::Sqlite,
+        &sql,
+        values,
+    ))
+    .all(db)
+    .await?;
+
+    let mut series = entity::series::Entity::find()
+        .filter(entity::series::Column::Id.is_in(rows.iter().map(|x| x.id)))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|x| (x.id, x))
+        .collect::<HashMap<_, _>>();
+
+    let hits = rows
+        .into_iter()
+        .filter_map(|row| {
+            Some(SearchHit {
+                series: series.remove(&row.id)?,
+                score: row.score,
+                highlights: SearchHighlights {
+                    name: to_html(&row.name),
+                    description: row.description.as_deref().map(to_html),
+                    alt_names: row.alt_names.as_deref().map(to_html),
+                    genres: row.genres.as_deref().map(to_html),
+                },
+            })
+        })
+        .collect();
+
+    Ok(hits)
+}
+
+/// Escape a highlighted column and turn its match markers into `<mark>` tags
+fn to_html(text: &str) -> String {
+    let mut html = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            MARK_START => html.push_str("<mark>"),
+            MARK_END => html.push_str("</mark>"),
+            '&' => html.push_str("&amp;"),
+            '<' => html.push_str("&lt;"),
+            '>' => html.push_str("&gt;"),
+            c => html.push(c),
+        }
+    }
+    html
+}
@@ -0,0 +1,241 @@
+//! Searching a migrated in-memory database, so the FTS5 table and its triggers are the real ones
+
+use migration::{Migrator, MigratorTrait};
+use sea_orm::{prelude::*, ActiveValue, ConnectOptions, Database};
+
+use super::search;
+
+async fn database() -> DatabaseConnection {
+    let mut opt = ConnectOptions::new("sqlite::memory:".to_string());
+    // Every connection to an in-memory database gets its own database
+    opt.max_connections(1).sqlx_logging(false);
+
+    let db = Database::connect(opt).await.unwrap();
+    Migrator::up(&db, None).await.unwrap();
+
+    for (name, description, alt_names, genres) in [
+        (
+            "Sousou no Frieren",
+            "An elf mage looks back on the journey of her party after the demon king is defeated.",
+            "Frieren: Beyond Journey's End",
+            "Adventure\nDrama\nFantasy",
+        ),
+        (
+            "Magic Kaito 1412",
+            "A high school student finds out his father was a phantom thief.",
+            "",
+            "Mystery\nComedy",
+        ),
+        (
+            "Mahoutsukai no Yome",
+            "A girl sold at an auction is bought by a mage who wants her as his apprentice.",
+            "The Ancient Magus' Bride",
+            "Fantasy\nSlice of Life",
+        ),
+        (
+            "100% Orange Juice",
+            "A board game where every card is a kind of magic.",
+            "",
+            "Comedy",
+        ),
+    ] {
+        entity::series::ActiveModel {
+            name: ActiveValue::Set(name.to_string()),
+            description: ActiveValue::Set(Some(description.to_string()).filter(|x| !x.is_empty())),
+            alt_names: ActiveValue::Set(Some(alt_names.to_string()).filter(|x| !x.is_empty())),
+            genres: ActiveValue::Set(Some(genres.to_string())),
+            ..Default::default()
+        }
+        .insert(&db)
+        .await
+        .unwrap();
+    }
+
+    db
+}
+
+async fn names(db: &DatabaseConnection, query: &str) -> Vec<String> {
+    search(db, query, 10)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|x| x.series.name)
+        .collect()
+}
+
+/// Names in alphabetical order, for results that are all ranked the same
+async fn names_of(db: &DatabaseConnection, query: &str) -> Vec<String> {
+    let mut names = names(db, query).await;
+    names.sort();
+    names
+}
+
+#[tokio::test]
+async fn matches_any_column() {
+    let db = database().await;
+
+    assert_eq!(names(&db, "frieren").await, ["Sousou no Frieren"]);
+    // Alternative names
+    assert_eq!(names(&db, "journey's end").await, ["Sousou no Frieren"]);
+    // Genres
+    assert_eq!(names(&db, "mystery").await, ["Magic Kaito 1412"]);
+    // Any part of a word
+    assert_eq!(names(&db, "phant").await, ["Magic Kaito 1412"]);
+    assert!(names(&db, "gundam").await.is_empty());
+}
+
+#[tokio::test]
+async fn needs_every_term() {
+    let db = database().await;
+
+    assert_eq!(
+        names(&db, "mage fantasy").await.len(),
+        2,
+        "both mages are in fantasies"
+    );
+    assert_eq!(names(&db, "mage auction").await, ["Mahoutsukai no Yome"]);
+    assert!(names(&db, "mage mystery").await.is_empty());
+}
+
+#[tokio::test]
+async fn ranks_names_above_descriptions() {
+    let db = database().await;
+
+    let hits = search(&db, "magic", 10).await.unwrap();
+
+    let names = hits
+        .iter()
+        .map(|x| x.series.name.as_str())
+        .collect::<Vec<_>>();
+    // In the name of one and the description of the other
+    assert_eq!(names, ["Magic Kaito 1412", "100% Orange Juice"]);
+    assert!(hits[0].score > hits[1].score);
+}
+
+#[tokio::test]
+async fn falls_back_to_like_for_short_terms() {
+    let db = database().await;
+
+    // Too short for the trigram index
+    let hits = search(&db, "no", 10).await.unwrap();
+    let names = hits
+        .iter()
+        .map(|x| x.series.name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["Mahoutsukai no Yome", "Sousou no Frieren"]);
+    assert!(hits.iter().all(|x| x.score == 0.0));
+    // Nothing to highlight without the index
+    assert_eq!(hits[1].highlights.name, "Sousou no Frieren");
+
+    // Short terms narrow down what the long ones match
+    assert_eq!(
+        names_of(&db, "fantasy no").await,
+        ["Mahoutsukai no Yome", "Sousou no Frieren"]
+    );
+    assert_eq!(names_of(&db, "fantasy ap").await, ["Mahoutsukai no Yome"]);
+}
+
+#[tokio::test]
+async fn escapes_like_wildcards() {
+    let db = database().await;
+
+    assert_eq!(names(&db, "%").await, ["100% Orange Juice"]);
+    assert!(names(&db, "_").await.is_empty());
+    assert!(names(&db, "\\").await.is_empty());
+}
+
+#[tokio::test]
+async fn escapes_quotes_in_match_terms() {
+    let db = database().await;
+
+    // Would be a syntax error if it ended up in the MATCH query as is
+    assert!(names(&db, "\"frieren").await.is_empty());
+    assert_eq!(names(&db, "magus'").await, ["Mahoutsukai no Yome"]);
+}
+
+#[tokio::test]
+async fn highlights_matches() {
+    let db = database().await;
+
+    let hits = search(&db, "frieren elf", 10).await.unwrap();
+
+    assert_eq!(hits.len(), 1);
+    let highlights = &hits[0].highlights;
+    assert_eq!(highlights.name, "Sousou no <mark>Frieren</mark>");
+    assert_eq!(
+        highlights.alt_names.as_deref(),
+        Some("<mark>Frieren</mark>: Beyond Journey's End")
+    );
+    assert!(highlights
+        .description
+        .as_deref()
+        .is_some_and(|x| x.contains("An <mark>elf</mark> mage")));
+    assert_eq!(
+        highlights.genres.as_deref(),
+        Some("Adventure\nDrama\nFantasy")
+    );
+}
+
+#[tokio::test]
+async fn escapes_html_around_highlights() {
+    let db = database().await;
+    entity::series::ActiveModel {
+        name: ActiveValue::Set("<script>alert('Frieren')</script>".to_string()),
+        description: ActiveValue::Set(Some("Tom & Jerry <b>Frieren</b>".to_string())),
+        ..Default::default()
+    }
+    .insert(&db)
+    .await
+    .unwrap();
+
+    let hits = search(&db, "alert", 10).await.unwrap();
+
+    assert_eq!(hits.len(), 1);
+    let highlights = &hits[0].highlights;
+    assert_eq!(
+        highlights.name,
+        "&lt;script&gt;<mark>alert</mark>('Frieren')&lt;/script&gt;"
+    );
+    assert_eq!(
+        highlights.description.as_deref(),
+        Some("Tom &amp; Jerry &lt;b&gt;Frieren&lt;/b&gt;")
+    );
+
+    // Without highlighting too
+    let hits = search(&db, "<b", 10).await.unwrap();
+
+    assert_eq!(hits.len(), 1);
+    assert_eq!(
+        hits[0].highlights.name,
+        "&lt;script&gt;alert('Frieren')&lt;/script&gt;"
+    );
+}
+
+#[tokio::test]
+async fn follows_updates_and_deletes() {
+    let db = database().await;
+
+    let series = entity::series::Entity::find()
+        .filter(entity::series::Column::Name.eq("Magic Kaito 1412"))
+        .one(&db)
+        .await
+        .unwrap()
+        .unwrap();
+    let mut series = entity::series::ActiveModel::from(series);
+    series.name = ActiveValue::Set("Detective Conan".to_string());
+    let series = series.update(&db).await.unwrap();
+
+    assert!(names(&db, "kaito").await.is_empty());
+    assert_eq!(names(&db, "conan").await, ["Detective Conan"]);
+
+    series.delete(&db).await.unwrap();
+    assert!(names(&db, "conan").await.is_empty());
+}
+
+#[tokio::test]
+async fn ignores_empty_queries() {
+    let db = database().await;
+
+    assert!(names(&db, "").await.is_empty());
+    assert!(names(&db, "   ").await.is_empty());
+}
@@ -0,0 +1,62 @@
+//! Parts of episodes, like openings, that users marked as skippable.
+//!
+//! Markers belong to an episode of a series rather than to a source, so they apply whichever site
+//! the episode is streamed from. Episode numbers are those of the series, so with the episode
+//! mapping of the source already applied.
+
+use anyhow::Result;
+use sea_orm::{prelude::*, ActiveValue, QueryOrder, TryIntoModel};
+
+use crate::metadata::{SkipMarker, SkipMarkerKind};
+
+/// Markers of a series, of a single episode if `episode_number` is set
+pub async fn list<C>(
+    db: &C,
+    series_id: i32,
+    episode_number: Option<f64>,
+) -> Result<Vec<entity::skip_markers::Model>>
+where
+    C: ConnectionTrait,
+{
+    let mut select = entity::skip_markers::Entity::find()
+        .filter(entity::skip_markers::Column::SeriesId.eq(series_id))
+        .order_by_asc(entity::skip_markers::Column::EpisodeNumber)
+        .order_by_asc(entity::skip_markers::Column::StartSeconds);
+    if let Some(episode_number) = episode_number {
+        select = select.filter(entity::skip_markers::Column::EpisodeNumber.eq(episode_number));
+    }
+
+    Ok(select.all(db).await?)
+}
+
+pub async fn add<C>(
+    db: &C,
+    user_id: i32,
+    series_id: i32,
+    episode_number: f64,
+    marker: &SkipMarker,
+) -> Result<entity::skip_markers::Model>
+where
+    C: ConnectionTrait,
+{
+    let model = entity::skip_markers::ActiveModel {
+        series_id: ActiveValue::Set(series_id),
+        episode_number: ActiveValue::Set(episode_number),
+        kind: ActiveValue::Set(marker.kind.as_str().to_string()),
+        start_seconds: ActiveValue::Set(marker.start),
+        end_seconds: ActiveValue::Set(marker.end),
+        user_id: ActiveValue::Set(Some(user_id)),
+        ..Default::default()
+    };
+
+    Ok(model.save(db).await?.try_into_model()?)
+}
+
+/// The marker in the format sites' markers are in, `None` if it's somehow invalid
+pub fn to_marker(model: &entity::skip_markers::Model) -> Option<SkipMarker> {
+    SkipMarker::new(
+        model.kind.parse::<SkipMarkerKind>().ok()?,
+        model.start_seconds,
+        model.end_seconds,
+    )
+}
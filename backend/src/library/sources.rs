@@ -0,0 +1,153 @@
+use anyhow::Result;
+use log::{debug, trace};
+use sea_orm::{prelude::*, ActiveValue, QueryOrder};
+
+use crate::metadata::{self, AnimeInfo, MetaSeriesInfo};
+
+/// Search the supported sites for a series and link every match to it.
+///
+/// Sources that are already linked are left alone.
+pub async fn discover_and_attach(
+    db: &DatabaseConnection,
+    series_id: i32,
+    name: &str,
+    mal_id: u32,
+) -> Result<Vec<entity::series_sources::Model>> {
+    trace!("Discovering sources for series={series_id:?} mal_id={mal_id:?}");
+
+    let found = metadata::discover_sources(name, mal_id).await?;
+
+    let mut attached = vec![];
+    for meta in found {
+        let Some(site) = meta.site() else {
+            continue;
+        };
+
+        let new = entity::series_sources::ActiveModel {
+            for_series_id: ActiveValue::Set(series_id),
+            series_site: ActiveValue::Set(site.to_string()),
+            series_site_id: ActiveValue::Set(meta.series_id().to_string()),
+            ..Default::default()
+        };
+
+        match new.insert(db).await {
+            Ok(source) => attached.push(source),
+            Err(e)
+                if e.sql_err()
+                    .map(|x| matches!(x, SqlErr::UniqueConstraintViolation(_)))
+                    .unwrap_or_default() =>
+            {
+                trace!("Source {meta:?} is already linked");
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    debug!(
+        "Attached {count} new sources to series {series_id}",
+        count = attached.len()
+    );
+
+    Ok(attached)
+}
+
+/// The source linking a site's series to one in the library, if there is one
+pub async fn find_by_meta<C>(
+    db: &C,
+    meta: &MetaSeriesInfo,
+) -> Result<Option<entity::series_sources::Model>>
+where
+    C: ConnectionTrait,
+{
+    let Some(site) = meta.site() else {
+        return Ok(None);
+    };
+
+    let source = entity::series_sources::Entity::find()
+        .filter(entity::series_sources::Column::SeriesSite.eq(site.to_string()))
+        .filter(entity::series_sources::Column::SeriesSiteId.eq(meta.series_id()))
+        .one(db)
+        .await?;
+
+    Ok(source)
+}
+
+/// Link a site's series to a series in the library
+pub async fn attach<C>(
+    db: &C,
+    series_id: i32,
+    meta: &MetaSeriesInfo,
+) -> Result<entity::series_sources::Model>
+where
+    C: ConnectionTrait,
+{
+    let Some(site) = meta.site() else {
+        anyhow::bail!("Sources can't be attached for {meta:?}");
+    };
+
+    let source = entity::series_sources::ActiveModel {
+        for_series_id: ActiveValue::Set(series_id),
+        series_site: ActiveValue::Set(site.to_string()),
+        series_site_id: ActiveValue::Set(meta.series_id().to_string()),
+        ..Default::default()
+    }
+    .insert(db)
+    .await?;
+
+    Ok(source)
+}
+
+/// The library's series for a site's series, created with the site's name if it isn't in the library yet.
+///
+/// Series are matched by MAL id first and name second. Returns whether the series was created.
+/// The rest of a created series is left to [`enrich::fill_created`](super::enrich::fill_created).
+pub async fn find_or_create_series<C>(
+    db: &C,
+    info: &AnimeInfo,
+) -> Result<(entity::series::Model, bool)>
+where
+    C: ConnectionTrait,
+{
+    #[allow(clippy::cast_possible_wrap)]
+    let mal_id = info.mal_id.map(|x| x as i32);
+
+    if let Some(mal_id) = mal_id {
+        let by_mal_id = entity::series::Entity::find()
+            .filter(entity::series::Column::MalId.eq(mal_id))
+            .order_by_asc(entity::series::Column::Id)
+            .one(db)
+            .await?;
+
+        if let Some(series) = by_mal_id {
+            return Ok((series, false));
+        }
+    }
+
+    let by_name = entity::series::Entity::find()
+        .filter(entity::series::Column::Name.eq(info.name.as_str()))
+        .one(db)
+        .await?;
+    if let Some(series) = by_name {
+        return Ok((series, false));
+    }
+
+    trace!("Creating series {:?} from {:?}", info.name, info.url);
+    let series = entity::series::ActiveModel {
+        name: ActiveValue::Set(info.name.clone()),
+        ..Default::default()
+    }
+    .insert(db)
+    .await?;
+
+    Ok((series, true))
+}
+
+/// The site's series a source points at
+pub fn meta(source: &entity::series_sources::Model) -> Result<MetaSeriesInfo> {
+    let meta = serde_json::from_value(serde_json::json!({
+        "site": source.series_site,
+        "seriesId": source.series_site_id,
+    }))?;
+
+    Ok(meta)
+}
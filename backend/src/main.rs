@@ -7,11 +7,15 @@
 use config::CONFIG;
 use log::trace;
 
+mod auth;
 mod config;
 mod db;
+mod library;
 mod logger;
 mod metadata;
 mod server;
+#[cfg(test)]
+mod test_util;
 
 fn main() {
     logger::init();
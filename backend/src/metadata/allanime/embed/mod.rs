@@ -0,0 +1,271 @@
+//! Following the sources allanime lists for an episode to the videos they point at.
+//!
+//! Most sources link to allanime's own embed API, which responds with JSON listing the HLS
+//! playlists or MP4 files of the source and the headers they have to be requested with.
+//! Some sources link to a video directly, and the rest are players of other sites that
+//! can only be used in an iframe, which are skipped.
+
+use std::collections::BTreeMap;
+
+use reqwest::header;
+
+use crate::metadata::{
+    allanime::query::{
+        client::{DEFAULT_HEADERS, QUERY_CLIENT, USER_AGENT},
+        models::episode::SourceUrl,
+    },
+    common::prelude::*,
+    StreamKind, StreamSource, SubtitleFormat, SubtitleTrack,
+};
+
+#[cfg(test)]
+mod tests;
+
+#[derive(Debug, Clone, Deserialize)]
+struct EmbedResponse {
+    #[serde(default)]
+    links: Vec<EmbedLink>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EmbedLink {
+    link: Option<String>,
+    #[serde(default)]
+    hls: bool,
+    #[serde(default)]
+    mp4: bool,
+    resolution_str: Option<String>,
+    #[serde(default)]
+    headers: BTreeMap<String, String>,
+    port_data: Option<PortData>,
+    #[serde(default)]
+    subtitles: Vec<EmbedSubtitle>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct EmbedSubtitle {
+    src: String,
+    lang: Option<String>,
+    label: Option<String>,
+    /// Either a bool or the string `default`
+    #[serde(default)]
+    default: serde_json::Value,
+}
+
+impl From<EmbedSubtitle> for SubtitleTrack {
+    fn from(subtitle: EmbedSubtitle) -> Self {
+        Self {
+            format: SubtitleFormat::of_url(&subtitle.src),
+            url: subtitle.src,
+            language: subtitle.lang.filter(|x| !x.is_empty()),
+            label: subtitle.label.filter(|x| !x.is_empty()),
+            default: match subtitle.default {
+                serde_json::Value::Bool(x) => x,
+                serde_json::Value::String(x) => !x.is_empty(),
+                _ => false,
+            },
+            vtt_url: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct PortData {
+    #[serde(default)]
+    streams: Vec<PortStream>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct PortStream {
+    format: Option<String>,
+    url: String,
+    hardsub_lang: Option<String>,
+}
+
+/// Headers every stream of allanime is requested with, unless the embed says otherwise.
+///
+/// Same `Origin` and `Referer` as the requests to the API, so it looks like the site's own player.
+fn default_headers() -> BTreeMap<String, String> {
+    let mut headers = [header::ORIGIN, header::REFERER]
+        .into_iter()
+        .filter_map(|name| {
+            let value = DEFAULT_HEADERS.get(&name)?.to_str().ok()?;
+
+            Some((name.to_string(), value.to_string()))
+        })
+        .collect::<BTreeMap<_, _>>();
+    headers.insert(header::USER_AGENT.to_string(), USER_AGENT.to_string());
+
+    headers
+}
+
+fn with_headers(extra: &BTreeMap<String, String>) -> BTreeMap<String, String> {
+    let mut headers = default_headers();
+    for (key, value) in extra {
+        // Keys are case insensitive, so the ones from the embed replace the defaults
+        headers.retain(|x, _| !x.eq_ignore_ascii_case(key));
+        headers.insert(key.to_lowercase(), value.clone());
+    }
+
+    headers
+}
+
+/// Only keep resolutions like `1080p`, the embed API also puts things like `Hls` there
+fn resolution(resolution: Option<&str>) -> Option<String> {
+    let resolution = resolution?.trim();
+    let digits = resolution.strip_suffix(['p', 'P'])?;
+
+    if !digits.is_empty() && digits.chars().all(|x| x.is_ascii_digit()) {
+        Some(resolution.to_lowercase())
+    } else {
+        None
+    }
+}
+
+fn stream(
+    source: &SourceUrl,
+    url: &str,
+    kind: StreamKind,
+    resolution: Option<String>,
+    hardsub_language: Option<String>,
+    headers: BTreeMap<String, String>,
+) -> StreamSource {
+    StreamSource {
+        url: url.to_string(),
+        kind,
+        name: source.source_name.clone(),
+        resolution,
+        hardsub_language: hardsub_language.filter(|x| !x.is_empty()),
+        headers,
+        expires_at: StreamSource::expiry_hint(url),
+        proxy_url: None,
+        variants: vec![],
+        renditions: vec![],
+        subtitles: vec![],
+    }
+}
+
+/// Parse the response of the embed API for a (decoded) source.
+///
+/// Links that aren't HLS playlists or MP4 files are left out.
+pub fn parse(source: &SourceUrl, body: &str) -> Result<Vec<StreamSource>> {
+    let response =
+        serde_json::from_str::<EmbedResponse>(body).context("Failed to parse embed response")?;
+
+    let mut streams = vec![];
+    for link in response.links {
+        let headers = with_headers(&link.headers);
+        let first = streams.len();
+
+        if let Some(url) = link.link.as_deref().filter(|x| x.starts_with("http")) {
+            let kind = if link.hls {
+                Some(StreamKind::Hls)
+            } else if link.mp4 {
+                Some(StreamKind::Mp4)
+            } else {
+                StreamKind::of_url(url)
+            };
+
+            match kind {
+                Some(kind) => streams.push(stream(
+                    source,
+                    url,
+                    kind,
+                    resolution(link.resolution_str.as_deref()),
+                    None,
+                    headers.clone(),
+                )),
+                None => trace!("Skipping embed link of unknown kind: {:?}", url),
+            }
+        }
+
+        // Some sources (eg. `Ak`) list the videos per hardsub language instead
+        for port_stream in link.port_data.map(|x| x.streams).unwrap_or_default() {
+            let kind = match port_stream.format.as_deref() {
+                Some(x) if x.contains("hls") => Some(StreamKind::Hls),
+                Some(x) if x.contains("mp4") => Some(StreamKind::Mp4),
+                _ => StreamKind::of_url(&port_stream.url),
+            };
+
+            match kind {
+                Some(kind) => streams.push(stream(
+                    source,
+                    &port_stream.url,
+                    kind,
+                    None,
+                    port_stream.hardsub_lang,
+                    headers.clone(),
+                )),
+                None => trace!(
+                    "Skipping embed stream of unknown kind: {:?}",
+                    port_stream.url
+                ),
+            }
+        }
+
+        let subtitles = link
+            .subtitles
+            .into_iter()
+            .filter(|x| x.src.starts_with("http"))
+            .map(SubtitleTrack::from)
+            .collect::<Vec<_>>();
+        for stream in &mut streams[first..] {
+            stream.subtitles = subtitles.clone();
+        }
+    }
+
+    Ok(streams)
+}
+
+/// Resolve a (decoded) source into the videos it points at
+pub async fn resolve(source: &SourceUrl) -> Result<Vec<StreamSource>> {
+    if source.is_embed() {
+        trace!("Fetching embed {:?}", source.source_url);
+        let body = QUERY_CLIENT
+            .get(&source.source_url)
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+
+        return parse(source, &body);
+    }
+
+    if let Some(kind) = StreamKind::of_url(&source.source_url) {
+        return Ok(vec![stream(
+            source,
+            &source.source_url,
+            kind,
+            None,
+            None,
+            default_headers(),
+        )]);
+    }
+
+    trace!("Skipping iframe source {:?}", source.source_name);
+    Ok(vec![])
+}
+
+/// Resolve all the sources of an episode, the ones allanime prefers first.
+///
+/// Sources that fail to resolve are logged and left out.
+pub async fn resolve_all(sources: &[SourceUrl]) -> Vec<StreamSource> {
+    let mut sources = sources.iter().collect::<Vec<_>>();
+    sources.sort_by(|a, b| b.priority.total_cmp(&a.priority));
+
+    let results = futures::future::join_all(sources.iter().map(|source| resolve(source))).await;
+
+    sources
+        .into_iter()
+        .zip(results)
+        .flat_map(|(source, result)| match result {
+            Ok(streams) => streams,
+            Err(e) => {
+                debug!("Failed to resolve source {:?}: {:?}", source.source_name, e);
+                vec![]
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,136 @@
+//! Resolving sources against responses captured from the embed API
+
+use chrono::{TimeZone, Utc};
+
+use super::{parse, resolve};
+use crate::metadata::{allanime::query::models::episode::SourceUrl, StreamKind, SubtitleFormat};
+
+const HLS_RESPONSE: &str = include_str!("fixtures/clock-hls.json");
+const MP4_RESPONSE: &str = include_str!("fixtures/clock-mp4.json");
+const PORT_DATA_RESPONSE: &str = include_str!("fixtures/clock-port-data.json");
+
+/// The inverse of [`SourceUrl::try_decode_source`] for sources prefixed with `--`
+fn encode(url: &str) -> String {
+    let key = "s5feqxw21".chars().map(u32::from).fold(0, |acc, x| acc ^ x);
+
+    url.chars().fold("--".to_string(), |mut acc, x| {
+        acc.push_str(&format!("{:02x}", u32::from(x) ^ key));
+        acc
+    })
+}
+
+fn source(name: &str, url: &str) -> SourceUrl {
+    SourceUrl {
+        source_url: url.to_string(),
+        priority: 7.0,
+        source_name: name.to_string(),
+        type_field: "iframe".to_string(),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn decodes_embed_sources() {
+    let mut source = source("Default", &encode("/apivtwo/clock?id=7d2473746a243c24"));
+    source.decode();
+
+    assert_eq!(
+        source.source_url,
+        "https://embed.ssbcontent.site/apivtwo/clock.json?id=7d2473746a243c24"
+    );
+    assert!(source.is_embed());
+}
+
+#[test]
+fn parses_hls_links() {
+    let streams = parse(&source("Default", ""), HLS_RESPONSE).unwrap();
+
+    assert_eq!(streams.len(), 1);
+    let stream = &streams[0];
+    assert_eq!(stream.kind, StreamKind::Hls);
+    assert_eq!(stream.name, "Default");
+    assert_eq!(StreamKind::of_url(&stream.url), Some(StreamKind::Hls));
+    // `Hls` isn't a resolution
+    assert_eq!(stream.resolution, None);
+    // The embed's own headers win over the defaults
+    assert_eq!(
+        stream.headers.get("referer").map(String::as_str),
+        Some("https://embtaku.pro/")
+    );
+    assert_eq!(
+        stream.headers.get("origin").map(String::as_str),
+        Some("https://embtaku.pro")
+    );
+    assert!(stream.headers.contains_key("user-agent"));
+    assert_eq!(stream.expires_at, None);
+
+    assert_eq!(stream.subtitles.len(), 2);
+    assert_eq!(stream.subtitles[0].format, Some(SubtitleFormat::Vtt));
+    assert_eq!(stream.subtitles[0].language.as_deref(), Some("en"));
+    assert!(stream.subtitles[0].default);
+    assert_eq!(stream.subtitles[1].format, Some(SubtitleFormat::Ass));
+    assert!(!stream.subtitles[1].default);
+}
+
+#[test]
+fn parses_mp4_links() {
+    let streams = parse(&source("S-mp4", ""), MP4_RESPONSE).unwrap();
+
+    assert_eq!(streams.len(), 2);
+    assert!(streams.iter().all(|x| x.kind == StreamKind::Mp4));
+    assert!(streams
+        .iter()
+        .all(|x| x.headers.get("referer").map(String::as_str) == Some("https://allanime.to")));
+
+    assert_eq!(
+        streams[0].expires_at,
+        Some(Utc.timestamp_opt(1_698_681_835, 0).unwrap())
+    );
+    // Not marked as mp4, so the kind comes from the extension
+    assert_eq!(streams[1].resolution.as_deref(), Some("1080p"));
+    assert_eq!(streams[1].expires_at, None);
+}
+
+#[test]
+fn parses_port_data_streams() {
+    let streams = parse(&source("Ak", ""), PORT_DATA_RESPONSE).unwrap();
+
+    // The DASH manifest isn't something we can play
+    assert_eq!(streams.len(), 2);
+    assert!(streams.iter().all(|x| x.kind == StreamKind::Hls));
+    assert_eq!(streams[0].hardsub_language.as_deref(), Some("en-US"));
+    assert_eq!(streams[1].hardsub_language, None);
+    // Expiry in milliseconds
+    assert_eq!(
+        streams[0].expires_at,
+        Some(Utc.timestamp_opt(1_698_764_235, 0).unwrap())
+    );
+}
+
+#[test]
+fn rejects_invalid_responses() {
+    assert!(parse(&source("Default", ""), "<html>Not found</html>").is_err());
+    assert!(parse(&source("Default", ""), "{}").unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn resolves_direct_links_without_fetching() {
+    let streams = resolve(&source(
+        "Yt-mp4",
+        "https://tools.fast4speed.rsvp/media9/videos/QTK8MjjzpLBmXk4Tq/sub/1.mp4",
+    ))
+    .await
+    .unwrap();
+
+    assert_eq!(streams.len(), 1);
+    assert_eq!(streams[0].kind, StreamKind::Mp4);
+
+    let streams = resolve(&source(
+        "Mp4",
+        "https://www.mp4upload.com/embed-2dxfqzxoyn1v.html",
+    ))
+    .await
+    .unwrap();
+
+    assert!(streams.is_empty());
+}
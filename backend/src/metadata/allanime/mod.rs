@@ -2,11 +2,20 @@ use serde_with::{serde_as, DisplayFromStr};
 
 use self::query::models::episode::Episode;
 
-use super::{common::prelude::*, AnimeInfo, SeriesTranslation};
+use url::Url;
+
+use super::{
+    common::{prelude::*, util},
+    AnimeInfo, MetaEpisodeInfo, MetaSeriesInfo, ResolvedUrl, SeriesTranslation, StreamSource,
+};
 
 mod anime;
+pub mod embed;
 pub mod query;
 
+#[cfg(test)]
+mod tests;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct AllanimeSeries {
@@ -29,6 +38,66 @@ pub async fn series_info(info: AllanimeSeries) -> Result<AnimeInfo> {
     anime::get_series_info(&info.id).await
 }
 
+pub async fn search_by_mal_id(query: &str, mal_id: u32) -> Result<Vec<AllanimeSeries>> {
+    let shows = query::search_shows(query).await?;
+
+    Ok(shows
+        .into_iter()
+        .filter(|x| {
+            x.mal_id
+                .as_ref()
+                .and_then(|x| x.0.parse::<u32>().ok())
+                .is_some_and(|x| x == mal_id)
+        })
+        .filter_map(|x| x.id)
+        .map(|id| AllanimeSeries { id })
+        .collect())
+}
+
 pub async fn episode_info(info: AllanimeEpisode) -> Result<Episode> {
     anime::get_episode_info(&info.series.id, info.episode_number, info.episode_type).await
 }
+
+pub async fn episode_streams(info: AllanimeEpisode) -> Result<Vec<StreamSource>> {
+    let episode = episode_info(info).await?;
+
+    Ok(embed::resolve_all(&episode.source_urls).await)
+}
+
+/// Parse links like `https://allanime.to/bangumi/<id>/<slug>` or `https://allanime.to/bangumi/<id>/<slug>/p-3-sub`
+pub fn resolve_url(url: &Url) -> Option<ResolvedUrl> {
+    if !util::is_same_site(url, query::client::BASE_SITE_URL) {
+        return None;
+    }
+
+    let segments = url
+        .path_segments()?
+        .filter(|x| !x.is_empty())
+        .collect::<Vec<_>>();
+    let (id, rest) = match segments.as_slice() {
+        ["bangumi" | "anime", id, rest @ ..] => (*id, rest),
+        _ => return None,
+    };
+
+    let series = AllanimeSeries { id: id.to_string() };
+    let episode = rest
+        .iter()
+        .find_map(|x| x.strip_prefix("p-"))
+        .and_then(|x| x.rsplit_once('-'))
+        .and_then(|(number, translation)| {
+            Some(AllanimeEpisode {
+                series: series.clone(),
+                episode_number: number.parse().ok()?,
+                // Episodes of unknown translations can't be looked up
+                episode_type: translation
+                    .parse()
+                    .ok()
+                    .filter(|x| *x != SeriesTranslation::Unknown)?,
+            })
+        });
+
+    Some(ResolvedUrl {
+        series: MetaSeriesInfo::Allanime(series),
+        episode: episode.map(MetaEpisodeInfo::Allanime),
+    })
+}
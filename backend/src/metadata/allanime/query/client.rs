@@ -14,7 +14,7 @@ pub const USER_AGENT: &str =
     "Mozilla/5.0 (X11; Linux x86_64; rv:109.0) Gecko/20100101 Firefox/118.0";
 
 lazy_static! {
-    static ref DEFAULT_HEADERS: HeaderMap = HeaderMap::from_iter([
+    pub static ref DEFAULT_HEADERS: HeaderMap = HeaderMap::from_iter([
         (
             header::ACCEPT,
             HeaderValue::from_static("application/json, text/plain, */*"),
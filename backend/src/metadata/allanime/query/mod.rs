@@ -12,7 +12,7 @@ use self::models::{
     show::{ShowEpisode, ShowEpisodes},
 };
 
-mod client;
+pub mod client;
 pub mod models;
 
 mod show_info_2 {
@@ -65,9 +65,19 @@ mod show_info_2 {
     }
 }
 
+fn html_to_text(html: &str) -> String {
+    let doc = Html::parse_document(html);
+
+    doc.root_element()
+        .text()
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
 #[allow(clippy::too_many_lines)]
 pub async fn show_info(id: &str) -> Result<metadata::AnimeInfo> {
-    use remote_graphql_queries::prelude::*;
+    use remote_graphql_queries::{allanime::common_::TranslationType, prelude::*};
     trace!("Getting show info for {}", id);
 
     let resp: allanime::show_info::ShowInfo = allanime::do_query(
@@ -99,59 +109,59 @@ pub async fn show_info(id: &str) -> Result<metadata::AnimeInfo> {
     let episodes = episode_infos
         .into_iter()
         .filter_map(|x| {
-            let mut ret = vec![];
-
             let episode_number = x.episode_id_num?;
             let title = x.title.clone().unwrap_or_default();
+            let description = x
+                .description
+                .as_deref()
+                .map(html_to_text)
+                .filter(|x| !x.is_empty());
+            // Relative thumbnails point at a CDN whose host isn't known here
+            let thumbnail = x
+                .thumbnails
+                .iter()
+                .flatten()
+                .flatten()
+                .find(|x| x.starts_with("https://") || x.starts_with("http://"))
+                .cloned();
 
-            if x.sub_info.is_some() {
-                ret.push(metadata::EpisodeInfo {
-                    id: show_id.clone(),
-                    episode_number,
-                    title: title.clone(),
-                    translation: SeriesTranslation::Sub,
-                    url: format!(
-                        "{base}/bangumi/{id}/{slug}/p-{num}-{t}",
-                        base = BASE_SITE_URL,
-                        id = id,
-                        slug = show_title_slug.clone(),
-                        num = episode_number,
-                        t = "sub",
-                    ),
-                });
-            }
-
-            if x.dub_info.is_some() {
-                ret.push(metadata::EpisodeInfo {
-                    id: show_id.clone(),
-                    episode_number,
-                    title: title.clone(),
-                    translation: SeriesTranslation::Dub,
-                    url: format!(
-                        "{base}/bangumi/{id}/{slug}/p-{num}-{t}",
-                        base = BASE_SITE_URL,
-                        id = id,
-                        slug = show_title_slug.clone(),
-                        num = episode_number,
-                        t = "dub"
-                    ),
-                });
-            }
+            let ret = [
+                (&x.sub_info, TranslationType::Sub, SeriesTranslation::Sub),
+                (&x.dub_info, TranslationType::Dub, SeriesTranslation::Dub),
+                (&x.raw_info, TranslationType::Raw, SeriesTranslation::Raw),
+            ]
+            .into_iter()
+            .filter(|(info, _, _)| info.is_some())
+            .map(|(_, for_type, translation)| metadata::EpisodeInfo {
+                id: show_id.clone(),
+                episode_number,
+                title: title.clone(),
+                translation,
+                url: format!(
+                    "{base}/bangumi/{id}/{slug}/p-{num}-{t}",
+                    base = BASE_SITE_URL,
+                    id = id,
+                    slug = show_title_slug.clone(),
+                    num = episode_number,
+                    t = match translation {
+                        SeriesTranslation::Dub => "dub",
+                        SeriesTranslation::Sub => "sub",
+                        SeriesTranslation::Raw | SeriesTranslation::Unknown => "raw",
+                    },
+                ),
+                description: description.clone(),
+                thumbnail: thumbnail.clone(),
+                available_at: x.uploaded_at(&for_type),
+                is_filler: false,
+            })
+            .collect::<Vec<_>>();
 
             Some(ret)
         })
         .flatten()
         .collect::<Vec<_>>();
 
-    let description = show.description.map(|x| {
-        let doc = Html::parse_document(&x);
-
-        doc.root_element()
-            .text()
-            .collect::<String>()
-            .trim()
-            .to_string()
-    });
+    let description = show.description.as_deref().map(html_to_text);
 
     let mut meta: HashMap<String, serde_json::Value> = HashMap::new();
     meta.insert("countryOfOrigin".into(), json!(show.country_of_origin));
@@ -188,6 +198,28 @@ pub async fn show_info(id: &str) -> Result<metadata::AnimeInfo> {
     })
 }
 
+pub async fn search_shows(
+    query: &str,
+) -> Result<Vec<remote_graphql_queries::allanime::search_shows::SearchShow>> {
+    use remote_graphql_queries::prelude::*;
+    trace!("Searching shows for {:?}", query);
+
+    let resp: allanime::search_shows::SearchShows = allanime::do_query(
+        allanime::search_shows::SearchShows::build(allanime::search_shows::SearchShowsVariables {
+            search: allanime::search_shows::SearchInput {
+                query: Some(query.to_string()),
+                allow_adult: Some(true),
+                allow_unknown: Some(true),
+            },
+            limit: Some(26),
+        }),
+    )
+    .await
+    .context(format!("Failed to search shows for query={:?}", query))?;
+
+    Ok(resp.shows.edges.unwrap_or_default())
+}
+
 #[allow(dead_code)]
 pub async fn show_episodes(id: &str) -> Result<Vec<ShowEpisode>> {
     trace!("Getting show episodes for {}", id);
@@ -224,7 +256,7 @@ pub async fn episode_info(
             "translationType": match episode_type {
                 SeriesTranslation::Dub => "dub",
                 SeriesTranslation::Sub => "sub",
-                SeriesTranslation::Unknown => "raw",
+                SeriesTranslation::Raw | SeriesTranslation::Unknown => "raw",
             },
             "episodeString": episode_number.to_string(),
         }),
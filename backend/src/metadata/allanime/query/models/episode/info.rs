@@ -114,7 +114,6 @@ impl SourceUrl {
         self.decoded();
     }
 
-    #[allow(dead_code)]
     pub fn is_embed(&self) -> bool {
         self.source_url.starts_with(BASE_EMBED_URL.as_str())
     }
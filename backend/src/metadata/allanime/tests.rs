@@ -0,0 +1,90 @@
+//! Links copied from the site
+
+use url::Url;
+
+use super::resolve_url;
+use crate::metadata::{MetaEpisodeInfo, MetaSeriesInfo, SeriesTranslation};
+
+const ID: &str = "ReooPAxPMsHM4KPMY";
+
+/// Series id, and episode number and translation a link resolves to
+fn resolve(url: &str) -> Option<(String, Option<(String, SeriesTranslation)>)> {
+    let resolved = resolve_url(&Url::parse(url).unwrap())?;
+
+    let MetaSeriesInfo::Allanime(series) = resolved.series else {
+        panic!("Resolved to another site: {:?}", resolved.series);
+    };
+    let episode = resolved.episode.map(|x| match x {
+        MetaEpisodeInfo::Allanime(episode) => {
+            (episode.episode_number.to_string(), episode.episode_type)
+        }
+        MetaEpisodeInfo::Aniwatch(_) => panic!("Resolved to another site: {x:?}"),
+    });
+
+    Some((series.id, episode))
+}
+
+#[test]
+fn resolves_series_and_episodes() {
+    for (path, episode) in [
+        (format!("/bangumi/{ID}"), None),
+        (format!("/bangumi/{ID}/helck"), None),
+        (format!("/bangumi/{ID}/helck/"), None),
+        (format!("/anime/{ID}/helck"), None),
+        (
+            format!("/bangumi/{ID}/helck/p-3-sub"),
+            Some(("3", SeriesTranslation::Sub)),
+        ),
+        (
+            format!("/bangumi/{ID}/helck/p-3-dub?autoplay=1"),
+            Some(("3", SeriesTranslation::Dub)),
+        ),
+        // Specials are numbered between episodes
+        (
+            format!("/anime/{ID}/p-12.5-raw"),
+            Some(("12.5", SeriesTranslation::Raw)),
+        ),
+        // Episodes that can't be made sense of still resolve the series
+        (format!("/bangumi/{ID}/helck/p-3-fandub"), None),
+        (format!("/bangumi/{ID}/helck/p-three-sub"), None),
+        (format!("/bangumi/{ID}/helck/p-3"), None),
+    ] {
+        for base in ["https://allanime.to", "https://www.allanime.to"] {
+            let url = format!("{base}{path}");
+
+            assert_eq!(
+                resolve(&url),
+                Some((
+                    ID.to_string(),
+                    episode.map(|(number, translation)| (number.to_string(), translation))
+                )),
+                "{url}"
+            );
+        }
+    }
+}
+
+#[test]
+fn ignores_other_sites() {
+    for url in [
+        format!("https://aniwatch.to/bangumi/{ID}/helck"),
+        format!("https://aniwave.to/anime/{ID}/helck"),
+        format!("https://allanime.to.example.com/bangumi/{ID}/helck"),
+        format!("https://notallanime.to/bangumi/{ID}/helck"),
+    ] {
+        assert_eq!(resolve(&url), None, "{url}");
+    }
+}
+
+#[test]
+fn ignores_other_pages() {
+    for url in [
+        "https://allanime.to",
+        "https://allanime.to/bangumi",
+        "https://allanime.to/anime",
+        "https://allanime.to/search-anime?query=helck",
+        "https://allanime.to/manga/helck",
+    ] {
+        assert_eq!(resolve(url), None, "{url}");
+    }
+}
@@ -6,7 +6,9 @@ use serde::{Deserialize, Serialize};
 use serde_json::json;
 use tokio::task;
 
-use crate::metadata::{aniwatch::request, EpisodeInfo, SeriesTranslation};
+use crate::metadata::{
+    aniwatch::request, EpisodeInfo, SeriesTranslation, SkipMarker, SkipMarkerKind,
+};
 
 pub async fn get_list(anime_id: &str) -> Result<Vec<EpisodeInfo>> {
     debug!("Getting episode list for {id}", id = anime_id);
@@ -57,12 +59,18 @@ pub async fn get_list(anime_id: &str) -> Result<Vec<EpisodeInfo>> {
                 .ok_or_else(|| anyhow!("Couldn't extract url"))?
                 .to_string();
 
+            let is_filler = el.classes().any(|x| x == "ssl-item-filler");
+
             Ok(EpisodeInfo {
                 id,
                 title,
                 episode_number,
                 url: format!("{BASE_URL}{url}", BASE_URL = request::BASE_URL, url = url),
                 translation: SeriesTranslation::Unknown,
+                description: None,
+                thumbnail: None,
+                available_at: None,
+                is_filler,
             })
         })
         .collect()
@@ -74,12 +82,33 @@ struct ApiHtmlResponse {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct EpisodeSource {
     id: String,
     name: String,
     url: String,
     #[serde(rename = "type")]
     source_type: SeriesTranslation,
+    skip_markers: Vec<SkipMarker>,
+}
+
+/// Aniwatch reports `{"start": 0, "end": 0}` for parts it doesn't know about
+fn skip_markers(resp: &serde_json::Map<String, serde_json::Value>) -> Vec<SkipMarker> {
+    [
+        ("intro", SkipMarkerKind::Intro),
+        ("outro", SkipMarkerKind::Outro),
+    ]
+    .into_iter()
+    .filter_map(|(key, kind)| {
+        let marker = resp.get(key)?;
+
+        SkipMarker::new(
+            kind,
+            marker.get("start")?.as_f64()?,
+            marker.get("end")?.as_f64()?,
+        )
+    })
+    .collect()
 }
 
 fn get_episode_sources_from_html(html: &str) -> Vec<EpisodeSource> {
@@ -109,6 +138,7 @@ fn get_episode_sources_from_html(html: &str) -> Vec<EpisodeSource> {
                 name,
                 source_type,
                 url: String::new(),
+                skip_markers: vec![],
             })
         })
         .collect();
@@ -179,6 +209,7 @@ pub async fn get_info(anime_id: &str, episode_id: &str) -> Result<serde_json::Va
                 .get("link")
                 .and_then(|x| x.as_str())
                 .map(std::string::ToString::to_string)?;
+            source.skip_markers = skip_markers(&resp);
 
             Some(source)
         })
@@ -1,12 +1,17 @@
+use url::Url;
+
 use super::{
     common::{prelude::*, util},
-    AnimeInfo,
+    AnimeInfo, MetaEpisodeInfo, MetaSeriesInfo, ResolvedUrl,
 };
 
 mod anime;
 mod episode;
 mod request;
 
+#[cfg(test)]
+mod tests;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct AniwatchSeries {
@@ -31,3 +36,43 @@ pub async fn series_info(info: AniwatchSeries) -> Result<AnimeInfo> {
 pub async fn episode_info(info: AniwatchEpisode) -> Result<serde_json::Value> {
     episode::get_info(&info.series.id, &info.episode_id).await
 }
+
+/// Parse links like `https://aniwatch.to/watch/helck-18475?ep=103690` or `https://aniwatch.to/helck-18475`
+pub fn resolve_url(url: &Url) -> Option<ResolvedUrl> {
+    if !util::is_same_site(url, request::BASE_URL) {
+        return None;
+    }
+
+    let segments = url
+        .path_segments()?
+        .filter(|x| !x.is_empty())
+        .collect::<Vec<_>>();
+    let id = match segments.as_slice() {
+        ["watch", id] | [id] => *id,
+        _ => return None,
+    };
+    // Series ids always end in a number, which keeps out pages like `/home`
+    if !id
+        .rsplit_once('-')
+        .is_some_and(|(_, x)| !x.is_empty() && x.chars().all(|x| x.is_ascii_digit()))
+    {
+        return None;
+    }
+
+    let series = AniwatchSeries {
+        id: id.to_string(),
+        estimate_release_time: false,
+    };
+    let episode = url
+        .query_pairs()
+        .find(|(k, v)| k == "ep" && !v.is_empty())
+        .map(|(_, episode_id)| AniwatchEpisode {
+            series: series.clone(),
+            episode_id: episode_id.to_string(),
+        });
+
+    Some(ResolvedUrl {
+        series: MetaSeriesInfo::Aniwatch(series),
+        episode: episode.map(MetaEpisodeInfo::Aniwatch),
+    })
+}
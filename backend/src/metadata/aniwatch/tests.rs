@@ -0,0 +1,83 @@
+//! Links copied from the site
+
+use url::Url;
+
+use super::resolve_url;
+use crate::metadata::{MetaEpisodeInfo, MetaSeriesInfo};
+
+/// Series id and episode id a link resolves to
+fn resolve(url: &str) -> Option<(String, Option<String>)> {
+    let resolved = resolve_url(&Url::parse(url).unwrap())?;
+
+    let MetaSeriesInfo::Aniwatch(series) = resolved.series else {
+        panic!("Resolved to another site: {:?}", resolved.series);
+    };
+    let episode = resolved.episode.map(|x| match x {
+        MetaEpisodeInfo::Aniwatch(episode) => episode.episode_id,
+        MetaEpisodeInfo::Allanime(_) => panic!("Resolved to another site: {x:?}"),
+    });
+
+    Some((series.id, episode))
+}
+
+#[test]
+fn resolves_series_and_episodes() {
+    for (url, series, episode) in [
+        ("https://aniwatch.to/helck-18475", "helck-18475", None),
+        ("https://aniwatch.to/helck-18475/", "helck-18475", None),
+        ("https://www.aniwatch.to/helck-18475", "helck-18475", None),
+        ("http://aniwatch.to/helck-18475", "helck-18475", None),
+        ("https://aniwatch.to/watch/helck-18475", "helck-18475", None),
+        (
+            "https://aniwatch.to/watch/helck-18475?ep=103690",
+            "helck-18475",
+            Some("103690"),
+        ),
+        (
+            "https://aniwatch.to/watch/helck-18475?ref=search&ep=103690#player",
+            "helck-18475",
+            Some("103690"),
+        ),
+        // Without an episode id there is no episode
+        (
+            "https://aniwatch.to/watch/helck-18475?ep=",
+            "helck-18475",
+            None,
+        ),
+    ] {
+        assert_eq!(
+            resolve(url),
+            Some((series.to_string(), episode.map(String::from))),
+            "{url}"
+        );
+    }
+}
+
+#[test]
+fn ignores_other_sites() {
+    for url in [
+        "https://aniwave.to/helck-18475",
+        "https://allanime.to/helck-18475",
+        "https://aniwatch.to.example.com/helck-18475",
+        "https://notaniwatch.to/helck-18475",
+    ] {
+        assert_eq!(resolve(url), None, "{url}");
+    }
+}
+
+#[test]
+fn ignores_other_pages() {
+    for url in [
+        "https://aniwatch.to",
+        "https://aniwatch.to/home",
+        "https://aniwatch.to/watch",
+        "https://aniwatch.to/search?keyword=helck",
+        "https://aniwatch.to/genre/action",
+        "https://aniwatch.to/watch/helck-18475/extra",
+        "https://aniwatch.to/18475",
+        "https://aniwatch.to/helck-",
+        "https://aniwatch.to/helck-18475a",
+    ] {
+        assert_eq!(resolve(url), None, "{url}");
+    }
+}
@@ -1,8 +1,16 @@
-use super::{common::prelude::*, AnimeInfo};
+use url::Url;
+
+use super::{
+    common::{prelude::*, util},
+    AnimeInfo, MetaSeriesInfo, ResolvedUrl,
+};
 
 mod anime;
 mod request;
 
+#[cfg(test)]
+mod tests;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct AniwaveSeries {
@@ -13,3 +21,29 @@ pub struct AniwaveSeries {
 pub async fn series_info(info: AniwaveSeries) -> Result<AnimeInfo> {
     anime::get_info(&info.id).await
 }
+
+/// Parse links like `https://aniwave.to/watch/helck.q1mv9/ep-1`.
+///
+/// Episodes of Aniwave can't be looked up, so only the series is resolved.
+pub fn resolve_url(url: &Url) -> Option<ResolvedUrl> {
+    if !util::is_same_site(url, request::BASE_URL) {
+        return None;
+    }
+
+    let segments = url
+        .path_segments()?
+        .filter(|x| !x.is_empty())
+        .collect::<Vec<_>>();
+    let id = match segments.as_slice() {
+        ["watch", id] | ["watch", id, _] => *id,
+        _ => return None,
+    };
+    if !id.contains('.') {
+        return None;
+    }
+
+    Some(ResolvedUrl {
+        series: MetaSeriesInfo::Aniwave(AniwaveSeries { id: id.to_string() }),
+        episode: None,
+    })
+}
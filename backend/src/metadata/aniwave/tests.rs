@@ -0,0 +1,60 @@
+//! Links copied from the site
+
+use url::Url;
+
+use super::resolve_url;
+use crate::metadata::MetaSeriesInfo;
+
+/// Series id a link resolves to
+fn resolve(url: &str) -> Option<String> {
+    let resolved = resolve_url(&Url::parse(url).unwrap())?;
+    assert!(resolved.episode.is_none(), "{url}");
+
+    match resolved.series {
+        MetaSeriesInfo::Aniwave(series) => Some(series.id),
+        series => panic!("Resolved to another site: {series:?}"),
+    }
+}
+
+#[test]
+fn resolves_series() {
+    for url in [
+        "https://aniwave.to/watch/helck.q1mv9",
+        "https://aniwave.to/watch/helck.q1mv9/",
+        "https://www.aniwave.to/watch/helck.q1mv9",
+        "http://aniwave.to/watch/helck.q1mv9",
+        // Episodes only resolve to their series
+        "https://aniwave.to/watch/helck.q1mv9/ep-1",
+        "https://aniwave.to/watch/helck.q1mv9/ep-12?autoplay=1#player",
+    ] {
+        assert_eq!(resolve(url).as_deref(), Some("helck.q1mv9"), "{url}");
+    }
+}
+
+#[test]
+fn ignores_other_sites() {
+    for url in [
+        "https://aniwatch.to/watch/helck.q1mv9",
+        "https://allanime.to/watch/helck.q1mv9",
+        "https://aniwave.to.example.com/watch/helck.q1mv9",
+        "https://notaniwave.to/watch/helck.q1mv9",
+    ] {
+        assert_eq!(resolve(url), None, "{url}");
+    }
+}
+
+#[test]
+fn ignores_other_pages() {
+    for url in [
+        "https://aniwave.to",
+        "https://aniwave.to/home",
+        "https://aniwave.to/watch",
+        "https://aniwave.to/filter?keyword=helck",
+        "https://aniwave.to/helck.q1mv9",
+        // Ids always have a dot before the part that makes them unique
+        "https://aniwave.to/watch/helck",
+        "https://aniwave.to/watch/helck.q1mv9/ep-1/extra",
+    ] {
+        assert_eq!(resolve(url), None, "{url}");
+    }
+}
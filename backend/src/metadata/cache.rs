@@ -0,0 +1,57 @@
+//! Short-lived in-memory cache of series info.
+//!
+//! Pages that show the same series over and over shouldn't make the sites
+//! load it again every time.
+
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use lazy_static::lazy_static;
+
+use super::{AnimeInfo, MetaSeriesInfo};
+use crate::config::CONFIG;
+
+lazy_static! {
+    static ref SERIES_INFO: Mutex<HashMap<String, (Instant, AnimeInfo)>> =
+        Mutex::new(HashMap::new());
+}
+
+fn ttl() -> Duration {
+    CONFIG.library.source_cache_ttl
+}
+
+fn key(meta: &MetaSeriesInfo) -> Option<String> {
+    serde_json::to_string(meta).ok()
+}
+
+/// The cached info of a series, if it was fetched recently enough
+pub fn series_info(meta: &MetaSeriesInfo) -> Option<AnimeInfo> {
+    let key = key(meta)?;
+    let cache = SERIES_INFO.lock().ok()?;
+    let (fetched_at, info) = cache.get(&key)?;
+
+    if fetched_at.elapsed() > ttl() {
+        return None;
+    }
+
+    Some(info.clone())
+}
+
+pub fn put_series_info(meta: &MetaSeriesInfo, info: &AnimeInfo) {
+    let ttl = ttl();
+    if ttl.is_zero() {
+        return;
+    }
+    let Some(key) = key(meta) else {
+        return;
+    };
+    let Ok(mut cache) = SERIES_INFO.lock() else {
+        return;
+    };
+
+    cache.retain(|_, (fetched_at, _)| fetched_at.elapsed() <= ttl);
+    cache.insert(key, (Instant::now(), info.clone()));
+}
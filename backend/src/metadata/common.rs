@@ -1,4 +1,19 @@
 pub mod util {
+    use url::Url;
+
+    /// Whether the URL points at the same site as `base`, with or without `www.`
+    pub fn is_same_site(url: &Url, base: &str) -> bool {
+        let Some(base) = Url::parse(base).ok() else {
+            return false;
+        };
+        let strip = |host: &str| host.trim_start_matches("www.").to_lowercase();
+
+        match (url.host_str(), base.host_str()) {
+            (Some(host), Some(base)) => strip(host) == strip(base),
+            _ => false,
+        }
+    }
+
     pub mod bool_str {
         use serde::{Deserialize, Deserializer, Serializer};
 
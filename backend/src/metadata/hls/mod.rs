@@ -0,0 +1,166 @@
+//! Reading the qualities and alternative tracks of HLS streams from their master playlists.
+
+use std::{collections::HashMap, time::Duration};
+
+use reqwest::Client;
+use url::Url;
+
+use super::{
+    common::prelude::*, public_client, RenditionKind, StreamKind, StreamRendition, StreamSource,
+    StreamVariant,
+};
+
+#[cfg(test)]
+mod tests;
+
+/// How long to wait for a master playlist before handing out the stream without its variants
+const FETCH_TIMEOUT: Duration = Duration::from_secs(5);
+
+lazy_static! {
+    static ref CLIENT: Client = public_client::builder()
+        .connect_timeout(Duration::from_secs(3))
+        .timeout(FETCH_TIMEOUT)
+        .build()
+        .unwrap();
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MasterPlaylist {
+    /// Ordered by bandwidth, highest first
+    pub variants: Vec<StreamVariant>,
+    pub renditions: Vec<StreamRendition>,
+}
+
+/// Attributes of a tag, like `BANDWIDTH=1280000,CODECS="avc1.4d401f,mp4a.40.2"`
+fn attributes(list: &str) -> HashMap<String, String> {
+    let mut attributes = HashMap::new();
+    let mut rest = list.trim();
+
+    while let Some((key, value)) = rest.split_once('=') {
+        let (value, next) = if let Some(quoted) = value.strip_prefix('"') {
+            match quoted.split_once('"') {
+                Some((value, next)) => (value, next),
+                None => (quoted, ""),
+            }
+        } else {
+            value.split_once(',').unwrap_or((value, ""))
+        };
+
+        attributes.insert(key.trim().to_uppercase(), value.to_string());
+        rest = next.trim_start_matches(',').trim_start();
+    }
+
+    attributes
+}
+
+fn is_yes(value: Option<&String>) -> bool {
+    value.is_some_and(|x| x.eq_ignore_ascii_case("YES"))
+}
+
+/// Parse a master playlist fetched from `base`.
+///
+/// Media playlists have neither variants nor renditions, so they parse into an empty one.
+pub fn parse_master(playlist: &str, base: &Url) -> MasterPlaylist {
+    let join = |uri: &str| base.join(uri.trim()).map(String::from).ok();
+
+    let mut master = MasterPlaylist::default();
+    let mut lines = playlist.lines().map(str::trim).filter(|x| !x.is_empty());
+    while let Some(line) = lines.next() {
+        if let Some(list) = line.strip_prefix("#EXT-X-STREAM-INF:") {
+            let attributes = attributes(list);
+            // The URI of the variant is the next line that isn't a tag
+            let Some(url) = lines.find(|x| !x.starts_with('#')).and_then(join) else {
+                continue;
+            };
+            let (width, height) = attributes
+                .get("RESOLUTION")
+                .and_then(|x| x.split_once(['x', 'X']))
+                .map_or((None, None), |(width, height)| {
+                    (width.parse().ok(), height.parse().ok())
+                });
+
+            master.variants.push(StreamVariant {
+                url,
+                bandwidth: attributes
+                    .get("BANDWIDTH")
+                    .and_then(|x| x.parse().ok())
+                    .unwrap_or_default(),
+                average_bandwidth: attributes
+                    .get("AVERAGE-BANDWIDTH")
+                    .and_then(|x| x.parse().ok()),
+                width,
+                height,
+                codecs: attributes.get("CODECS").cloned(),
+                audio_group: attributes.get("AUDIO").cloned(),
+                subtitles_group: attributes.get("SUBTITLES").cloned(),
+                proxy_url: None,
+            });
+        } else if let Some(list) = line.strip_prefix("#EXT-X-MEDIA:") {
+            let attributes = attributes(list);
+            let kind = match attributes.get("TYPE").map(String::as_str) {
+                Some("AUDIO") => RenditionKind::Audio,
+                Some("VIDEO") => RenditionKind::Video,
+                Some("SUBTITLES") => RenditionKind::Subtitles,
+                Some("CLOSED-CAPTIONS") => RenditionKind::ClosedCaptions,
+                _ => continue,
+            };
+
+            master.renditions.push(StreamRendition {
+                kind,
+                group_id: attributes.get("GROUP-ID").cloned().unwrap_or_default(),
+                name: attributes.get("NAME").cloned().unwrap_or_default(),
+                language: attributes.get("LANGUAGE").cloned(),
+                url: attributes.get("URI").and_then(|x| join(x)),
+                default: is_yes(attributes.get("DEFAULT")),
+                autoselect: is_yes(attributes.get("AUTOSELECT")),
+                proxy_url: None,
+            });
+        }
+    }
+
+    master
+        .variants
+        .sort_by(|a, b| b.bandwidth.cmp(&a.bandwidth));
+
+    master
+}
+
+async fn fetch_master(stream: &StreamSource) -> Result<MasterPlaylist> {
+    public_client::check_url(&Url::parse(&stream.url)?)?;
+
+    let mut request = CLIENT.get(&stream.url);
+    for (name, value) in &stream.headers {
+        request = request.header(name, value);
+    }
+
+    let response = request.send().await?.error_for_status()?;
+    let base = response.url().clone();
+    let playlist = response.text().await?;
+
+    Ok(parse_master(&playlist, &base))
+}
+
+/// Fill in the variants and renditions of the HLS streams.
+///
+/// Streams whose playlist can't be fetched are left as they are.
+pub async fn describe(streams: &mut [StreamSource]) {
+    let results = futures::future::join_all(streams.iter().map(|stream| async move {
+        if stream.kind == StreamKind::Hls {
+            Some(fetch_master(stream).await)
+        } else {
+            None
+        }
+    }))
+    .await;
+
+    for (stream, result) in streams.iter_mut().zip(results) {
+        match result {
+            Some(Ok(master)) => {
+                stream.variants = master.variants;
+                stream.renditions = master.renditions;
+            }
+            Some(Err(e)) => debug!("Failed to fetch master playlist {:?}: {:?}", stream.url, e),
+            None => {}
+        }
+    }
+}
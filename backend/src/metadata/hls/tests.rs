@@ -0,0 +1,93 @@
+//! Reading master playlists
+
+use url::Url;
+
+use super::{attributes, parse_master, MasterPlaylist};
+use crate::metadata::RenditionKind;
+
+const MASTER: &str = r#"#EXTM3U
+#EXT-X-VERSION:4
+#EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID="aud",NAME="Japanese",LANGUAGE="ja",DEFAULT=YES,AUTOSELECT=YES,URI="audio/ja.m3u8"
+#EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID="aud",NAME="English",LANGUAGE="en",URI="audio/en.m3u8"
+#EXT-X-MEDIA:TYPE=SUBTITLES,GROUP-ID="subs",NAME="English, Full",LANGUAGE="en",AUTOSELECT=YES,URI="https://subs.example/en.m3u8"
+#EXT-X-MEDIA:TYPE=CLOSED-CAPTIONS,GROUP-ID="cc",NAME="CC1",INSTREAM-ID="CC1"
+
+#EXT-X-STREAM-INF:BANDWIDTH=1280000,AVERAGE-BANDWIDTH=1000000,RESOLUTION=854x480,CODECS="avc1.4d401f,mp4a.40.2",AUDIO="aud",SUBTITLES="subs"
+480p/index.m3u8
+#EXT-X-STREAM-INF:BANDWIDTH=5000000,RESOLUTION=1920x1080,FRAME-RATE=23.976,CODECS="avc1.640028,mp4a.40.2",AUDIO="aud",SUBTITLES="subs"
+/videos/1080p/index.m3u8
+#EXT-X-I-FRAME-STREAM-INF:BANDWIDTH=86000,URI="iframes.m3u8"
+"#;
+
+fn base() -> Url {
+    Url::parse("https://cdn.example/videos/master.m3u8").unwrap()
+}
+
+#[test]
+fn parses_attribute_lists() {
+    let attributes = attributes(r#"BANDWIDTH=1280000,CODECS="avc1.4d401f,mp4a.40.2",NAME="a=b""#);
+
+    assert_eq!(attributes["BANDWIDTH"], "1280000");
+    assert_eq!(attributes["CODECS"], "avc1.4d401f,mp4a.40.2");
+    assert_eq!(attributes["NAME"], "a=b");
+}
+
+#[test]
+fn parses_variants() {
+    let master = parse_master(MASTER, &base());
+
+    assert_eq!(master.variants.len(), 2);
+
+    let best = &master.variants[0];
+    assert_eq!(best.url, "https://cdn.example/videos/1080p/index.m3u8");
+    assert_eq!(best.bandwidth, 5_000_000);
+    assert_eq!(best.average_bandwidth, None);
+    assert_eq!((best.width, best.height), (Some(1920), Some(1080)));
+
+    let worst = &master.variants[1];
+    assert_eq!(worst.url, "https://cdn.example/videos/480p/index.m3u8");
+    assert_eq!(worst.average_bandwidth, Some(1_000_000));
+    assert_eq!(worst.height, Some(480));
+    assert_eq!(worst.codecs.as_deref(), Some("avc1.4d401f,mp4a.40.2"));
+    assert_eq!(worst.audio_group.as_deref(), Some("aud"));
+    assert_eq!(worst.subtitles_group.as_deref(), Some("subs"));
+}
+
+#[test]
+fn parses_renditions() {
+    let master = parse_master(MASTER, &base());
+
+    assert_eq!(master.renditions.len(), 4);
+
+    let japanese = &master.renditions[0];
+    assert_eq!(japanese.kind, RenditionKind::Audio);
+    assert_eq!(japanese.group_id, "aud");
+    assert_eq!(japanese.language.as_deref(), Some("ja"));
+    assert_eq!(
+        japanese.url.as_deref(),
+        Some("https://cdn.example/videos/audio/ja.m3u8")
+    );
+    assert!(japanese.default && japanese.autoselect);
+    assert!(!master.renditions[1].default);
+
+    let subtitles = &master.renditions[2];
+    assert_eq!(subtitles.kind, RenditionKind::Subtitles);
+    assert_eq!(subtitles.name, "English, Full");
+    assert_eq!(
+        subtitles.url.as_deref(),
+        Some("https://subs.example/en.m3u8")
+    );
+
+    assert_eq!(master.renditions[3].kind, RenditionKind::ClosedCaptions);
+    assert_eq!(master.renditions[3].url, None);
+}
+
+#[test]
+fn media_playlists_have_no_variants() {
+    let master = parse_master(
+        "#EXTM3U\n#EXT-X-TARGETDURATION:10\n#EXTINF:10.0,\nsegment-0.ts\n#EXT-X-ENDLIST\n",
+        &base(),
+    );
+
+    assert_eq!(master, MasterPlaylist::default());
+}
@@ -1,19 +1,30 @@
-use std::{collections::HashMap, str::FromStr};
+use std::{
+    collections::{BTreeMap, HashMap},
+    str::FromStr,
+};
 
 use common::prelude::*;
 use remote_graphql_queries::allanime::common_::AiringStatus;
+use utoipa::ToSchema;
 
 pub mod allanime;
 pub mod aniwatch;
 pub mod aniwave;
+pub mod cache;
 mod common;
+pub mod hls;
 pub mod myanimelist;
+pub mod public_client;
+pub mod subtitles;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, ToSchema,
+)]
 #[serde(rename_all = "camelCase")]
 pub enum SeriesTranslation {
     Dub,
     Sub,
+    Raw,
     Unknown,
 }
 
@@ -24,12 +35,13 @@ impl FromStr for SeriesTranslation {
         match s.to_lowercase().as_str() {
             "dub" => Ok(Self::Dub),
             "sub" => Ok(Self::Sub),
+            "raw" => Ok(Self::Raw),
             _ => Ok(Self::Unknown),
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct AltName {
     pub name: String,
@@ -37,7 +49,7 @@ pub struct AltName {
     pub language: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub enum AnimeStatus {
     Unknown,
@@ -97,9 +109,224 @@ pub struct EpisodeInfo {
     pub translation: SeriesTranslation,
     pub episode_number: f64,
     pub url: String,
+    /// Synopsis of the episode, if the site has one
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub thumbnail: Option<String>,
+    /// When the episode became available in this translation (ie. its air date), if the site says
+    pub available_at: Option<DateTime<Utc>>,
+    /// Not part of the story of the source material, only set by sites that mark fillers
+    #[serde(default)]
+    pub is_filler: bool,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum SkipMarkerKind {
+    Intro,
+    Outro,
+    Recap,
+}
+impl SkipMarkerKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Intro => "intro",
+            Self::Outro => "outro",
+            Self::Recap => "recap",
+        }
+    }
+}
+impl FromStr for SkipMarkerKind {
+    type Err = serde_json::Error;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        serde_json::from_str(&format!("\"{}\"", s))
+    }
+}
+
+/// A part of an episode that can be skipped, in seconds from the start of the video
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SkipMarker {
+    pub kind: SkipMarkerKind,
+    pub start: f64,
+    pub end: f64,
+}
+impl SkipMarker {
+    /// `None` for empty ranges, which sites use when they don't know where the part is
+    pub fn new(kind: SkipMarkerKind, start: f64, end: f64) -> Option<Self> {
+        if !start.is_finite() || !end.is_finite() || start < 0.0 || end <= start {
+            return None;
+        }
+
+        Some(Self { kind, start, end })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum StreamKind {
+    Hls,
+    Mp4,
+}
+impl StreamKind {
+    /// Going by the extension of the URL's path
+    pub fn of_url(url: &str) -> Option<Self> {
+        let url = url::Url::parse(url).ok()?;
+        let extension = std::path::Path::new(url.path()).extension()?;
+
+        if extension.eq_ignore_ascii_case("m3u8") {
+            Some(Self::Hls)
+        } else if extension.eq_ignore_ascii_case("mp4") {
+            Some(Self::Mp4)
+        } else {
+            None
+        }
+    }
+}
+
+/// One quality of an HLS stream, from its master playlist
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct StreamVariant {
+    /// Media playlist of the variant
+    pub url: String,
+    /// Peak bits per second
+    pub bandwidth: u64,
+    pub average_bandwidth: Option<u64>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub codecs: Option<String>,
+    /// Group of the audio renditions the variant plays with
+    pub audio_group: Option<String>,
+    /// Group of the subtitle renditions the variant plays with
+    pub subtitles_group: Option<String>,
+    /// Signed link to the media playlist of the variant through `/v1/stream/proxy`, from the server root.
+    ///
+    /// Set by the server when handing out streams.
+    pub proxy_url: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum RenditionKind {
+    Audio,
+    Video,
+    Subtitles,
+    ClosedCaptions,
+}
+
+/// An alternative audio, video or subtitle track of an HLS stream, from its master playlist
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct StreamRendition {
+    pub kind: RenditionKind,
+    pub group_id: String,
+    pub name: String,
+    pub language: Option<String>,
+    /// Media playlist of the rendition, not set for ones that are part of the variants themselves
+    pub url: Option<String>,
+    pub default: bool,
+    pub autoselect: bool,
+    /// Signed link to the media playlist of the rendition through `/v1/stream/proxy`, from the server root.
+    ///
+    /// Set by the server when handing out streams, for renditions that have a URL.
+    pub proxy_url: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum SubtitleFormat {
+    Vtt,
+    Ass,
+    Srt,
+}
+impl SubtitleFormat {
+    /// Going by the extension of the URL's path
+    pub fn of_url(url: &str) -> Option<Self> {
+        let url = url::Url::parse(url).ok()?;
+        let extension = std::path::Path::new(url.path()).extension()?.to_str()?;
+
+        match extension.to_lowercase().as_str() {
+            "vtt" | "webvtt" => Some(Self::Vtt),
+            "ass" | "ssa" => Some(Self::Ass),
+            "srt" => Some(Self::Srt),
+            _ => None,
+        }
+    }
+}
+
+/// Subtitles of a stream that come as a separate file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SubtitleTrack {
+    pub url: String,
+    /// `None` if the URL doesn't tell
+    pub format: Option<SubtitleFormat>,
+    pub language: Option<String>,
+    pub label: Option<String>,
+    pub default: bool,
+    /// Signed link to the subtitles converted to VTT through `/v1/stream/subtitles`, from the server root.
+    ///
+    /// Set by the server when handing out streams.
+    pub vtt_url: Option<String>,
+}
+
+/// A video of an episode that can be played directly
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct StreamSource {
+    pub url: String,
+    pub kind: StreamKind,
+    /// Name the site gives the source it got the stream from
+    pub name: String,
+    /// Eg. `1080p`, if the site says
+    pub resolution: Option<String>,
+    /// Language of the subtitles burned into the video, if there are any
+    pub hardsub_language: Option<String>,
+    /// Headers the stream has to be requested with, `Referer` included
+    pub headers: BTreeMap<String, String>,
+    /// When the link stops working, if the link itself says
+    pub expires_at: Option<DateTime<Utc>>,
+    /// Signed link to the stream through `/v1/stream/proxy`, from the server root.
+    ///
+    /// Set by the server when handing out streams, for players that can't send the headers themselves.
+    pub proxy_url: Option<String>,
+    /// Qualities of HLS streams, empty if the playlist isn't a master playlist or couldn't be fetched
+    pub variants: Vec<StreamVariant>,
+    /// Alternative tracks of HLS streams, same as the variants
+    pub renditions: Vec<StreamRendition>,
+    /// Subtitles the site lists next to the stream
+    pub subtitles: Vec<SubtitleTrack>,
+}
+impl StreamSource {
+    /// Signed links usually carry the time they expire at as a unix timestamp in their query
+    pub fn expiry_hint(url: &str) -> Option<DateTime<Utc>> {
+        let url = url::Url::parse(url).ok()?;
+
+        url.query_pairs()
+            .filter(|(key, _)| {
+                matches!(
+                    key.to_lowercase().as_str(),
+                    "expires" | "expire" | "expiry" | "exp" | "e"
+                )
+            })
+            .find_map(|(_, value)| {
+                let timestamp = value.parse::<i64>().ok()?;
+                // Some sites use milliseconds
+                let timestamp = if timestamp > 100_000_000_000 {
+                    timestamp / 1000
+                } else {
+                    timestamp
+                };
+
+                DateTime::from_timestamp(timestamp, 0).filter(|x| x.year() >= 2020)
+            })
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
 #[serde(rename_all = "kebab-case")]
 pub enum AnimeSite {
     Aniwatch,
@@ -134,6 +361,26 @@ pub enum MetaSeriesInfo {
     },
 }
 
+impl MetaSeriesInfo {
+    pub fn site(&self) -> Option<AnimeSite> {
+        match self {
+            Self::Aniwatch(_) => Some(AnimeSite::Aniwatch),
+            Self::Aniwave(_) => Some(AnimeSite::Aniwave),
+            Self::Allanime(_) => Some(AnimeSite::Allanime),
+            Self::Test { .. } => None,
+        }
+    }
+
+    pub fn series_id(&self) -> &str {
+        match self {
+            Self::Aniwatch(info) => &info.id,
+            Self::Aniwave(info) => &info.id,
+            Self::Allanime(info) => &info.id,
+            Self::Test { test_id } => test_id,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "kebab-case")]
 #[serde(tag = "site")]
@@ -142,6 +389,33 @@ pub enum MetaEpisodeInfo {
     Allanime(allanime::AllanimeEpisode),
 }
 
+impl MetaEpisodeInfo {
+    pub fn series(&self) -> MetaSeriesInfo {
+        match self {
+            Self::Aniwatch(info) => MetaSeriesInfo::Aniwatch(info.series.clone()),
+            Self::Allanime(info) => MetaSeriesInfo::Allanime(info.series.clone()),
+        }
+    }
+}
+
+/// The series, and the episode if there is one, that a link to a supported site points at
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResolvedUrl {
+    pub series: MetaSeriesInfo,
+    pub episode: Option<MetaEpisodeInfo>,
+}
+
+/// Figure out which series (and episode) a link copied from one of the supported sites is for
+pub fn resolve_url(url: &str) -> Result<ResolvedUrl> {
+    let url = url::Url::parse(url.trim()).context("Invalid URL")?;
+
+    aniwatch::resolve_url(&url)
+        .or_else(|| aniwave::resolve_url(&url))
+        .or_else(|| allanime::resolve_url(&url))
+        .ok_or_else(|| anyhow!("Not a link to a series or an episode on a supported site"))
+}
+
 pub async fn series_info(info: MetaSeriesInfo) -> Result<AnimeInfo> {
     match info {
         MetaSeriesInfo::Aniwatch(info) => aniwatch::series_info(info).await,
@@ -156,6 +430,34 @@ pub async fn series_info(info: MetaSeriesInfo) -> Result<AnimeInfo> {
     }
 }
 
+/// Look for sources of a series on the supported sites.
+///
+/// Only sites that expose the MAL id of their shows can be searched,
+/// since that is the only reliable way to tell two seasons with similar names apart.
+pub async fn discover_sources(query: &str, mal_id: u32) -> Result<Vec<MetaSeriesInfo>> {
+    let found = allanime::search_by_mal_id(query, mal_id).await?;
+
+    Ok(found.into_iter().map(MetaSeriesInfo::Allanime).collect())
+}
+
+/// The number of an episode within its series
+pub async fn episode_number(info: &MetaEpisodeInfo) -> Result<f64> {
+    match info {
+        MetaEpisodeInfo::Allanime(info) => Ok(info.episode_number),
+        // Aniwatch links only carry the episode's id, so it has to be found in the episode list
+        MetaEpisodeInfo::Aniwatch(info) => {
+            let series = aniwatch::series_info(info.series.clone()).await?;
+
+            series
+                .episodes
+                .iter()
+                .find(|x| x.id == info.episode_id)
+                .map(|x| x.episode_number)
+                .ok_or_else(|| anyhow!("Episode {:?} not found", info.episode_id))
+        }
+    }
+}
+
 pub async fn episode_info(info: MetaEpisodeInfo) -> Result<serde_json::Value> {
     match info {
         MetaEpisodeInfo::Allanime(info) => allanime::episode_info(info)
@@ -164,3 +466,15 @@ pub async fn episode_info(info: MetaEpisodeInfo) -> Result<serde_json::Value> {
         MetaEpisodeInfo::Aniwatch(info) => aniwatch::episode_info(info).await,
     }
 }
+
+/// Resolve the sources of an episode into videos that can be played directly,
+/// along with the qualities of the HLS ones
+pub async fn episode_streams(info: MetaEpisodeInfo) -> Result<Vec<StreamSource>> {
+    let mut streams = match info {
+        MetaEpisodeInfo::Allanime(info) => allanime::episode_streams(info).await?,
+        MetaEpisodeInfo::Aniwatch(_) => bail!("Resolving streams isn't supported for aniwatch"),
+    };
+    hls::describe(&mut streams).await;
+
+    Ok(streams)
+}
@@ -16,16 +16,16 @@ pub struct Picture {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all(serialize = "camelCase"))]
 pub struct AlternativeTitles {
-    synonyms: Option<Vec<String>>,
-    en: Option<String>,
-    ja: Option<String>,
+    pub synonyms: Option<Vec<String>>,
+    pub en: Option<String>,
+    pub ja: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all(serialize = "camelCase"))]
 pub struct Genre {
     id: u32,
-    name: String,
+    pub name: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -132,16 +132,16 @@ pub enum Rating {
 #[derive(Debug, Clone, Serialize, Deserialize, FieldNamesAsArray)]
 #[serde(rename_all(serialize = "camelCase"))]
 pub struct AnimeDetails {
-    id: u32,
-    title: String,
+    pub id: u32,
+    pub title: String,
     main_picture: Option<Picture>,
     /// "synonyms" or ISO 639-1
-    alternative_titles: Option<AlternativeTitles>,
+    pub alternative_titles: Option<AlternativeTitles>,
     start_date: Option<String>,
     end_date: Option<String>,
     /// Synopsis.
     /// The API strips BBCode tags from the result.
-    synopsis: Option<String>,
+    pub synopsis: Option<String>,
     /// Mean score.
     /// When the `mean` can not be calculated, such as when the number of user scores is small, the result does not include this field.
     mean: Option<f64>,
@@ -152,14 +152,14 @@ pub struct AnimeDetails {
     num_list_users: u32,
     num_scoring_users: u32,
     nsfw: Option<Nsfw>,
-    genres: Option<Vec<Genre>>,
+    pub genres: Option<Vec<Genre>>,
     created_at: Option<String>,
     updated_at: Option<String>,
     media_type: MediaType,
     /// Airing status.
-    status: AnimeStatus,
+    pub status: AnimeStatus,
     /// The total number of episodes of this series. If unknown, it is 0.
-    num_episodes: u32,
+    pub num_episodes: u32,
     start_season: Option<StartSeason>,
     /// Broadcast date.
     broadcast: Option<Broadcast>,
@@ -1,15 +1,14 @@
 use anyhow::{anyhow, Result};
 use reqwest::{Client, Response};
 
-const CLIENT_ID: &str = "16c0cefeeb62cb0fb474388753256fa5";
-
-pub const BASE_URL: &str = "https://api.myanimelist.net/v2";
+use crate::config::CONFIG;
 
 pub async fn get_page(url: &str) -> Result<Response> {
-    let url = if url.starts_with(BASE_URL) {
+    let base_url = CONFIG.mal.api_url.as_str();
+    let url = if url.starts_with(base_url) {
         url.to_string()
     } else {
-        format!("{BASE_URL}{url}")
+        format!("{base_url}{url}")
     };
 
     Client::builder()
@@ -20,7 +19,7 @@ pub async fn get_page(url: &str) -> Result<Response> {
         .header("Accept", "*/*")
         .header("X-Requested-With", "XMLHttpRequest")
         .header("User-Agent", "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/102.0.0.0 Safari/537.36")
-        .header("X-Mal-Client-Id", CLIENT_ID)
+        .header("X-Mal-Client-Id", &CONFIG.mal.client_id)
         .send()
         .await
         .map_err(|e| anyhow!(e))
@@ -0,0 +1,134 @@
+//! HTTP clients for URLs that come from sites, which refuse to reach into the server's own network.
+//!
+//! Stream and playlist URLs are whatever a site (or a playlist it serves) says they are,
+//! so without this anyone who can get a URL in front of the server could make it fetch
+//! things like `http://127.0.0.1/` or the cloud metadata service at `169.254.169.254`.
+//! Hosts are checked after resolving them, on every connection, so redirects and names
+//! that resolve to private addresses are caught too.
+
+use std::{
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    sync::Arc,
+};
+
+use hyper::client::connect::dns::Name;
+use reqwest::{
+    dns::{Addrs, Resolve, Resolving},
+    redirect, ClientBuilder,
+};
+use url::{Host, Url};
+
+use super::common::prelude::*;
+
+#[cfg(test)]
+mod tests;
+
+/// Redirects followed before giving up, same as the default of `reqwest`
+const MAX_REDIRECTS: usize = 10;
+
+/// Whether the address is on the public internet
+pub fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_v4(ip),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public_v4(ip),
+            None => is_public_v6(ip),
+        },
+    }
+}
+
+fn is_public_v4(ip: Ipv4Addr) -> bool {
+    let [a, b, ..] = ip.octets();
+    // Shared address space of carrier-grade NAT, 100.64.0.0/10
+    let is_shared = a == 100 && (b & 0b1100_0000) == 64;
+
+    !(ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        || ip.is_multicast()
+        || is_shared
+        || a == 0)
+}
+
+fn is_public_v6(ip: Ipv6Addr) -> bool {
+    let first = ip.segments()[0];
+    // fc00::/7
+    let is_unique_local = (first & 0xfe00) == 0xfc00;
+    // fe80::/10
+    let is_link_local = (first & 0xffc0) == 0xfe80;
+
+    !(ip.is_loopback()
+        || ip.is_unspecified()
+        || ip.is_multicast()
+        || is_unique_local
+        || is_link_local)
+}
+
+/// Check a URL before fetching it.
+///
+/// Hosts that are names are checked once they are resolved, by the resolver of the clients.
+pub fn check_url(url: &Url) -> Result<()> {
+    if !matches!(url.scheme(), "http" | "https") {
+        bail!("Refusing to fetch {:?} URL", url.scheme());
+    }
+
+    let ip = match url.host() {
+        Some(Host::Ipv4(ip)) => IpAddr::V4(ip),
+        Some(Host::Ipv6(ip)) => IpAddr::V6(ip),
+        Some(Host::Domain(_)) => return Ok(()),
+        None => bail!("URL has no host"),
+    };
+    if !is_public(ip) {
+        bail!("Refusing to fetch from non-public address {}", ip);
+    }
+
+    Ok(())
+}
+
+/// Resolves names like the system does, leaving out addresses that aren't public
+struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let addrs = tokio::net::lookup_host((name.as_str(), 0))
+                .await?
+                .filter(|x| is_public(x.ip()))
+                .collect::<Vec<SocketAddr>>();
+
+            if addrs.is_empty() {
+                return Err(
+                    anyhow!("{:?} doesn't resolve to a public address", name.as_str()).into(),
+                );
+            }
+
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+fn redirect_policy() -> redirect::Policy {
+    redirect::Policy::custom(|attempt| {
+        if attempt.previous().len() >= MAX_REDIRECTS {
+            return attempt.error("Too many redirects");
+        }
+
+        match check_url(attempt.url()) {
+            Ok(()) => attempt.follow(),
+            Err(e) => attempt.error(e),
+        }
+    })
+}
+
+/// Builder of a client that only connects to public addresses.
+///
+/// URLs still have to go through [`check_url`] before they're fetched, addresses in them aren't resolved.
+/// Proxies from the environment are ignored, they would resolve the host instead of the [`PublicResolver`].
+pub fn builder() -> ClientBuilder {
+    reqwest::Client::builder()
+        .no_proxy()
+        .dns_resolver(Arc::new(PublicResolver))
+        .redirect(redirect_policy())
+}
@@ -0,0 +1,82 @@
+//! Telling public addresses from the server's own network
+
+use std::net::IpAddr;
+
+use url::Url;
+
+use super::{builder, check_url, is_public};
+
+#[test]
+fn refuses_non_public_addresses() {
+    for ip in [
+        "127.0.0.1",
+        "10.1.2.3",
+        "172.16.0.1",
+        "192.168.1.1",
+        "169.254.169.254",
+        "100.64.0.1",
+        "0.0.0.0",
+        "255.255.255.255",
+        "::1",
+        "::",
+        "fd00::1",
+        "fe80::1",
+        "::ffff:127.0.0.1",
+        "::ffff:192.168.1.1",
+    ] {
+        assert!(!is_public(ip.parse::<IpAddr>().unwrap()), "{ip}");
+    }
+}
+
+#[test]
+fn allows_public_addresses() {
+    for ip in [
+        "1.1.1.1",
+        "93.184.216.34",
+        "172.32.0.1",
+        "100.128.0.1",
+        "2606:4700:4700::1111",
+        "::ffff:8.8.8.8",
+    ] {
+        assert!(is_public(ip.parse::<IpAddr>().unwrap()), "{ip}");
+    }
+}
+
+#[test]
+fn checks_urls() {
+    for url in [
+        "http://127.0.0.1:8080/video.mp4",
+        "http://[::1]/video.mp4",
+        "https://169.254.169.254/latest/meta-data/",
+        // Other ways of writing 127.0.0.1, which URLs normalize
+        "http://2130706433/",
+        "http://0x7f.1/",
+        "file:///etc/passwd",
+        "ftp://cdn.example/video.mp4",
+    ] {
+        assert!(check_url(&Url::parse(url).unwrap()).is_err(), "{url}");
+    }
+
+    for url in [
+        "https://cdn.example/video.mp4",
+        "http://93.184.216.34/master.m3u8",
+    ] {
+        assert!(check_url(&Url::parse(url).unwrap()).is_ok(), "{url}");
+    }
+}
+
+#[tokio::test]
+async fn refuses_names_of_local_addresses() {
+    let client = builder().build().unwrap();
+
+    let error = client
+        .get("http://localhost:1/video.mp4")
+        .send()
+        .await
+        .unwrap_err();
+
+    assert!(
+        format!("{error:?}").contains("doesn't resolve to a public address"),
+        "{error:?}"
+    );
+}
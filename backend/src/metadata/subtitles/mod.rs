@@ -0,0 +1,152 @@
+//! Converting the subtitle files sites list next to their streams into VTT,
+//! the only format browsers play on their own.
+
+use super::SubtitleFormat;
+
+#[cfg(test)]
+mod tests;
+
+/// Figure out the format from the contents, for files whose URL doesn't tell
+pub fn detect_format(subtitles: &str) -> SubtitleFormat {
+    let subtitles = subtitles.trim_start_matches('\u{feff}').trim_start();
+
+    if subtitles.starts_with("WEBVTT") {
+        SubtitleFormat::Vtt
+    } else if subtitles.starts_with("[Script Info]") || subtitles.contains("\nDialogue:") {
+        SubtitleFormat::Ass
+    } else {
+        SubtitleFormat::Srt
+    }
+}
+
+/// Convert subtitles to VTT, going by the format of the contents if it isn't known
+pub fn to_webvtt(subtitles: &str, format: Option<SubtitleFormat>) -> String {
+    let subtitles = subtitles
+        .trim_start_matches('\u{feff}')
+        .replace("\r\n", "\n")
+        .replace('\r', "\n");
+
+    match format.unwrap_or_else(|| detect_format(&subtitles)) {
+        SubtitleFormat::Vtt => subtitles,
+        SubtitleFormat::Srt => srt_to_webvtt(&subtitles),
+        SubtitleFormat::Ass => ass_to_webvtt(&subtitles),
+    }
+}
+
+/// SRT is VTT with a comma before the milliseconds and without the header
+fn srt_to_webvtt(subtitles: &str) -> String {
+    let mut vtt = String::from("WEBVTT\n\n");
+    for line in subtitles.trim().lines() {
+        if line.contains("-->") {
+            vtt.push_str(&line.replace(',', "."));
+        } else {
+            vtt.push_str(line);
+        }
+        vtt.push('\n');
+    }
+
+    vtt
+}
+
+/// `H:MM:SS.cc` to `HH:MM:SS.mmm`
+fn ass_timestamp(timestamp: &str) -> Option<String> {
+    let (hours, rest) = timestamp.trim().split_once(':')?;
+    let (minutes, rest) = rest.split_once(':')?;
+    let (seconds, centiseconds) = rest.split_once('.').unwrap_or((rest, "0"));
+
+    let hours = hours.parse::<u32>().ok()?;
+    let minutes = minutes.parse::<u32>().ok()?;
+    let seconds = seconds.parse::<u32>().ok()?;
+    // Fractions are usually centiseconds, but not always
+    let millis = format!("{:0<3}", centiseconds)
+        .get(..3)?
+        .parse::<u32>()
+        .ok()?;
+
+    Some(format!("{hours:02}:{minutes:02}:{seconds:02}.{millis:03}"))
+}
+
+/// Drop the `{\...}` override blocks and turn the escapes into what they stand for
+fn ass_text(text: &str) -> String {
+    let mut plain = String::new();
+    let mut in_override = false;
+    for x in text.chars() {
+        match x {
+            '{' => in_override = true,
+            '}' if in_override => in_override = false,
+            _ if in_override => {}
+            _ => plain.push(x),
+        }
+    }
+
+    plain
+        .replace("\\N", "\n")
+        .replace("\\n", "\n")
+        .replace("\\h", " ")
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Only the dialogue of the `[Events]` section is kept, styling and positioning are dropped
+fn ass_to_webvtt(subtitles: &str) -> String {
+    let mut cues = vec![];
+    let mut in_events = false;
+    // Default field order of ASS, used until the section says otherwise
+    let mut fields = [
+        "Layer", "Start", "End", "Style", "Name", "MarginL", "MarginR", "MarginV", "Effect", "Text",
+    ]
+    .map(String::from)
+    .to_vec();
+
+    for line in subtitles.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_events = line.eq_ignore_ascii_case("[Events]");
+            continue;
+        }
+        if !in_events {
+            continue;
+        }
+
+        if let Some(format) = line.strip_prefix("Format:") {
+            fields = format.split(',').map(|x| x.trim().to_string()).collect();
+            continue;
+        }
+        let Some(dialogue) = line.strip_prefix("Dialogue:") else {
+            continue;
+        };
+
+        // The text is last and can contain commas itself
+        let values = dialogue.splitn(fields.len(), ',').collect::<Vec<_>>();
+        let field = |name: &str| {
+            fields
+                .iter()
+                .position(|x| x.eq_ignore_ascii_case(name))
+                .and_then(|i| values.get(i))
+                .copied()
+        };
+
+        let (Some(start), Some(end), Some(text)) = (
+            field("Start").and_then(ass_timestamp),
+            field("End").and_then(ass_timestamp),
+            field("Text").map(ass_text),
+        ) else {
+            continue;
+        };
+        if text.trim().is_empty() {
+            continue;
+        }
+
+        cues.push((start, end, text));
+    }
+
+    // Timestamps are zero padded, so they sort as strings
+    cues.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut vtt = String::from("WEBVTT\n");
+    for (start, end, text) in cues {
+        vtt.push_str(&format!("\n{start} --> {end}\n{}\n", text.trim()));
+    }
+
+    vtt
+}
@@ -0,0 +1,56 @@
+//! Converting subtitles to VTT
+
+use super::{detect_format, to_webvtt};
+use crate::metadata::SubtitleFormat;
+
+const SRT: &str = "\u{feff}1\r\n00:00:01,500 --> 00:00:04,000\r\n<i>Hello</i>, world\r\n\r\n2\r\n00:01:02,000 --> 00:01:03,250\r\nSecond line\r\n";
+
+const ASS: &str = r"[Script Info]
+Title: Episode 1
+ScriptType: v4.00+
+
+[V4+ Styles]
+Format: Name, Fontname, Fontsize
+Style: Default,Arial,20
+
+[Events]
+Format: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text
+Dialogue: 0,0:01:02.00,0:01:03.25,Default,,0,0,0,,Later, but listed first
+Dialogue: 0,0:00:01.50,0:00:04.00,Default,,0,0,0,,{\i1}Hello{\i0}, world\Nand a <second> line
+Comment: 0,0:00:05.00,0:00:06.00,Default,,0,0,0,,Not shown
+Dialogue: 0,0:00:07.00,0:00:08.00,Default,,0,0,0,,{\pos(10,10)}
+";
+
+#[test]
+fn detects_formats() {
+    assert_eq!(detect_format("WEBVTT\n\n"), SubtitleFormat::Vtt);
+    assert_eq!(detect_format(ASS), SubtitleFormat::Ass);
+    assert_eq!(detect_format(SRT), SubtitleFormat::Srt);
+}
+
+#[test]
+fn converts_srt() {
+    assert_eq!(
+        to_webvtt(SRT, Some(SubtitleFormat::Srt)),
+        "WEBVTT\n\n\
+        1\n00:00:01.500 --> 00:00:04.000\n<i>Hello</i>, world\n\n\
+        2\n00:01:02.000 --> 00:01:03.250\nSecond line\n"
+    );
+}
+
+#[test]
+fn converts_ass() {
+    assert_eq!(
+        to_webvtt(ASS, None),
+        "WEBVTT\n\
+        \n00:00:01.500 --> 00:00:04.000\nHello, world\nand a &lt;second&gt; line\n\
+        \n00:01:02.000 --> 00:01:03.250\nLater, but listed first\n"
+    );
+}
+
+#[test]
+fn leaves_webvtt_alone() {
+    let vtt = "WEBVTT\n\n00:00:01.000 --> 00:00:02.000\nHi\n";
+
+    assert_eq!(to_webvtt(vtt, Some(SubtitleFormat::Vtt)), vtt);
+}
@@ -4,16 +4,16 @@ use std::{
 };
 
 use axum::{
-    http::{HeaderName, HeaderValue, Request},
+    http::{HeaderName, HeaderValue, Method, Request},
     middleware::{self, Next},
     response::Response,
     Extension, Server, ServiceExt,
 };
-use log::{debug, info, trace};
+use log::{debug, info, trace, warn};
 use reqwest::header;
 use tower::{layer::Layer, ServiceBuilder};
 use tower_http::{
-    cors::{self, CorsLayer},
+    cors::CorsLayer,
     normalize_path::NormalizePathLayer,
     request_id::{MakeRequestUuid, PropagateRequestIdLayer, RequestId, SetRequestIdLayer},
     set_header::SetResponseHeaderLayer,
@@ -24,10 +24,12 @@ use tower_http::{
 
 use crate::config::CONFIG;
 use crate::server::state::AppState;
+use crate::{db, library};
 
 mod router;
 mod server_timing;
 mod state;
+mod stream_proxy;
 
 lazy_static::lazy_static! {
     static ref CACHE_CONTROL: HeaderValue = HeaderValue::from_static("private, max-age=0");
@@ -51,6 +53,40 @@ async fn server_timings_fn<B>(
     resp
 }
 
+/// Only configured origins may make cross-origin requests, since those carry the session cookie
+fn cors_layer() -> CorsLayer {
+    let origins = CONFIG
+        .server
+        .cors_origins
+        .iter()
+        .filter_map(|x| match x.parse::<HeaderValue>() {
+            Ok(x) => Some(x),
+            Err(e) => {
+                warn!("Ignoring invalid CORS origin {x:?}: {e}");
+                None
+            }
+        })
+        .collect::<Vec<_>>();
+
+    CorsLayer::new()
+        .allow_origin(origins)
+        .allow_credentials(true)
+        .allow_methods([
+            Method::GET,
+            Method::POST,
+            Method::PUT,
+            Method::PATCH,
+            Method::DELETE,
+        ])
+        .allow_headers([
+            header::ACCEPT,
+            header::AUTHORIZATION,
+            header::CONTENT_TYPE,
+            header::RANGE,
+        ])
+        .expose_headers([header::CONTENT_RANGE, header::ACCEPT_RANGES])
+}
+
 #[tokio::main]
 pub async fn run() -> anyhow::Result<()> {
     let listener = TcpListener::bind((CONFIG.server.host.clone(), CONFIG.server.port))?;
@@ -64,17 +100,16 @@ pub async fn run() -> anyhow::Result<()> {
     debug!("Using app state: {:?}", app_state);
 
     app_state.db.init().await?;
+    db::snapshot::spawn_periodic_snapshots(app_state.db.connection());
+    library::mal_sync::spawn_periodic_sync(app_state.db.connection());
+    library::enrich::spawn_periodic_backfill(app_state.db.connection());
 
     let server_timings = server_timing::ServerTimings::new();
 
     let x_request_id = HeaderName::from_static("x-request-id");
     let router = router::create_router()
         .route_layer(middleware::from_fn(server_timings_fn))
-        .layer(
-            CorsLayer::new()
-                .allow_methods(cors::Any)
-                .allow_origin(cors::Any),
-        )
+        .layer(cors_layer())
         .layer(
             ServiceBuilder::new()
                 .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid))
@@ -4,6 +4,7 @@ use axum::{
 };
 use reqwest::StatusCode;
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
 use self::error::ApiError;
 
@@ -53,7 +54,7 @@ mod status_code_serializer {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ApiResponseMeta {
     /// Timestamp in RFC3339 format
@@ -76,7 +77,25 @@ pub struct ApiResponseMeta {
     /// This is the HTTP status code of the response.
     /// Placed here for convenience.
     #[serde(rename = "status", with = "status_code_serializer")]
+    #[schema(value_type = u16)]
     pub status_code: StatusCode,
+
+    /// Pagination information
+    ///
+    /// Only present on responses that return one page of a longer list.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page: Option<PageMeta>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PageMeta {
+    /// Number of items matching the request across all pages
+    pub total: u64,
+    /// Number of items skipped before this page
+    pub offset: u64,
+    /// Maximum number of items in a page
+    pub limit: u64,
 }
 
 fn serialize_empty<S>(serializer: S) -> Result<S::Ok, S::Error>
@@ -103,6 +122,7 @@ impl<TData: Serialize> ApiResponse<TData> {
                 api_version: api_version.into(),
                 status_code,
                 timestamp: chrono::Utc::now().to_rfc3339(),
+                page: None,
             },
             #[cfg(debug_assertions)]
             _debug: None,
@@ -119,6 +139,11 @@ impl<TData: Serialize> ApiResponse<TData> {
         self
     }
 
+    pub fn with_page(mut self, page: PageMeta) -> Self {
+        self.meta.page = Some(page);
+        self
+    }
+
     pub fn with_body(mut self, body: ApiResponseBody<TData>) -> ApiResponse<TData> {
         self.body = body;
         self
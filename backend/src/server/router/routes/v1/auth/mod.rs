@@ -0,0 +1,181 @@
+use axum::{
+    async_trait,
+    extract::FromRequestParts,
+    http::{header, request::Parts, HeaderMap, Method},
+};
+use axum_extra::extract::CookieJar;
+use log::trace;
+use reqwest::StatusCode;
+
+use super::response::V1Response;
+use crate::{
+    auth::{self, TokenScope},
+    config::CONFIG,
+    server::state::AppState,
+};
+
+#[cfg(test)]
+mod tests;
+
+/// Name of the cookie the session token is kept in
+pub const SESSION_COOKIE: &str = "session";
+
+/// The user making the request.
+///
+/// Requests are authenticated either with a `Bearer` token in the `Authorization` header
+/// (sessions and API tokens both work) or with the session cookie.
+/// Read-only API tokens are turned away from anything but `GET` requests.
+#[derive(Debug, Clone)]
+pub struct AuthUser(pub entity::users::Model);
+
+/// What the credentials of the request may do, `None` for sessions
+#[derive(Debug, Clone, Copy)]
+pub struct AuthScope(pub Option<TokenScope>);
+
+impl AuthScope {
+    pub fn allows(self, scope: TokenScope) -> bool {
+        self.0.map_or(true, |x| x >= scope)
+    }
+}
+
+/// The user making the request, who has to be an admin using an admin-scoped token or a session
+#[derive(Debug, Clone)]
+pub struct AdminUser(pub entity::users::Model);
+
+/// The token the request was made with, if any
+pub fn request_token(headers: &HeaderMap) -> Option<String> {
+    if let Some(authorization) = headers.get(header::AUTHORIZATION) {
+        return authorization
+            .to_str()
+            .ok()?
+            .strip_prefix("Bearer ")
+            .map(|x| x.trim().to_string());
+    }
+
+    CookieJar::from_headers(headers)
+        .get(SESSION_COOKIE)
+        .map(|x| x.value().to_string())
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for AuthUser
+where
+    S: Send + Sync,
+{
+    type Rejection = V1Response;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        // Already looked up by the middleware guarding the route
+        if let Some(user) = parts.extensions.get::<Self>() {
+            return Ok(user.clone());
+        }
+
+        let Some(app_state) = parts.extensions.get::<AppState>().cloned() else {
+            return Err(V1Response::ErrorEmpty(StatusCode::INTERNAL_SERVER_ERROR));
+        };
+
+        let Some(token) = request_token(&parts.headers) else {
+            return Err(V1Response::Error(
+                StatusCode::UNAUTHORIZED,
+                anyhow::anyhow!("Not logged in").into(),
+            ));
+        };
+
+        let db = app_state.db.connection();
+        match auth::user_for_token(&db, &token).await {
+            Ok(Some(owner)) => {
+                trace!(
+                    "Request made by user {} with scope {:?}",
+                    owner.user.id,
+                    owner.scope
+                );
+
+                let scope = AuthScope(owner.scope);
+                let read_only = matches!(parts.method, Method::GET | Method::HEAD);
+                if !read_only && !scope.allows(TokenScope::LibraryWrite) {
+                    return Err(V1Response::Error(
+                        StatusCode::FORBIDDEN,
+                        anyhow::anyhow!("Token is read-only").into(),
+                    ));
+                }
+
+                let user = Self(owner.user);
+                parts.extensions.insert(user.clone());
+                parts.extensions.insert(scope);
+                Ok(user)
+            }
+            Ok(None) => Err(V1Response::Error(
+                StatusCode::UNAUTHORIZED,
+                anyhow::anyhow!("Invalid or expired token").into(),
+            )),
+            Err(e) => Err(V1Response::Error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                anyhow::anyhow!("Failed to check token: {}", e).into(),
+            )),
+        }
+    }
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for AdminUser
+where
+    S: Send + Sync,
+{
+    type Rejection = V1Response;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let AuthUser(user) = AuthUser::from_request_parts(parts, state).await?;
+        let scope = AuthScope::from_request_parts(parts, state).await?;
+
+        if !user.is_admin {
+            return Err(V1Response::Error(
+                StatusCode::FORBIDDEN,
+                anyhow::anyhow!("Only admins can do that").into(),
+            ));
+        }
+        if !scope.allows(TokenScope::Admin) {
+            return Err(V1Response::Error(
+                StatusCode::FORBIDDEN,
+                anyhow::anyhow!("Token doesn't have the admin scope").into(),
+            ));
+        }
+
+        Ok(Self(user))
+    }
+}
+
+/// `Set-Cookie` value that hands a session token to the browser
+pub fn session_cookie(token: &str) -> String {
+    let mut cookie = format!(
+        "{SESSION_COOKIE}={token}; Path=/; HttpOnly; SameSite=Lax; Max-Age={}",
+        CONFIG.auth.session_duration.as_secs()
+    );
+    if CONFIG.auth.secure_cookies {
+        cookie.push_str("; Secure");
+    }
+
+    cookie
+}
+
+/// `Set-Cookie` value that makes the browser forget the session token
+pub fn clear_session_cookie() -> String {
+    format!("{SESSION_COOKIE}=; Path=/; HttpOnly; SameSite=Lax; Max-Age=0")
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for AuthScope
+where
+    S: Send + Sync,
+{
+    type Rejection = V1Response;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        AuthUser::from_request_parts(parts, state).await?;
+
+        parts
+            .extensions
+            .get::<Self>()
+            .copied()
+            .ok_or(V1Response::ErrorEmpty(StatusCode::INTERNAL_SERVER_ERROR))
+    }
+}
@@ -0,0 +1,160 @@
+//! Who gets through the guards of the v1 routes, going through the router like a client would
+
+use axum::{
+    body::Body,
+    http::{header, Method, Request, StatusCode},
+    Extension,
+};
+use chrono::{Duration, Utc};
+use sea_orm::{prelude::*, ActiveValue};
+use tower::ServiceExt;
+
+use crate::{
+    auth::{create_api_token, create_session, TokenScope},
+    db::AppDb,
+    server::{router::routes::v1::create_router, state::AppState},
+    test_util::database,
+};
+
+async fn user(db: &DatabaseConnection, username: &str, is_admin: bool) -> entity::users::Model {
+    // Hashing a real password is slow and none of these log in with one
+    entity::users::ActiveModel {
+        username: ActiveValue::Set(username.to_string()),
+        password_hash: ActiveValue::Set(String::new()),
+        is_admin: ActiveValue::Set(is_admin),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap()
+}
+
+async fn session(db: &DatabaseConnection, user_id: i32) -> String {
+    create_session(db, user_id, std::time::Duration::from_secs(60 * 60))
+        .await
+        .unwrap()
+        .0
+}
+
+async fn api_token(db: &DatabaseConnection, user_id: i32, scope: TokenScope) -> String {
+    create_api_token(db, user_id, "Scripts", scope, None)
+        .await
+        .unwrap()
+        .0
+}
+
+/// Status of the response, and the error message if there is one
+async fn request(
+    db: &DatabaseConnection,
+    method: Method,
+    uri: &str,
+    token: Option<&str>,
+) -> (StatusCode, Option<String>) {
+    let mut request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(header::ACCEPT, "application/json");
+    if let Some(token) = token {
+        request = request.header(header::AUTHORIZATION, format!("Bearer {token}"));
+    }
+
+    let app_state = AppState {
+        db: AppDb::from_connection(db.clone()),
+    };
+    let response = create_router()
+        .layer(Extension(app_state))
+        .oneshot(request.body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+
+    let status = response.status();
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    let error = serde_json::from_slice::<serde_json::Value>(&body)
+        .ok()
+        .filter(|x| x["body"]["type"] == "error")
+        .and_then(|x| x["body"]["data"].as_str().map(str::to_string));
+
+    (status, error)
+}
+
+#[tokio::test]
+async fn turns_away_requests_without_a_valid_token() {
+    let db = database().await;
+    let frieren = user(&db, "frieren", false).await;
+    let (expired, _) = create_api_token(
+        &db,
+        frieren.id,
+        "Scripts",
+        TokenScope::Admin,
+        Some(Utc::now() - Duration::seconds(1)),
+    )
+    .await
+    .unwrap();
+
+    let (status, error) = request(&db, Method::GET, "/me", None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(error.as_deref(), Some("Not logged in"));
+
+    for token in [expired.as_str(), "unknown"] {
+        let (status, error) = request(&db, Method::GET, "/me", Some(token)).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(error.as_deref(), Some("Invalid or expired token"));
+    }
+}
+
+#[tokio::test]
+async fn refuses_changes_with_read_only_tokens() {
+    let db = database().await;
+    let frieren = user(&db, "frieren", false).await;
+    let read_only = api_token(&db, frieren.id, TokenScope::ReadOnly).await;
+    let library_write = api_token(&db, frieren.id, TokenScope::LibraryWrite).await;
+
+    let (status, _) = request(&db, Method::GET, "/me/tokens", Some(&read_only)).await;
+    assert_eq!(status, StatusCode::OK);
+
+    for (method, uri) in [
+        (Method::POST, "/me/tokens"),
+        (Method::PUT, "/me/settings"),
+        (Method::DELETE, "/me/tokens/1"),
+    ] {
+        let (status, error) = request(&db, method.clone(), uri, Some(&read_only)).await;
+        assert_eq!(status, StatusCode::FORBIDDEN, "{method} {uri}");
+        assert_eq!(
+            error.as_deref(),
+            Some("Token is read-only"),
+            "{method} {uri}"
+        );
+
+        let (status, _) = request(&db, method.clone(), uri, Some(&library_write)).await;
+        assert_ne!(status, StatusCode::FORBIDDEN, "{method} {uri}");
+    }
+}
+
+#[tokio::test]
+async fn keeps_admin_routes_to_admins() {
+    let db = database().await;
+    let admin = user(&db, "frieren", true).await;
+    let member = user(&db, "fern", false).await;
+
+    for token in [
+        session(&db, member.id).await,
+        api_token(&db, member.id, TokenScope::Admin).await,
+    ] {
+        let (status, error) = request(&db, Method::GET, "/admin/users", Some(&token)).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert_eq!(error.as_deref(), Some("Only admins can do that"));
+    }
+
+    let library_write = api_token(&db, admin.id, TokenScope::LibraryWrite).await;
+    let (status, error) = request(&db, Method::GET, "/admin/users", Some(&library_write)).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(error.as_deref(), Some("Token doesn't have the admin scope"));
+
+    for token in [
+        session(&db, admin.id).await,
+        api_token(&db, admin.id, TokenScope::Admin).await,
+    ] {
+        let (status, _) = request(&db, Method::GET, "/admin/users", Some(&token)).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+}
@@ -0,0 +1,127 @@
+use axum::{extract::Query, Extension, Json};
+use axum_extra::extract::WithRejection;
+use axum_macros::debug_handler;
+use log::{debug, trace};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use utoipa::IntoParams;
+
+use crate::{
+    db::backup::{self, Backup, RestoreMode, RestoreReport},
+    library::consistency::{self, ConsistencyReport},
+    server::{
+        router::routes::v1::{pagination::Page, response::V1Response},
+        state::AppState,
+    },
+};
+
+pub mod snapshots;
+pub mod users;
+
+/// Dump every table into a single JSON document
+#[utoipa::path(
+    get,
+    path = "/v1/admin/backup",
+    tag = "admin",
+    responses((status = 200, body = Backup))
+)]
+#[debug_handler]
+pub async fn backup(Extension(app_state): Extension<AppState>) -> V1Response<Backup> {
+    let db = app_state.db.connection();
+
+    match backup::create_backup(&db).await {
+        Ok(backup) => V1Response::Success(backup),
+        Err(e) => V1Response::Error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            anyhow::anyhow!("Failed to create backup: {}", e).into(),
+        ),
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, IntoParams)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query)]
+pub struct RestoreQuery {
+    pub mode: RestoreMode,
+}
+/// Load a backup made with [`backup`]
+#[utoipa::path(
+    post,
+    path = "/v1/admin/restore",
+    tag = "admin",
+    params(RestoreQuery),
+    request_body = Backup,
+    responses(
+        (status = 200, body = RestoreReport),
+        (status = 400, description = "Backup can't be restored into this database"),
+    )
+)]
+#[debug_handler]
+pub async fn restore(
+    Extension(app_state): Extension<AppState>,
+    WithRejection(Query(query), _): WithRejection<Query<RestoreQuery>, V1Response>,
+    WithRejection(Json(payload), _): WithRejection<Json<Backup>, V1Response>,
+) -> V1Response<RestoreReport> {
+    let db = app_state.db.connection();
+
+    trace!(
+        "Restoring backup from {:?} with mode {:?}",
+        payload.created_at,
+        query.mode
+    );
+    if let Err(e) = backup::validate_backup(&db, &payload, query.mode).await {
+        debug!("Refusing to restore backup: {:?}", e);
+        return V1Response::Error(StatusCode::BAD_REQUEST, e.into());
+    }
+
+    match backup::restore_backup(&db, payload, query.mode).await {
+        Ok(report) => V1Response::Success(report),
+        Err(e) => V1Response::Error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            anyhow::anyhow!("Failed to restore backup: {}", e).into(),
+        ),
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, IntoParams)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query)]
+pub struct ConsistencyQuery {
+    /// Only check this series. Duplicate MAL ids are still looked for across the whole library.
+    pub series_id: Option<i32>,
+    /// Number of series to skip
+    pub offset: Option<u64>,
+    /// Maximum number of series to check, 20 if not given and at most 500
+    pub limit: Option<u64>,
+}
+/// Compare what the sources of each series report and flag the ones that don't seem to belong to it.
+///
+/// Fetches the info of every source that wasn't fetched recently, so series are checked a page at a time.
+/// Sources that couldn't be fetched in time are reported as timed out, check fewer series at once to get them.
+#[utoipa::path(
+    get,
+    path = "/v1/admin/consistency",
+    tag = "admin",
+    params(ConsistencyQuery),
+    responses((status = 200, body = ConsistencyReport))
+)]
+#[debug_handler]
+pub async fn consistency(
+    Extension(app_state): Extension<AppState>,
+    WithRejection(Query(query), _): WithRejection<Query<ConsistencyQuery>, V1Response>,
+) -> V1Response<ConsistencyReport> {
+    let db = app_state.db.connection();
+
+    let page = Page::new(
+        query.offset,
+        Some(query.limit.unwrap_or(consistency::DEFAULT_PAGE_SIZE)),
+    );
+
+    match consistency::check(&db, query.series_id, page.offset, page.limit).await {
+        Ok((report, total)) => V1Response::Page(report, page.meta(total)),
+        Err(e) => V1Response::Error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            anyhow::anyhow!("Failed to check library consistency: {}", e).into(),
+        ),
+    }
+}
@@ -0,0 +1,94 @@
+use axum::Extension;
+use axum_macros::debug_handler;
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::{
+    config::CONFIG,
+    db::snapshot::{self, Snapshot},
+    server::{router::routes::v1::response::V1Response, state::AppState},
+};
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotsResponse {
+    pub enabled: bool,
+    pub interval_seconds: u64,
+    pub keep: usize,
+    pub snapshots: Vec<Snapshot>,
+}
+#[utoipa::path(
+    get,
+    path = "/v1/admin/snapshots",
+    operation_id = "list_snapshots",
+    tag = "admin",
+    responses((status = 200, body = SnapshotsResponse))
+)]
+#[debug_handler]
+pub async fn list() -> V1Response<SnapshotsResponse> {
+    let mut res = SnapshotsResponse {
+        enabled: false,
+        interval_seconds: CONFIG.database.snapshot_interval.as_secs(),
+        keep: CONFIG.database.snapshot_keep,
+        snapshots: vec![],
+    };
+
+    let Some(dir) = snapshot::snapshot_dir() else {
+        return V1Response::Success(res);
+    };
+    res.enabled = true;
+
+    match snapshot::list_snapshots(dir).await {
+        Ok(snapshots) => {
+            res.snapshots = snapshots;
+            V1Response::Success(res)
+        }
+        Err(e) => V1Response::Error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            anyhow::anyhow!("Failed to list snapshots: {}", e).into(),
+        ),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/admin/snapshots",
+    operation_id = "create_snapshot",
+    tag = "admin",
+    responses(
+        (status = 200, body = Snapshot),
+        (status = 400, description = "Snapshots are not enabled"),
+    )
+)]
+#[debug_handler]
+pub async fn create(Extension(app_state): Extension<AppState>) -> V1Response<Snapshot> {
+    let Some(dir) = snapshot::snapshot_dir() else {
+        return V1Response::Error(
+            StatusCode::BAD_REQUEST,
+            anyhow::anyhow!("Snapshots are not enabled, no snapshot directory is configured")
+                .into(),
+        );
+    };
+
+    let db = app_state.db.connection();
+
+    let created = match snapshot::create_snapshot(&db, dir).await {
+        Ok(x) => x,
+        Err(e) => {
+            return V1Response::Error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                anyhow::anyhow!("Failed to create snapshot: {}", e).into(),
+            )
+        }
+    };
+
+    if let Err(e) = snapshot::rotate_snapshots(dir).await {
+        return V1Response::Error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            anyhow::anyhow!("Failed to remove old snapshots: {}", e).into(),
+        );
+    }
+
+    V1Response::Success(created)
+}
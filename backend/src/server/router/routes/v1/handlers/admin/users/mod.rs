@@ -0,0 +1,91 @@
+use axum::{Extension, Json};
+use axum_extra::extract::WithRejection;
+use axum_macros::debug_handler;
+use reqwest::StatusCode;
+use sea_orm::{prelude::*, QueryOrder};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::{
+    auth,
+    server::{router::routes::v1::response::V1Response, state::AppState},
+};
+
+#[utoipa::path(
+    get,
+    path = "/v1/admin/users",
+    operation_id = "list_users",
+    tag = "admin",
+    responses((status = 200, body = Vec<User>))
+)]
+#[debug_handler]
+pub async fn list(
+    Extension(app_state): Extension<AppState>,
+) -> V1Response<Vec<entity::users::Model>> {
+    let db = app_state.db.connection();
+
+    match entity::users::Entity::find()
+        .order_by_asc(entity::users::Column::Id)
+        .all(&db)
+        .await
+    {
+        Ok(users) => V1Response::Success(users),
+        Err(e) => V1Response::Error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            anyhow::anyhow!("Failed to fetch users: {}", e).into(),
+        ),
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CreatePayload {
+    pub username: String,
+    pub password: String,
+    #[serde(default)]
+    pub is_admin: bool,
+}
+#[utoipa::path(
+    post,
+    path = "/v1/admin/users",
+    operation_id = "create_user",
+    tag = "admin",
+    request_body = CreatePayload,
+    responses(
+        (status = 200, body = User),
+        (status = 400, description = "Invalid username or password"),
+        (status = 409, description = "Username is already taken"),
+    )
+)]
+#[debug_handler]
+pub async fn create(
+    Extension(app_state): Extension<AppState>,
+    WithRejection(Json(payload), _): WithRejection<Json<CreatePayload>, V1Response>,
+) -> V1Response<entity::users::Model> {
+    let db = app_state.db.connection();
+
+    let exists = entity::users::Entity::find()
+        .filter(entity::users::Column::Username.eq(payload.username.as_str()))
+        .one(&db)
+        .await;
+    match exists {
+        Ok(None) => {}
+        Ok(Some(_)) => {
+            return V1Response::Error(
+                StatusCode::CONFLICT,
+                anyhow::anyhow!("Username is already taken").into(),
+            );
+        }
+        Err(e) => {
+            return V1Response::Error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                anyhow::anyhow!("Failed to check for existing users: {}", e).into(),
+            );
+        }
+    }
+
+    match auth::create_user(&db, &payload.username, &payload.password, payload.is_admin).await {
+        Ok(user) => V1Response::Success(user),
+        Err(e) => V1Response::Error(StatusCode::BAD_REQUEST, e.into()),
+    }
+}
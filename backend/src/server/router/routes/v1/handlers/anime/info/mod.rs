@@ -4,18 +4,41 @@ use axum_macros::debug_handler;
 use log::debug;
 use reqwest::StatusCode;
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
 use crate::{
-    metadata::{self, MetaEpisodeInfo, MetaSeriesInfo},
-    server::{router::routes::v1::response::V1Response, server_timing::ServerTimings},
+    library::{self, episodes::EpisodeMapping},
+    metadata::{self, MetaEpisodeInfo, MetaSeriesInfo, SkipMarker, StreamSource},
+    server::{
+        router::routes::v1::response::V1Response,
+        server_timing::ServerTimings,
+        state::AppState,
+        stream_proxy::{ProxyTarget, SUBTITLES_PATH},
+    },
 };
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct AnimeInfoFloatingResponse {
+    #[schema(value_type = Object)]
     pub meta: MetaSeriesInfo,
+    #[schema(value_type = Object)]
     pub info: metadata::AnimeInfo,
 }
+/// Look up a series on a site without adding it to the library
+#[utoipa::path(
+    get,
+    path = "/v1/anime/info",
+    tag = "anime",
+    params(
+        ("site" = AnimeSite, Query, description = "Site to look the series up on"),
+        ("seriesId" = String, Query, description = "Id of the series on the site"),
+    ),
+    responses(
+        (status = 200, body = AnimeInfoFloatingResponse),
+        (status = 400, description = "Series could not be looked up"),
+    )
+)]
 #[debug_handler]
 pub async fn anime_info_floating(
     WithRejection(Query(meta), _): WithRejection<Query<MetaSeriesInfo>, V1Response>,
@@ -32,15 +55,37 @@ pub async fn anime_info_floating(
     V1Response::Success(AnimeInfoFloatingResponse { meta, info })
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct EpisodeInfoFloatingResponse {
+    #[schema(value_type = Object)]
     pub meta: MetaEpisodeInfo,
+    #[schema(value_type = Object)]
     pub info: serde_json::Value,
+    /// Markers users submitted for the episode, if its series is in the library
+    pub skip_markers: Vec<SkipMarker>,
 }
+/// Look up an episode on a site without adding its series to the library
+#[utoipa::path(
+    get,
+    path = "/v1/anime/info/for-episode",
+    tag = "anime",
+    params(
+        ("site" = AnimeSite, Query, description = "Site to look the episode up on"),
+        ("seriesId" = String, Query, description = "Id of the series on the site"),
+        ("episodeId" = Option<String>, Query, description = "Id of the episode, for `aniwatch`"),
+        ("episodeNumber" = Option<f64>, Query, description = "Number of the episode, for `allanime`"),
+        ("episodeType" = Option<String>, Query, description = "`sub` or `dub`, for `allanime`"),
+    ),
+    responses(
+        (status = 200, body = EpisodeInfoFloatingResponse),
+        (status = 400, description = "Episode could not be looked up"),
+    )
+)]
 #[debug_handler]
 pub async fn episode_info_floating(
     WithRejection(Query(meta), _): WithRejection<Query<MetaEpisodeInfo>, V1Response>,
+    Extension(app_state): Extension<AppState>,
     Extension(server_timings): Extension<ServerTimings>,
 ) -> V1Response<EpisodeInfoFloatingResponse> {
     server_timings.add_started("episode_info", None);
@@ -55,5 +100,111 @@ pub async fn episode_info_floating(
         }
     };
 
-    V1Response::Success(EpisodeInfoFloatingResponse { meta, info })
+    let skip_markers = match stored_skip_markers(&app_state, &meta, &info).await {
+        Ok(x) => x,
+        Err(e) => {
+            debug!("Error getting stored skip markers: {:?}", e);
+            vec![]
+        }
+    };
+
+    V1Response::Success(EpisodeInfoFloatingResponse {
+        meta,
+        info,
+        skip_markers,
+    })
+}
+
+async fn stored_skip_markers(
+    app_state: &AppState,
+    meta: &MetaEpisodeInfo,
+    info: &serde_json::Value,
+) -> anyhow::Result<Vec<SkipMarker>> {
+    let db = app_state.db.connection();
+
+    let Some(source) = library::sources::find_by_meta(&db, &meta.series()).await? else {
+        return Ok(vec![]);
+    };
+
+    let site_episode_number = match meta {
+        MetaEpisodeInfo::Allanime(x) => Some(x.episode_number),
+        MetaEpisodeInfo::Aniwatch(_) => info
+            .pointer("/episode/episodeNumber")
+            .and_then(serde_json::Value::as_f64),
+    };
+    let Some(episode_number) = site_episode_number.and_then(|x| EpisodeMapping::of(&source).map(x))
+    else {
+        return Ok(vec![]);
+    };
+
+    let markers = library::skip_markers::list(&db, source.for_series_id, Some(episode_number))
+        .await?
+        .iter()
+        .filter_map(library::skip_markers::to_marker)
+        .collect();
+
+    Ok(markers)
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct EpisodeStreamsFloatingResponse {
+    #[schema(value_type = Object)]
+    pub meta: MetaEpisodeInfo,
+    /// The sources the site prefers come first
+    pub streams: Vec<StreamSource>,
+}
+/// Resolve the sources of an episode into videos that can be played directly
+///
+/// HLS streams list their qualities and alternative tracks, so players can offer picking one.
+/// Streams have to be requested with the headers listed for them, or through their `proxyUrl`.
+/// Links with an `expiresAt` have to be resolved again once it passes.
+#[utoipa::path(
+    get,
+    path = "/v1/anime/info/for-episode/streams",
+    tag = "anime",
+    params(
+        ("site" = AnimeSite, Query, description = "Site to look the episode up on"),
+        ("seriesId" = String, Query, description = "Id of the series on the site"),
+        ("episodeId" = Option<String>, Query, description = "Id of the episode, for `aniwatch`"),
+        ("episodeNumber" = Option<f64>, Query, description = "Number of the episode, for `allanime`"),
+        ("episodeType" = Option<String>, Query, description = "`sub` or `dub`, for `allanime`"),
+    ),
+    responses(
+        (status = 200, body = EpisodeStreamsFloatingResponse),
+        (status = 400, description = "Episode could not be looked up"),
+    )
+)]
+#[debug_handler]
+pub async fn episode_streams_floating(
+    WithRejection(Query(meta), _): WithRejection<Query<MetaEpisodeInfo>, V1Response>,
+    Extension(server_timings): Extension<ServerTimings>,
+) -> V1Response<EpisodeStreamsFloatingResponse> {
+    server_timings.add_started("episode_streams", None);
+    let streams = metadata::episode_streams(meta.clone()).await;
+    server_timings.end("episode_streams");
+    let mut streams = match streams {
+        Ok(streams) => streams,
+        Err(e) => {
+            debug!("Error resolving episode streams: {:?}", e);
+
+            return V1Response::Error(StatusCode::BAD_REQUEST, e.into());
+        }
+    };
+
+    for stream in &mut streams {
+        let target = ProxyTarget::new(&stream.url, &stream.headers, stream.expires_at);
+        for subtitles in &mut stream.subtitles {
+            subtitles.vtt_url = Some(target.with_url(&subtitles.url).link_to(SUBTITLES_PATH));
+        }
+        for variant in &mut stream.variants {
+            variant.proxy_url = Some(target.with_url(&variant.url).link());
+        }
+        for rendition in &mut stream.renditions {
+            rendition.proxy_url = rendition.url.as_ref().map(|x| target.with_url(x).link());
+        }
+        stream.proxy_url = Some(target.link());
+    }
+
+    V1Response::Success(EpisodeStreamsFloatingResponse { meta, streams })
 }
@@ -1,35 +1,83 @@
-use axum::{extract::Path, Extension, Json};
+use std::{collections::HashMap, time::Instant};
+
+use axum::{
+    extract::{Path, Query},
+    Extension, Json,
+};
 use axum_extra::extract::WithRejection;
 use axum_macros::debug_handler;
-use log::trace;
+use chrono::NaiveDate;
+use log::{debug, error, trace};
 use reqwest::StatusCode;
-use sea_orm::{prelude::*, QueryOrder, Set, Unchanged};
+use sea_orm::{
+    prelude::*,
+    sea_query::{Func, IntoCondition, Query as SelectQuery, SimpleExpr},
+    JoinType, LoaderTrait, QueryOrder, QuerySelect, Set, TryIntoModel, Unchanged,
+};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
+use utoipa::{IntoParams, ToSchema};
 
 use crate::{
-    metadata::{self, MetaSeriesInfo},
+    config::CONFIG,
+    library::{
+        self, enrich::Provenance, entries, health::SourceErrorKind, mal_sync, search, ListStatus,
+    },
+    metadata::{self, AnimeSite, MetaSeriesInfo},
     server::{
-        router::routes::v1::response::V1Response, server_timing::ServerTimings, state::AppState,
+        router::routes::v1::{
+            auth::AuthUser,
+            pagination::{Page, SortOrder, MAX_PAGE_SIZE},
+            response::V1Response,
+        },
+        server_timing::ServerTimings,
+        state::AppState,
     },
 };
 
 pub mod info;
+pub mod skip_markers;
 pub mod sources;
+pub mod url;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AddPayloadSource {
+    pub series_site: AnimeSite,
+    pub series_site_id: String,
+}
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct AddPayload {
-    pub name: String,
+    /// Required unless `source` is given, in which case it defaults to the source's name
+    pub name: Option<String>,
     pub description: Option<String>,
     pub mal_id: Option<i32>,
+    /// Site's series to create the anime from.
+    ///
+    /// It's added as a source and fields that aren't given are filled in from its info.
+    pub source: Option<AddPayloadSource>,
 }
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct AddResponse {
     pub payload: AddPayload,
+    #[schema(value_type = Series)]
     pub result: entity::series::Model,
+    #[schema(value_type = Option<SeriesSource>)]
+    pub source: Option<entity::series_sources::Model>,
 }
+#[utoipa::path(
+    put,
+    path = "/v1/anime",
+    tag = "anime",
+    request_body = AddPayload,
+    responses(
+        (status = 200, body = AddResponse),
+        (status = 400, description = "Neither a name nor a source was given, or the source couldn't be read"),
+        (status = 409, description = "Anime or source already exists"),
+    )
+)]
 #[debug_handler]
 pub async fn add(
     Extension(app_state): Extension<AppState>,
@@ -38,38 +86,225 @@ pub async fn add(
     let db = app_state.db.connection();
 
     trace!("Adding anime: {:?}", payload);
+    let source = match &payload.source {
+        Some(source) => {
+            let meta = serde_json::from_value::<MetaSeriesInfo>(json!({
+                "site": source.series_site,
+                "seriesId": source.series_site_id,
+            }));
+            let meta = match meta {
+                Ok(meta) => meta,
+                Err(e) => {
+                    return V1Response::Error(
+                        StatusCode::BAD_REQUEST,
+                        anyhow::anyhow!("Invalid source: {}", e).into(),
+                    );
+                }
+            };
+
+            match metadata::series_info(meta.clone()).await {
+                Ok(info) => Some((meta, info)),
+                Err(e) => {
+                    debug!("Error getting series info: {:?}", e);
+
+                    return V1Response::Error(StatusCode::BAD_REQUEST, e.into());
+                }
+            }
+        }
+        None => None,
+    };
+
+    let name = match (&payload.name, &source) {
+        (Some(name), _) => name.clone(),
+        (None, Some((_, info))) => info.name.clone(),
+        (None, None) => {
+            return V1Response::Error(
+                StatusCode::BAD_REQUEST,
+                anyhow::anyhow!("Either a name or a source is required").into(),
+            );
+        }
+    };
+
     let new = entity::series::ActiveModel {
-        name: Set(payload.name.clone()),
+        name: Set(name),
         description: Set(payload.description.clone()),
         mal_id: Set(payload.mal_id),
         ..Default::default()
     };
 
-    match new.insert(&db).await {
-        Ok(result) => V1Response::Success(AddResponse { payload, result }),
+    let result = match new.insert(&db).await {
+        Ok(result) => result,
         Err(e)
             if e.sql_err()
                 .map(|x| matches!(x, SqlErr::UniqueConstraintViolation(_)))
                 .unwrap_or_default() =>
         {
-            V1Response::Error(
+            return V1Response::Error(
                 StatusCode::CONFLICT,
                 anyhow::anyhow!("Anime already exists").into(),
-            )
+            );
         }
+        Err(e) => {
+            return V1Response::Error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                anyhow::anyhow!("Failed to add anime: {}", e).into(),
+            );
+        }
+    };
+
+    let Some((meta, info)) = source else {
+        return V1Response::Success(AddResponse {
+            payload,
+            result,
+            source: None,
+        });
+    };
+
+    add_source(&db, payload, result, &meta, &info).await
+}
+
+/// Link the source a series was created from and fill the series in from it
+async fn add_source(
+    db: &DatabaseConnection,
+    payload: AddPayload,
+    series: entity::series::Model,
+    meta: &MetaSeriesInfo,
+    info: &metadata::AnimeInfo,
+) -> V1Response<AddResponse> {
+    let source = match library::sources::attach(db, series.id, meta).await {
+        Ok(source) => source,
+        Err(e) => {
+            // Don't leave behind an anime the client will retry adding
+            if let Err(e) = entity::series::Entity::delete_by_id(series.id)
+                .exec(db)
+                .await
+            {
+                debug!(
+                    "Failed to remove anime after failing to add its source: {:?}",
+                    e
+                );
+            }
+
+            let is_conflict = e
+                .downcast_ref::<DbErr>()
+                .and_then(DbErr::sql_err)
+                .is_some_and(|x| matches!(x, SqlErr::UniqueConstraintViolation(_)));
+            if is_conflict {
+                return V1Response::Error(
+                    StatusCode::CONFLICT,
+                    anyhow::anyhow!("Source is already linked to another anime").into(),
+                );
+            }
+
+            return V1Response::Error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                anyhow::anyhow!("Failed to add anime source: {}", e).into(),
+            );
+        }
+    };
+
+    let result = match library::enrich::fill_created(db, series.clone(), &source, info).await {
+        Ok(report) => report.series,
+        Err(e) => {
+            debug!("Failed to fill in anime from its source: {:?}", e);
+            series
+        }
+    };
+
+    V1Response::Success(AddResponse {
+        payload,
+        result,
+        source: Some(source),
+    })
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct EnrichResponse {
+    pub report: library::enrich::EnrichReport,
+}
+/// Fill in the empty fields of an anime, like its MAL id or description, from the info of its sources
+#[utoipa::path(
+    post,
+    path = "/v1/anime/{series_id}/enrich",
+    tag = "anime",
+    params(("series_id" = i32, Path, description = "Id of the series")),
+    responses(
+        (status = 200, body = EnrichResponse),
+        (status = 404, description = "Anime not found"),
+    )
+)]
+#[debug_handler]
+pub async fn enrich(
+    Extension(app_state): Extension<AppState>,
+    Path(series_id): Path<i32>,
+) -> V1Response<EnrichResponse> {
+    let db = app_state.db.connection();
+
+    match library::enrich::enrich(&db, series_id, true).await {
+        Ok(Some(report)) => V1Response::Success(EnrichResponse { report }),
+        Ok(None) => V1Response::ErrorEmpty(StatusCode::NOT_FOUND),
+        Err(e) => V1Response::Error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            anyhow::anyhow!("Failed to fill in anime: {}", e).into(),
+        ),
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct EpisodesResponse {
+    pub timeline: library::episodes::Timeline,
+}
+/// All the episodes of an anime across its sources, with the translations each site has
+///
+/// Also has what's up next for the user if the anime is in their library.
+#[utoipa::path(
+    get,
+    path = "/v1/anime/{series_id}/episodes",
+    tag = "anime",
+    params(("series_id" = i32, Path, description = "Id of the series")),
+    responses(
+        (status = 200, body = EpisodesResponse),
+        (status = 404, description = "Anime not found"),
+    )
+)]
+#[debug_handler]
+pub async fn episodes(
+    Extension(app_state): Extension<AppState>,
+    AuthUser(user): AuthUser,
+    Path(series_id): Path<i32>,
+) -> V1Response<EpisodesResponse> {
+    let db = app_state.db.connection();
+
+    match library::episodes::timeline(&db, user.id, series_id).await {
+        Ok(Some(timeline)) => V1Response::Success(EpisodesResponse { timeline }),
+        Ok(None) => V1Response::ErrorEmpty(StatusCode::NOT_FOUND),
         Err(e) => V1Response::Error(
             StatusCode::INTERNAL_SERVER_ERROR,
-            anyhow::anyhow!("Failed to add anime: {}", e).into(),
+            anyhow::anyhow!("Failed to get episodes: {}", e).into(),
         ),
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct MalInfoResponse {
+    #[schema(value_type = Series)]
     pub anime: entity::series::Model,
+    #[schema(value_type = Object)]
     pub mal_info: metadata::myanimelist::anime::details::AnimeDetails,
 }
+#[utoipa::path(
+    get,
+    path = "/v1/anime/{series_id}/mal-info",
+    tag = "anime",
+    params(("series_id" = i32, Path, description = "Id of the series")),
+    responses(
+        (status = 200, body = MalInfoResponse),
+        (status = 404, description = "Anime not found or not linked to MyAnimeList"),
+    )
+)]
 #[debug_handler]
 pub async fn mal_info(
     Extension(app_state): Extension<AppState>,
@@ -117,19 +352,31 @@ pub async fn mal_info(
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct UpdatePayload {
     pub name: String,
     pub description: Option<String>,
     pub mal_id: Option<i32>,
 }
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct UpdateResponse {
     pub payload: UpdatePayload,
+    #[schema(value_type = Series)]
     pub result: entity::series::Model,
 }
+#[utoipa::path(
+    patch,
+    path = "/v1/anime/{series_id}",
+    tag = "anime",
+    params(("series_id" = i32, Path, description = "Id of the series")),
+    request_body = UpdatePayload,
+    responses(
+        (status = 200, body = UpdateResponse),
+        (status = 404, description = "Anime not found"),
+    )
+)]
 #[debug_handler]
 pub async fn update(
     Extension(app_state): Extension<AppState>,
@@ -138,12 +385,41 @@ pub async fn update(
 ) -> V1Response<UpdateResponse> {
     let db = app_state.db.connection();
 
+    let series = match entity::series::Entity::find_by_id(series_id).one(&db).await {
+        Ok(Some(series)) => series,
+        Ok(None) => {
+            return V1Response::Error(
+                StatusCode::NOT_FOUND,
+                anyhow::anyhow!("Anime not found").into(),
+            );
+        }
+        Err(e) => {
+            return V1Response::Error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                anyhow::anyhow!("Failed to fetch anime: {}", e).into(),
+            );
+        }
+    };
+
+    // Fields edited by hand no longer come from a source
+    let mut provenance = Provenance::of(&series);
+    if series.name != payload.name {
+        provenance.name = None;
+    }
+    if series.description != payload.description {
+        provenance.description = None;
+    }
+    if series.mal_id != payload.mal_id {
+        provenance.mal_id = None;
+    }
+
     trace!("Updating anime: {:?}", payload);
     let model = entity::series::ActiveModel {
         id: Unchanged(series_id),
         name: Set(payload.name.clone()),
         description: Set(payload.description.clone()),
         mal_id: Set(payload.mal_id),
+        provenance: Set(provenance.to_json()),
         ..Default::default()
     };
 
@@ -156,11 +432,126 @@ pub async fn update(
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateListPayload {
+    pub status: Option<ListStatus>,
+    /// Score out of 10
+    pub score: Option<u8>,
+    pub watched_episodes: Option<u32>,
+    pub started_watching_at: Option<NaiveDate>,
+    pub finished_watching_at: Option<NaiveDate>,
+    /// Leave filler episodes out of what's up next and the unwatched count.
+    ///
+    /// Left as it is if not given.
+    pub skip_fillers: Option<bool>,
+}
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateListResponse {
+    pub payload: UpdateListPayload,
+    #[schema(value_type = LibraryEntry)]
+    pub result: entity::library_entries::Model,
+}
+#[utoipa::path(
+    put,
+    path = "/v1/anime/{series_id}/list",
+    tag = "anime",
+    params(("series_id" = i32, Path, description = "Id of the series")),
+    request_body = UpdateListPayload,
+    responses(
+        (status = 200, body = UpdateListResponse),
+        (status = 400, description = "Score is out of range"),
+        (status = 404, description = "Anime not found"),
+    )
+)]
+#[debug_handler]
+pub async fn update_list(
+    Extension(app_state): Extension<AppState>,
+    AuthUser(user): AuthUser,
+    Path(series_id): Path<i32>,
+    WithRejection(Json(payload), _): WithRejection<Json<UpdateListPayload>, V1Response>,
+) -> V1Response<UpdateListResponse> {
+    let db = app_state.db.connection();
+
+    if payload.score.is_some_and(|x| !(1..=10).contains(&x)) {
+        return V1Response::Error(
+            StatusCode::BAD_REQUEST,
+            anyhow::anyhow!("Score must be between 1 and 10").into(),
+        );
+    }
+
+    let series = match entity::series::Entity::find_by_id(series_id).one(&db).await {
+        Ok(Some(series)) => series,
+        Ok(None) => {
+            return V1Response::Error(
+                StatusCode::NOT_FOUND,
+                anyhow::anyhow!("Anime not found").into(),
+            );
+        }
+        Err(e) => {
+            return V1Response::Error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                anyhow::anyhow!("Failed to fetch anime: {}", e).into(),
+            );
+        }
+    };
+
+    trace!(
+        "Updating list entry of anime {} for user {}: {:?}",
+        series_id,
+        user.id,
+        payload
+    );
+    let mut model = match entries::find_or_new(&db, user.id, series_id).await {
+        Ok(model) => model,
+        Err(e) => {
+            return V1Response::Error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                anyhow::anyhow!("Failed to fetch list entry: {}", e).into(),
+            );
+        }
+    };
+    model.list_status = Set(payload.status.map(|x| x.to_string()));
+    model.score = Set(payload.score.map(i32::from));
+    #[allow(clippy::cast_possible_wrap)]
+    {
+        model.watched_episodes = Set(payload.watched_episodes.map(|x| x as i32));
+    }
+    model.started_watching_at = Set(payload.started_watching_at.map(|x| x.to_string()));
+    model.finished_watching_at = Set(payload.finished_watching_at.map(|x| x.to_string()));
+    if let Some(skip_fillers) = payload.skip_fillers {
+        model.skip_fillers = Set(skip_fillers);
+    }
+    model.list_updated_at = Set(Some(chrono::Utc::now().to_rfc3339()));
+
+    match model.save(&db).await.and_then(TryIntoModel::try_into_model) {
+        Ok(result) => {
+            if series.mal_id.is_some() {
+                mal_sync::spawn_push(db, user.id, series_id);
+            }
+
+            V1Response::Success(UpdateListResponse { payload, result })
+        }
+        Err(e) => V1Response::Error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            anyhow::anyhow!("Failed to update list entry: {}", e).into(),
+        ),
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct RemoveResponse {
     pub series_id: i32,
 }
+#[utoipa::path(
+    delete,
+    path = "/v1/anime/{series_id}",
+    tag = "anime",
+    params(("series_id" = i32, Path, description = "Id of the series")),
+    responses((status = 200, body = RemoveResponse))
+)]
 #[debug_handler]
 pub async fn remove(
     Extension(app_state): Extension<AppState>,
@@ -181,49 +572,302 @@ pub async fn remove(
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ListResponseItem {
+    #[schema(value_type = Series)]
     pub anime: entity::series::Model,
+    #[schema(value_type = Vec<SeriesSource>)]
     pub sources: Vec<entity::series_sources::Model>,
+    /// The entry on the current user's list, if the anime is on it
+    #[schema(value_type = Option<LibraryEntry>)]
+    pub entry: Option<entity::library_entries::Model>,
 }
 pub type ListResponse = Vec<ListResponseItem>;
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum ListSort {
+    Name,
+    CreatedAt,
+    UpdatedAt,
+    /// When the user's list entry was last updated
+    LastWatched,
+    /// When the soonest next episode is expected, going by what the sources reported when last fetched
+    NextRelease,
+}
+#[derive(Debug, Deserialize, IntoParams)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query)]
+pub struct ListQuery {
+    /// Number of series to skip
+    pub offset: Option<u64>,
+    /// Maximum number of series to return, 100 if not given and at most 500
+    pub limit: Option<u64>,
+    /// Newest first if not given
+    pub sort: Option<ListSort>,
+    /// Ascending for names and next releases, descending for everything else if not given
+    pub order: Option<SortOrder>,
+    /// Only series with a source on this site
+    pub site: Option<AnimeSite>,
+    /// Only series that are (or aren't) linked to MyAnimeList
+    pub has_mal_id: Option<bool>,
+    /// Only series with this status on the user's list
+    pub status: Option<ListStatus>,
+    /// Only series that are (or aren't) on the user's list
+    pub in_list: Option<bool>,
+    /// Only series with this genre, ignoring case
+    pub genre: Option<String>,
+}
+impl ListQuery {
+    fn select(&self, user_id: i32) -> Select<entity::series::Entity> {
+        let mut select = entity::series::Entity::find().join(
+            JoinType::LeftJoin,
+            entity::series::Relation::LibraryEntries
+                .def()
+                .on_condition(move |_, right| {
+                    Expr::col((right, entity::library_entries::Column::UserId))
+                        .eq(user_id)
+                        .into_condition()
+                }),
+        );
+
+        if let Some(site) = &self.site {
+            select = select.filter(
+                entity::series::Column::Id.in_subquery(
+                    SelectQuery::select()
+                        .column(entity::series_sources::Column::ForSeriesId)
+                        .from(entity::series_sources::Entity)
+                        .and_where(entity::series_sources::Column::SeriesSite.eq(site.to_string()))
+                        .to_owned(),
+                ),
+            );
+        }
+        select = match self.has_mal_id {
+            Some(true) => select.filter(entity::series::Column::MalId.is_not_null()),
+            Some(false) => select.filter(entity::series::Column::MalId.is_null()),
+            None => select,
+        };
+        if let Some(status) = self.status {
+            select =
+                select.filter(entity::library_entries::Column::ListStatus.eq(status.to_string()));
+        }
+        select = match self.in_list {
+            Some(true) => select.filter(entity::library_entries::Column::Id.is_not_null()),
+            Some(false) => select.filter(entity::library_entries::Column::Id.is_null()),
+            None => select,
+        };
+        if let Some(genre) = self
+            .genre
+            .as_deref()
+            .map(str::trim)
+            .filter(|x| !x.is_empty())
+        {
+            // Genres are kept one per line
+            select = select.filter(Expr::cust_with_values(
+                "instr(char(10) || lower(\"series\".\"genres\") || char(10), char(10) || lower(?) || char(10)) > 0",
+                [genre],
+            ));
+        }
+
+        let order = self
+            .order
+            .unwrap_or(match self.sort {
+                Some(ListSort::Name | ListSort::NextRelease) => SortOrder::Asc,
+                _ => SortOrder::Desc,
+            })
+            .into();
+        select = match self.sort {
+            Some(ListSort::Name) => select.order_by(entity::series::Column::Name, order),
+            Some(ListSort::CreatedAt) => select.order_by(entity::series::Column::CreatedAt, order),
+            Some(ListSort::UpdatedAt) => select.order_by(entity::series::Column::UpdatedAt, order),
+            Some(ListSort::LastWatched) => select
+                // Series that were never watched go last either way
+                .order_by_asc(entity::library_entries::Column::ListUpdatedAt.is_null())
+                .order_by(entity::library_entries::Column::ListUpdatedAt, order),
+            Some(ListSort::NextRelease) => {
+                let next_release = next_release();
+                select
+                    // Series without an upcoming episode go last either way
+                    .order_by_asc(Expr::expr(next_release.clone()).is_null())
+                    .order_by(next_release, order)
+            }
+            None => select,
+        };
+
+        select.order_by_desc(entity::series::Column::Id)
+    }
+}
+/// Soonest release the sources of a series expect that is still in the future
+fn next_release() -> SimpleExpr {
+    SimpleExpr::SubQuery(
+        None,
+        Box::new(
+            SelectQuery::select()
+                .expr(Func::min(Expr::col(
+                    entity::series_sources::Column::NextReleaseAt,
+                )))
+                .from(entity::series_sources::Entity)
+                .and_where(
+                    Expr::col((
+                        entity::series_sources::Entity,
+                        entity::series_sources::Column::ForSeriesId,
+                    ))
+                    .equals((entity::series::Entity, entity::series::Column::Id)),
+                )
+                .and_where(
+                    entity::series_sources::Column::NextReleaseAt
+                        .gt(chrono::Utc::now().to_rfc3339()),
+                )
+                .to_owned()
+                .into_sub_query_statement(),
+        ),
+    )
+}
+/// List the anime in the library, one page at a time
+#[utoipa::path(
+    get,
+    path = "/v1/anime",
+    tag = "anime",
+    params(ListQuery),
+    responses(
+        (status = 200, body = Vec<ListResponseItem>),
+        (status = 400, description = "Invalid query"),
+    )
+)]
 #[debug_handler]
-pub async fn list(Extension(app_state): Extension<AppState>) -> V1Response<ListResponse> {
+pub async fn list(
+    Extension(app_state): Extension<AppState>,
+    AuthUser(user): AuthUser,
+    WithRejection(Query(query), _): WithRejection<Query<ListQuery>, V1Response>,
+) -> V1Response<ListResponse> {
     let db = app_state.db.connection();
+    let page = Page::new(query.offset, query.limit);
+    let select = query.select(user.id);
 
-    let list = match entity::series::Entity::find()
-        .order_by_desc(entity::series::Column::Id)
-        .find_with_related(entity::series_sources::Entity)
+    let total = match select.clone().count(&db).await {
+        Ok(total) => total,
+        Err(e) => {
+            return V1Response::Error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                anyhow::anyhow!("Failed to count anime: {}", e).into(),
+            );
+        }
+    };
+
+    let list = match page.apply(select).all(&db).await {
+        Ok(list) => list,
+        Err(e) => {
+            return V1Response::Error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                anyhow::anyhow!("Failed to fetch anime list: {}", e).into(),
+            );
+        }
+    };
+
+    let sources = match list.load_many(entity::series_sources::Entity, &db).await {
+        Ok(sources) => sources,
+        Err(e) => {
+            return V1Response::Error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                anyhow::anyhow!("Failed to fetch anime sources: {}", e).into(),
+            );
+        }
+    };
+
+    let mut entries = match entity::library_entries::Entity::find()
+        .filter(entity::library_entries::Column::UserId.eq(user.id))
+        .filter(entity::library_entries::Column::SeriesId.is_in(list.iter().map(|x| x.id)))
         .all(&db)
         .await
     {
-        Ok(list) => list,
+        Ok(entries) => entries
+            .into_iter()
+            .map(|x| (x.series_id, x))
+            .collect::<HashMap<_, _>>(),
         Err(e) => {
             return V1Response::Error(
                 StatusCode::INTERNAL_SERVER_ERROR,
-                anyhow::anyhow!("Failed to fetch anime list: {}", e).into(),
+                anyhow::anyhow!("Failed to fetch list entries: {}", e).into(),
             );
         }
     };
 
     let list = list
         .into_iter()
-        .map(|(anime, sources)| ListResponseItem { anime, sources })
+        .zip(sources)
+        .map(|(anime, sources)| ListResponseItem {
+            entry: entries.remove(&anime.id),
+            anime,
+            sources,
+        })
         .collect::<Vec<_>>();
 
-    V1Response::Success(list)
+    V1Response::Page(list, page.meta(total))
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query)]
+pub struct SearchQuery {
+    /// What to look for. Every word has to appear somewhere in the series.
+    pub q: String,
+    /// Maximum number of results, 20 if not given
+    pub limit: Option<u64>,
+}
+pub type SearchResponse = Vec<search::SearchHit>;
+/// Search the library by name, alternative names, description and genres
+#[utoipa::path(
+    get,
+    path = "/v1/anime/search",
+    tag = "anime",
+    params(SearchQuery),
+    responses(
+        (status = 200, body = Vec<SearchHit>),
+        (status = 400, description = "Invalid query"),
+    )
+)]
+#[debug_handler]
+pub async fn search(
+    Extension(app_state): Extension<AppState>,
+    WithRejection(Query(query), _): WithRejection<Query<SearchQuery>, V1Response>,
+) -> V1Response<SearchResponse> {
+    let db = app_state.db.connection();
+    let limit = query.limit.unwrap_or(20).clamp(1, MAX_PAGE_SIZE);
+
+    match search::search(&db, &query.q, limit).await {
+        Ok(hits) => V1Response::Success(hits),
+        Err(e) => V1Response::Error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            anyhow::anyhow!("Failed to search anime: {}", e).into(),
+        ),
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct InfoResponse {
+    #[schema(value_type = Series)]
     pub anime: entity::series::Model,
+    #[schema(value_type = Vec<SeriesSource>)]
     pub sources: Vec<entity::series_sources::Model>,
+    /// The entry on the current user's list, if the anime is on it
+    #[schema(value_type = Option<LibraryEntry>)]
+    pub entry: Option<entity::library_entries::Model>,
 }
+#[utoipa::path(
+    get,
+    path = "/v1/anime/{series_id}",
+    tag = "anime",
+    params(("series_id" = i32, Path, description = "Id of the series")),
+    responses(
+        (status = 200, body = InfoResponse),
+        (status = 404, description = "Anime not found"),
+    )
+)]
 #[debug_handler]
 pub async fn info(
     Extension(app_state): Extension<AppState>,
+    AuthUser(user): AuthUser,
     Path(series_id): Path<i32>,
 ) -> V1Response<InfoResponse> {
     let db = app_state.db.connection();
@@ -244,22 +888,91 @@ pub async fn info(
         }
     };
 
+    let entry = match entries::find(&db, user.id, series_id).await {
+        Ok(entry) => entry,
+        Err(e) => {
+            return V1Response::Error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                anyhow::anyhow!("Failed to fetch list entry: {}", e).into(),
+            );
+        }
+    };
+
     match anime {
         a if a.len() == 1 => {
             let (anime, sources) = a.into_iter().next().unwrap();
-            V1Response::Success(InfoResponse { anime, sources })
+            V1Response::Success(InfoResponse {
+                anime,
+                sources,
+                entry,
+            })
         }
 
         _ => V1Response::ErrorEmpty(StatusCode::NOT_FOUND),
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum SourceStatus {
+    Ok,
+    UpstreamError,
+    ParseError,
+    Timeout,
+    UnsupportedSite,
+    /// Fetching the source crashed on the server
+    InternalError,
+}
+impl From<SourceErrorKind> for SourceStatus {
+    fn from(kind: SourceErrorKind) -> Self {
+        match kind {
+            SourceErrorKind::Upstream => Self::UpstreamError,
+            SourceErrorKind::Parse => Self::ParseError,
+            SourceErrorKind::Timeout => Self::Timeout,
+            SourceErrorKind::UnsupportedSite => Self::UnsupportedSite,
+        }
+    }
+}
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct InfoExtendedSource {
+    pub source_id: i32,
+    /// Site and id of the site's series
+    #[schema(value_type = Object)]
+    pub source: serde_json::Value,
+    pub status: SourceStatus,
+    /// What went wrong, unless the status is `ok`
+    pub error: Option<String>,
+    /// How long getting the info took, in milliseconds
+    pub latency_ms: u64,
+    /// Whether recently fetched info was reused instead of asking the site again
+    pub from_cache: bool,
+    /// The series' info, if the status is `ok`
+    #[schema(value_type = Option<Object>)]
+    pub data: Option<metadata::AnimeInfo>,
+}
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct InfoExtendedResponse {
+    #[schema(value_type = Series)]
     pub anime: entity::series::Model,
-    pub sources: Vec<serde_json::Value>,
+    pub sources: Vec<InfoExtendedSource>,
+    /// The info of all the sources, and of MAL if the anime has a MAL id, combined into one
+    pub merged: library::merge::MergedAnime,
 }
+/// An anime with what each of its sources reports right now, and all of it merged together.
+///
+/// The anime itself is left as it is, fill it in from its sources with the enrich endpoint.
+#[utoipa::path(
+    get,
+    path = "/v1/anime/{series_id}/details",
+    tag = "anime",
+    params(("series_id" = i32, Path, description = "Id of the series")),
+    responses(
+        (status = 200, body = InfoExtendedResponse),
+        (status = 404, description = "Anime not found"),
+    )
+)]
 #[debug_handler]
 pub async fn info_extended(
     Extension(app_state): Extension<AppState>,
@@ -294,42 +1007,143 @@ pub async fn info_extended(
         }
     };
 
-    let sources = sources
+    let started = Instant::now();
+    let tasks = sources
         .into_iter()
-        .filter_map(|source| {
-            let meta = serde_json::from_value::<MetaSeriesInfo>(json!({
-                "site": source.series_site,
-                "seriesId": source.series_site_id,
-            }));
-
-            let meta = match meta {
-                Ok(meta) => meta,
-                Err(e) => {
-                    trace!("Error parsing source metadata: {:?}", e);
-                    return None;
-                }
-            };
+        .map(|source| {
+            let db = db.clone();
 
-            server_timings.add_started(format!("source_{}", source.series_site).as_ref(), None);
-            let task = tokio::task::spawn(async move {
-                let data = metadata::series_info(meta.clone()).await.ok()?;
+            server_timings.add_started(
+                &format!("source_{}", source.id),
+                Some(source.series_site.clone()),
+            );
+            let task = tokio::task::spawn({
+                let source = source.clone();
 
-                Some(json!({
-                    "source": meta,
-                    "data": data,
-                }))
+                async move { library::health::fetch_info_cached(&db, &source).await }
             });
 
-            Some(task)
+            async move { (source, task.await, started.elapsed()) }
         })
         .collect::<Vec<_>>();
 
-    let sources = futures::future::join_all(sources)
-        .await
+    let mal_task = tokio::task::spawn(mal_details(anime.mal_id));
+
+    let results = futures::future::join_all(tasks).await;
+    for (source, _, _) in &results {
+        server_timings.end(&format!("source_{}", source.id));
+    }
+
+    let infos = results
+        .iter()
+        .filter_map(|(source, fetched, _)| {
+            let fetched = fetched.as_ref().ok()?.as_ref().ok()?;
+
+            Some((source.clone(), fetched.info.clone()))
+        })
+        .collect::<Vec<_>>();
+
+    let mal_details = mal_task.await.ok().flatten();
+    let merged = library::merge::merge(&infos, mal_details.as_ref());
+
+    let sources = results
         .into_iter()
-        .filter_map(std::result::Result::ok)
-        .flatten()
+        .map(|(source, fetched, latency)| match fetched {
+            Ok(fetched) => extended_source(&source, fetched, latency),
+            // Each fetch has its own timeout, so the tasks only fail if they panic
+            Err(e) => {
+                error!("Source task failed: {:?}", e);
+                failed_source(&source, latency)
+            }
+        })
         .collect::<Vec<_>>();
 
-    V1Response::Success(InfoExtendedResponse { anime, sources })
+    V1Response::Success(InfoExtendedResponse {
+        anime,
+        sources,
+        merged,
+    })
+}
+
+fn source_meta(source: &entity::series_sources::Model) -> serde_json::Value {
+    library::sources::meta(source).map_or_else(
+        |_| json!({ "site": source.series_site, "seriesId": source.series_site_id }),
+        |meta| json!(meta),
+    )
+}
+
+fn latency_ms(latency: std::time::Duration) -> u64 {
+    u64::try_from(latency.as_millis()).unwrap_or(u64::MAX)
+}
+
+fn extended_source(
+    source: &entity::series_sources::Model,
+    fetched: Result<library::health::Fetched, library::health::SourceError>,
+    latency: std::time::Duration,
+) -> InfoExtendedSource {
+    let meta = source_meta(source);
+    let latency_ms = latency_ms(latency);
+
+    match fetched {
+        Ok(fetched) => InfoExtendedSource {
+            source_id: source.id,
+            source: meta,
+            status: SourceStatus::Ok,
+            error: None,
+            latency_ms,
+            from_cache: fetched.from_cache,
+            data: Some(fetched.info),
+        },
+        Err(e) => InfoExtendedSource {
+            source_id: source.id,
+            source: meta,
+            status: e.kind.into(),
+            error: Some(e.to_string()),
+            latency_ms,
+            from_cache: false,
+            data: None,
+        },
+    }
+}
+
+/// A source whose fetch crashed instead of failing with an error
+fn failed_source(
+    source: &entity::series_sources::Model,
+    latency: std::time::Duration,
+) -> InfoExtendedSource {
+    InfoExtendedSource {
+        source_id: source.id,
+        source: source_meta(source),
+        status: SourceStatus::InternalError,
+        error: Some("Fetching the source failed unexpectedly".to_string()),
+        latency_ms: latency_ms(latency),
+        from_cache: false,
+        data: None,
+    }
+}
+
+/// MAL's details of a series, if it has a MAL id and MAL responds in time
+async fn mal_details(
+    mal_id: Option<i32>,
+) -> Option<metadata::myanimelist::anime::details::AnimeDetails> {
+    #[allow(clippy::cast_sign_loss)]
+    let mal_id = mal_id? as u32;
+
+    let details = tokio::time::timeout(
+        CONFIG.library.source_timeout,
+        metadata::myanimelist::anime::details::get_details(mal_id),
+    )
+    .await;
+
+    match details {
+        Ok(Ok(details)) => Some(details),
+        Ok(Err(e)) => {
+            debug!("Failed to get MAL details: {:?}", e);
+            None
+        }
+        Err(_) => {
+            debug!("Getting MAL details timed out");
+            None
+        }
+    }
 }
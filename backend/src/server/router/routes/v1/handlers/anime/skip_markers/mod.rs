@@ -0,0 +1,212 @@
+use axum::{
+    extract::{Json, Path, Query},
+    Extension,
+};
+use axum_extra::extract::WithRejection;
+use axum_macros::debug_handler;
+use log::trace;
+use reqwest::StatusCode;
+use sea_orm::{prelude::*, ModelTrait};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    library,
+    metadata::{SkipMarker, SkipMarkerKind},
+    server::{
+        router::routes::v1::{auth::AuthUser, response::V1Response},
+        state::AppState,
+    },
+};
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query)]
+pub struct ListQuery {
+    /// Only list the markers of this episode
+    pub episode_number: Option<f64>,
+}
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+#[schema(as = SkipMarkerListResponse)]
+pub struct ListResponse {
+    #[schema(value_type = Vec<StoredSkipMarker>)]
+    pub markers: Vec<entity::skip_markers::Model>,
+}
+/// List the skip markers users submitted for the episodes of an anime
+#[utoipa::path(
+    get,
+    path = "/v1/anime/{series_id}/skip-markers",
+    operation_id = "list_skip_markers",
+    tag = "anime",
+    params(("series_id" = i32, Path, description = "Id of the series"), ListQuery),
+    responses((status = 200, body = SkipMarkerListResponse))
+)]
+#[debug_handler]
+pub async fn list(
+    Extension(app_state): Extension<AppState>,
+    Path(series_id): Path<i32>,
+    WithRejection(Query(query), _): WithRejection<Query<ListQuery>, V1Response>,
+) -> V1Response<ListResponse> {
+    let db = app_state.db.connection();
+
+    match library::skip_markers::list(&db, series_id, query.episode_number).await {
+        Ok(markers) => V1Response::Success(ListResponse { markers }),
+        Err(e) => V1Response::Error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            anyhow::anyhow!("Failed to fetch skip markers: {}", e).into(),
+        ),
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+#[schema(as = SkipMarkerAddPayload)]
+pub struct AddPayload {
+    /// Number of the episode in the series, not on the site it was watched on
+    pub episode_number: f64,
+    pub kind: SkipMarkerKind,
+    /// Seconds from the start of the episode
+    pub start: f64,
+    /// Seconds from the start of the episode
+    pub end: f64,
+}
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+#[schema(as = SkipMarkerAddResponse)]
+pub struct AddResponse {
+    pub payload: AddPayload,
+    #[schema(value_type = StoredSkipMarker)]
+    pub result: entity::skip_markers::Model,
+}
+/// Mark a part of an episode of an anime, like its opening, as skippable
+#[utoipa::path(
+    put,
+    path = "/v1/anime/{series_id}/skip-markers",
+    operation_id = "add_skip_marker",
+    tag = "anime",
+    params(("series_id" = i32, Path, description = "Id of the series")),
+    request_body = SkipMarkerAddPayload,
+    responses(
+        (status = 200, body = SkipMarkerAddResponse),
+        (status = 400, description = "Invalid episode number or time range"),
+        (status = 404, description = "Anime not found"),
+    )
+)]
+#[debug_handler]
+pub async fn add(
+    Extension(app_state): Extension<AppState>,
+    AuthUser(user): AuthUser,
+    Path(series_id): Path<i32>,
+    WithRejection(Json(payload), _): WithRejection<Json<AddPayload>, V1Response>,
+) -> V1Response<AddResponse> {
+    let db = app_state.db.connection();
+
+    if !payload.episode_number.is_finite() || payload.episode_number < 0.0 {
+        return V1Response::Error(
+            StatusCode::BAD_REQUEST,
+            anyhow::anyhow!("Invalid episode number").into(),
+        );
+    }
+    let Some(marker) = SkipMarker::new(payload.kind, payload.start, payload.end) else {
+        return V1Response::Error(
+            StatusCode::BAD_REQUEST,
+            anyhow::anyhow!("The marker has to end after it starts").into(),
+        );
+    };
+
+    match entity::series::Entity::find_by_id(series_id).one(&db).await {
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            return V1Response::Error(
+                StatusCode::NOT_FOUND,
+                anyhow::anyhow!("Anime not found").into(),
+            );
+        }
+        Err(e) => {
+            return V1Response::Error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                anyhow::anyhow!("Failed to fetch anime: {}", e).into(),
+            );
+        }
+    }
+
+    trace!(
+        "Adding skip marker to episode {} of anime {} for user {}: {:?}",
+        payload.episode_number,
+        series_id,
+        user.id,
+        marker
+    );
+    match library::skip_markers::add(&db, user.id, series_id, payload.episode_number, &marker).await
+    {
+        Ok(result) => V1Response::Success(AddResponse { payload, result }),
+        Err(e) => V1Response::Error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            anyhow::anyhow!("Failed to add skip marker: {}", e).into(),
+        ),
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+#[schema(as = SkipMarkerRemoveResponse)]
+pub struct RemoveResponse {
+    pub marker_id: i32,
+}
+/// Remove a skip marker
+///
+/// Only the user that submitted the marker or an admin can remove it.
+#[utoipa::path(
+    delete,
+    path = "/v1/anime/{series_id}/skip-markers/{marker_id}",
+    operation_id = "remove_skip_marker",
+    tag = "anime",
+    params(
+        ("series_id" = i32, Path, description = "Id of the series"),
+        ("marker_id" = i32, Path, description = "Id of the marker"),
+    ),
+    responses(
+        (status = 200, body = SkipMarkerRemoveResponse),
+        (status = 403, description = "Marker was submitted by someone else"),
+        (status = 404, description = "Marker not found"),
+    )
+)]
+#[debug_handler]
+pub async fn remove(
+    Extension(app_state): Extension<AppState>,
+    AuthUser(user): AuthUser,
+    Path((series_id, marker_id)): Path<(i32, i32)>,
+) -> V1Response<RemoveResponse> {
+    let db = app_state.db.connection();
+
+    let marker = entity::skip_markers::Entity::find_by_id(marker_id)
+        .filter(entity::skip_markers::Column::SeriesId.eq(series_id))
+        .one(&db)
+        .await;
+    let marker = match marker {
+        Ok(Some(marker)) => marker,
+        Ok(None) => return V1Response::ErrorEmpty(StatusCode::NOT_FOUND),
+        Err(e) => {
+            return V1Response::Error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                anyhow::anyhow!("Failed to fetch skip marker: {}", e).into(),
+            );
+        }
+    };
+
+    if !user.is_admin && marker.user_id != Some(user.id) {
+        return V1Response::Error(
+            StatusCode::FORBIDDEN,
+            anyhow::anyhow!("Only the user that added the marker can remove it").into(),
+        );
+    }
+
+    match marker.delete(&db).await {
+        Ok(_) => V1Response::Success(RemoveResponse { marker_id }),
+        Err(e) => V1Response::Error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            anyhow::anyhow!("Failed to remove skip marker: {}", e).into(),
+        ),
+    }
+}
@@ -1,34 +1,110 @@
 use axum::{
     extract::Json,
-    extract::{FromRef, Path},
+    extract::{FromRef, Path, Query},
     Extension,
 };
 use axum_extra::extract::{OptionalPath, WithRejection};
 use axum_macros::debug_handler;
-use log::trace;
+use log::{debug, trace};
 use reqwest::StatusCode;
 use sea_orm::{prelude::*, ActiveValue, QueryOrder};
 use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
 
 use crate::{
-    metadata::AnimeSite,
-    server::{router::routes::v1::response::V1Response, state::AppState},
+    library::episodes::EpisodeMapping,
+    metadata::{self, AnimeSite, MetaSeriesInfo},
+    server::{
+        router::routes::v1::{
+            pagination::{Page, SortOrder},
+            response::V1Response,
+        },
+        state::AppState,
+    },
 };
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
+#[schema(as = SourceListResponse)]
 pub struct ListResponse {
+    #[schema(value_type = Vec<SeriesSource>)]
     pub series: Vec<entity::series_sources::Model>,
 }
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+#[schema(as = SourceListSort)]
+pub enum ListSort {
+    CreatedAt,
+    UpdatedAt,
+}
+#[derive(Debug, Deserialize, IntoParams)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query)]
+pub struct ListQuery {
+    /// Number of sources to skip
+    pub offset: Option<u64>,
+    /// Maximum number of sources to return, 100 if not given and at most 500
+    pub limit: Option<u64>,
+    /// Newest first if not given
+    #[param(value_type = Option<SourceListSort>)]
+    pub sort: Option<ListSort>,
+    /// Descending if not given
+    pub order: Option<SortOrder>,
+    /// Only sources on this site
+    pub site: Option<AnimeSite>,
+    /// Only sources that are (or aren't) marked as broken
+    pub broken: Option<bool>,
+}
+/// List the sources of all anime, one page at a time
+#[utoipa::path(
+    get,
+    path = "/v1/anime/sources",
+    operation_id = "list_sources",
+    tag = "sources",
+    params(ListQuery),
+    responses(
+        (status = 200, body = SourceListResponse),
+        (status = 400, description = "Invalid query"),
+    )
+)]
 #[debug_handler]
-pub async fn list(Extension(app_state): Extension<AppState>) -> V1Response<ListResponse> {
+pub async fn list(
+    Extension(app_state): Extension<AppState>,
+    WithRejection(Query(query), _): WithRejection<Query<ListQuery>, V1Response>,
+) -> V1Response<ListResponse> {
     let db = app_state.db.connection();
+    let page = Page::new(query.offset, query.limit);
 
-    let info = match entity::series_sources::Entity::find()
-        .order_by_desc(entity::series_sources::Column::Id)
-        .all(&db)
-        .await
-    {
+    let mut select = entity::series_sources::Entity::find();
+    if let Some(site) = &query.site {
+        select = select.filter(entity::series_sources::Column::SeriesSite.eq(site.to_string()));
+    }
+    if let Some(broken) = query.broken {
+        select = select.filter(entity::series_sources::Column::Broken.eq(broken));
+    }
+    let order = query.order.unwrap_or(SortOrder::Desc).into();
+    select = match query.sort {
+        Some(ListSort::CreatedAt) => {
+            select.order_by(entity::series_sources::Column::CreatedAt, order)
+        }
+        Some(ListSort::UpdatedAt) => {
+            select.order_by(entity::series_sources::Column::UpdatedAt, order)
+        }
+        None => select,
+    }
+    .order_by_desc(entity::series_sources::Column::Id);
+
+    let total = match select.clone().count(&db).await {
+        Ok(total) => total,
+        Err(e) => {
+            return V1Response::Error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                anyhow::anyhow!("Failed to count sources: {}", e).into(),
+            );
+        }
+    };
+
+    let info = match page.apply(select).all(&db).await {
         Ok(list) => list,
         Err(e) => {
             return V1Response::Error(
@@ -38,15 +114,28 @@ pub async fn list(Extension(app_state): Extension<AppState>) -> V1Response<ListR
         }
     };
 
-    V1Response::Success(ListResponse { series: info })
+    V1Response::Page(ListResponse { series: info }, page.meta(total))
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
+#[schema(as = SourceListForSeriesResponse)]
 pub struct ListForSeriesResponse {
+    #[schema(value_type = Series)]
     pub series: entity::series::Model,
+    #[schema(value_type = Vec<SeriesSource>)]
     pub sources: Vec<entity::series_sources::Model>,
 }
+#[utoipa::path(
+    get,
+    path = "/v1/anime/{series_id}/sources",
+    tag = "sources",
+    params(("series_id" = i32, Path, description = "Id of the series")),
+    responses(
+        (status = 200, body = SourceListForSeriesResponse),
+        (status = 404, description = "Anime not found"),
+    )
+)]
 #[debug_handler]
 pub async fn list_for_series(
     Extension(app_state): Extension<AppState>,
@@ -77,23 +166,54 @@ pub async fn list_for_series(
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
+#[schema(as = SourceAddPayload)]
 pub struct AddPayload {
     pub series_id: Option<i32>,
     pub series_site: AnimeSite,
     pub series_site_id: String,
 }
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Deserialize, IntoParams)]
 #[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query)]
+pub struct AddQuery {
+    /// Check that the series exists on the site before adding it and remember the name and MAL id the site reports
+    #[serde(default)]
+    pub verify: bool,
+}
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+#[schema(as = SourceAddResponse)]
 pub struct AddResponse {
     pub payload: AddPayload,
+    #[schema(value_type = SeriesSource)]
     pub result: entity::series_sources::Model,
+    /// Problems found while verifying that didn't stop the source from being added
+    pub warnings: Vec<String>,
 }
+/// Add a source to a series.
+///
+/// Also available as `PUT /v1/anime/{series_id}/sources`, which takes the series from the path.
+#[utoipa::path(
+    put,
+    path = "/v1/anime/sources",
+    operation_id = "add_source",
+    tag = "sources",
+    params(AddQuery),
+    request_body = SourceAddPayload,
+    responses(
+        (status = 200, body = SourceAddResponse),
+        (status = 400, description = "No series id given, or the source couldn't be verified"),
+        (status = 404, description = "Anime not found"),
+        (status = 409, description = "Source already exists"),
+    )
+)]
 #[debug_handler]
 pub async fn add(
     Extension(app_state): Extension<AppState>,
     OptionalPath(series_id): OptionalPath<i32>,
+    WithRejection(Query(query), _): WithRejection<Query<AddQuery>, V1Response>,
     WithRejection(Json(payload), _): WithRejection<Json<AddPayload>, V1Response>,
 ) -> V1Response<AddResponse> {
     let db = app_state.db.connection();
@@ -109,15 +229,34 @@ pub async fn add(
     };
 
     trace!("Adding anime source: {:?}", payload);
-    let new = entity::series_sources::ActiveModel {
+    let mut new = entity::series_sources::ActiveModel {
         for_series_id: ActiveValue::Set(series_id),
         series_site: ActiveValue::Set(payload.series_site.to_string()),
         series_site_id: ActiveValue::Set(payload.series_site_id.clone()),
         ..Default::default()
     };
 
+    let mut warnings = vec![];
+    if query.verify {
+        let info = match verify(&db, series_id, &payload).await {
+            Ok(x) => x,
+            Err(e) => return e,
+        };
+
+        if let Some(warning) = info.warning {
+            warnings.push(warning);
+        }
+        new.verified_name = ActiveValue::Set(Some(info.name));
+        new.verified_mal_id = ActiveValue::Set(info.mal_id);
+        new.verified_at = ActiveValue::Set(Some(chrono::Utc::now().to_rfc3339()));
+    }
+
     match new.insert(&db).await {
-        Ok(result) => V1Response::Success(AddResponse { payload, result }),
+        Ok(result) => V1Response::Success(AddResponse {
+            payload,
+            result,
+            warnings,
+        }),
         Err(e)
             if e.sql_err()
                 .map(|x| matches!(x, SqlErr::UniqueConstraintViolation(_)))
@@ -139,11 +278,92 @@ pub async fn add(
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+struct Verified {
+    name: String,
+    mal_id: Option<i32>,
+    warning: Option<String>,
+}
+
+/// Look the source up on its site and compare it with the series it's being added to
+async fn verify(
+    db: &DatabaseConnection,
+    series_id: i32,
+    payload: &AddPayload,
+) -> Result<Verified, V1Response<AddResponse>> {
+    let series = match entity::series::Entity::find_by_id(series_id).one(db).await {
+        Ok(Some(series)) => series,
+        Ok(None) => {
+            return Err(V1Response::Error(
+                StatusCode::NOT_FOUND,
+                anyhow::anyhow!("Anime not found").into(),
+            ));
+        }
+        Err(e) => {
+            return Err(V1Response::Error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                anyhow::anyhow!("Failed to fetch anime: {}", e).into(),
+            ));
+        }
+    };
+
+    let meta = serde_json::from_value::<MetaSeriesInfo>(serde_json::json!({
+        "site": payload.series_site,
+        "seriesId": payload.series_site_id,
+    }));
+    let info = match meta {
+        Ok(meta) => metadata::series_info(meta).await,
+        Err(e) => Err(e.into()),
+    };
+    let info = match info {
+        Ok(info) => info,
+        Err(e) => {
+            debug!("Error verifying source: {:?}", e);
+
+            return Err(V1Response::Error(
+                StatusCode::BAD_REQUEST,
+                anyhow::anyhow!(
+                    "Series {:?} couldn't be found on {}: {}",
+                    payload.series_site_id,
+                    payload.series_site.to_string(),
+                    e
+                )
+                .into(),
+            ));
+        }
+    };
+
+    #[allow(clippy::cast_possible_wrap)]
+    let mal_id = info.mal_id.map(|x| x as i32);
+    let warning = match (series.mal_id, mal_id) {
+        (Some(expected), Some(actual)) if expected != actual => Some(format!(
+            "The site reports MAL id {actual} for {:?}, but the anime has MAL id {expected}",
+            info.name
+        )),
+        _ => None,
+    };
+
+    Ok(Verified {
+        name: info.name,
+        mal_id,
+        warning,
+    })
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
+#[schema(as = SourceInfoResponse)]
 pub struct InfoResponse {
+    #[schema(value_type = Option<SeriesSource>)]
     pub source: Option<entity::series_sources::Model>,
 }
+#[utoipa::path(
+    get,
+    path = "/v1/anime/sources/{source_id}",
+    operation_id = "source_info",
+    tag = "sources",
+    params(("source_id" = i32, Path, description = "Id of the source")),
+    responses((status = 200, body = SourceInfoResponse))
+)]
 #[debug_handler]
 pub async fn info(
     Extension(app_state): Extension<AppState>,
@@ -167,12 +387,15 @@ pub async fn info(
     V1Response::Success(InfoResponse { source: info })
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
+#[schema(as = SourceUpdatePayload)]
 pub struct UpdatePayload {
     pub for_series_id: Option<i32>,
     pub series_site: Option<AnimeSite>,
     pub series_site_id: Option<String>,
+    /// Replaces the current episode mapping, an empty one removes it
+    pub episode_mapping: Option<EpisodeMapping>,
 }
 impl FromRef<UpdatePayload> for entity::series_sources::ActiveModel {
     fn from_ref(input: &UpdatePayload) -> Self {
@@ -190,15 +413,37 @@ impl FromRef<UpdatePayload> for entity::series_sources::ActiveModel {
             new.series_site_id = ActiveValue::Set(series_site_id.clone());
         }
 
+        if let Some(episode_mapping) = &input.episode_mapping {
+            new.episode_mapping = ActiveValue::Set(
+                Some(episode_mapping)
+                    .filter(|x| **x != EpisodeMapping::default())
+                    .and_then(|x| serde_json::to_value(x).ok()),
+            );
+        }
+
         new
     }
 }
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
+#[schema(as = SourceUpdateResponse)]
 pub struct UpdateResponse {
     pub payload: UpdatePayload,
+    #[schema(value_type = SeriesSource)]
     pub result: entity::series_sources::Model,
 }
+#[utoipa::path(
+    patch,
+    path = "/v1/anime/sources/{source_id}",
+    operation_id = "update_source",
+    tag = "sources",
+    params(("source_id" = i32, Path, description = "Id of the source")),
+    request_body = SourceUpdatePayload,
+    responses(
+        (status = 200, body = SourceUpdateResponse),
+        (status = 400, description = "Invalid episode mapping"),
+    )
+)]
 #[debug_handler]
 pub async fn update(
     Extension(app_state): Extension<AppState>,
@@ -208,6 +453,14 @@ pub async fn update(
     let db = app_state.db.connection();
 
     trace!("Updating anime source: {:?}", payload);
+    if let Some(Err(e)) = payload
+        .episode_mapping
+        .as_ref()
+        .map(EpisodeMapping::validate)
+    {
+        return V1Response::Error(StatusCode::BAD_REQUEST, e.into());
+    }
+
     let mut new = entity::series_sources::ActiveModel::from_ref(&payload);
     new.id = ActiveValue::Unchanged(source_id);
 
@@ -220,11 +473,20 @@ pub async fn update(
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
+#[schema(as = SourceRemoveResponse)]
 pub struct RemoveResponse {
     pub source_id: i32,
 }
+#[utoipa::path(
+    delete,
+    path = "/v1/anime/sources/{source_id}",
+    operation_id = "remove_source",
+    tag = "sources",
+    params(("source_id" = i32, Path, description = "Id of the source")),
+    responses((status = 200, body = SourceRemoveResponse))
+)]
 #[debug_handler]
 pub async fn remove(
     Extension(app_state): Extension<AppState>,
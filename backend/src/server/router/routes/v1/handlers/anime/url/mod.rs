@@ -0,0 +1,339 @@
+use axum::{extract::Json, Extension};
+use axum_extra::extract::WithRejection;
+use axum_macros::debug_handler;
+use log::{debug, trace};
+use reqwest::StatusCode;
+use sea_orm::{DbErr, EntityTrait, SqlErr};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::{
+    library::{enrich, entries, episodes::EpisodeMapping, mal_sync, sources},
+    metadata,
+    server::{
+        router::routes::v1::{auth::AuthUser, response::V1Response},
+        state::AppState,
+    },
+};
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+#[schema(as = AddFromUrlPayload)]
+pub struct AddPayload {
+    /// Link to a series or one of its episodes on a supported site
+    pub url: String,
+    /// Series to add the link to as a source.
+    ///
+    /// If not given, the series is looked up by its MAL id or name, or created from the site's info.
+    pub series_id: Option<i32>,
+}
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+#[schema(as = AddFromUrlResponse)]
+pub struct AddResponse {
+    #[schema(value_type = Series)]
+    pub series: entity::series::Model,
+    #[schema(value_type = SeriesSource)]
+    pub source: entity::series_sources::Model,
+    /// Whether the series had to be created
+    pub created_series: bool,
+    /// Whether the source had to be added, or the link was already known
+    pub created_source: bool,
+}
+/// Add the series a link points at as a source, creating the series if needed
+#[utoipa::path(
+    put,
+    path = "/v1/anime/url",
+    operation_id = "add_from_url",
+    tag = "anime",
+    request_body = AddFromUrlPayload,
+    responses(
+        (status = 200, body = AddFromUrlResponse),
+        (status = 400, description = "Link isn't to a supported site or the site couldn't be read"),
+        (status = 404, description = "Anime not found"),
+        (status = 409, description = "Link is already a source of another anime, or became one while it was being added"),
+    )
+)]
+#[allow(clippy::too_many_lines)]
+#[debug_handler]
+pub async fn add(
+    Extension(app_state): Extension<AppState>,
+    WithRejection(Json(payload), _): WithRejection<Json<AddPayload>, V1Response>,
+) -> V1Response<AddResponse> {
+    let db = app_state.db.connection();
+
+    let resolved = match metadata::resolve_url(&payload.url) {
+        Ok(resolved) => resolved,
+        Err(e) => return V1Response::Error(StatusCode::BAD_REQUEST, e.into()),
+    };
+    trace!("Adding {:?} from {:?}", resolved.series, payload.url);
+
+    let existing = match sources::find_by_meta(&db, &resolved.series).await {
+        Ok(existing) => existing,
+        Err(e) => {
+            return V1Response::Error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                anyhow::anyhow!("Failed to fetch anime source: {}", e).into(),
+            );
+        }
+    };
+    if let Some(source) = existing {
+        if payload
+            .series_id
+            .is_some_and(|series_id| series_id != source.for_series_id)
+        {
+            return V1Response::Error(
+                StatusCode::CONFLICT,
+                anyhow::anyhow!("Link is already a source of anime {}", source.for_series_id)
+                    .into(),
+            );
+        }
+
+        return match entity::series::Entity::find_by_id(source.for_series_id)
+            .one(&db)
+            .await
+        {
+            Ok(Some(series)) => V1Response::Success(AddResponse {
+                series,
+                source,
+                created_series: false,
+                created_source: false,
+            }),
+            Ok(None) => V1Response::ErrorEmpty(StatusCode::NOT_FOUND),
+            Err(e) => V1Response::Error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                anyhow::anyhow!("Failed to fetch anime: {}", e).into(),
+            ),
+        };
+    }
+
+    let (series, created_series, info) = match payload.series_id {
+        Some(series_id) => match entity::series::Entity::find_by_id(series_id).one(&db).await {
+            Ok(Some(series)) => (series, false, None),
+            Ok(None) => {
+                return V1Response::Error(
+                    StatusCode::NOT_FOUND,
+                    anyhow::anyhow!("Anime not found").into(),
+                );
+            }
+            Err(e) => {
+                return V1Response::Error(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    anyhow::anyhow!("Failed to fetch anime: {}", e).into(),
+                );
+            }
+        },
+        None => {
+            let info = match metadata::series_info(resolved.series.clone()).await {
+                Ok(info) => info,
+                Err(e) => {
+                    debug!("Error getting series info: {:?}", e);
+
+                    return V1Response::Error(StatusCode::BAD_REQUEST, e.into());
+                }
+            };
+
+            match sources::find_or_create_series(&db, &info).await {
+                Ok((series, created)) => (series, created, Some(info)),
+                Err(e) => {
+                    return V1Response::Error(
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        anyhow::anyhow!("Failed to create anime: {}", e).into(),
+                    );
+                }
+            }
+        }
+    };
+
+    let source = match sources::attach(&db, series.id, &resolved.series).await {
+        Ok(source) => source,
+        Err(e) => {
+            // Don't leave behind an anime the client will retry adding
+            if created_series {
+                if let Err(e) = entity::series::Entity::delete_by_id(series.id)
+                    .exec(&db)
+                    .await
+                {
+                    debug!(
+                        "Failed to remove anime after failing to add its source: {:?}",
+                        e
+                    );
+                }
+            }
+
+            // Someone else linked it since it was looked up
+            let is_conflict = e
+                .downcast_ref::<DbErr>()
+                .and_then(DbErr::sql_err)
+                .is_some_and(|x| matches!(x, SqlErr::UniqueConstraintViolation(_)));
+            if is_conflict {
+                return V1Response::Error(
+                    StatusCode::CONFLICT,
+                    anyhow::anyhow!("Link is already a source of another anime").into(),
+                );
+            }
+
+            return V1Response::Error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                anyhow::anyhow!("Failed to add anime source: {}", e).into(),
+            );
+        }
+    };
+
+    let series = match info {
+        Some(info) => {
+            let filled = if created_series {
+                enrich::fill_created(&db, series.clone(), &source, &info).await
+            } else {
+                enrich::fill(&db, series.clone(), &[(source.clone(), info)]).await
+            };
+
+            match filled {
+                Ok(report) => report.series,
+                Err(e) => {
+                    debug!("Failed to fill in anime from its source: {:?}", e);
+                    series
+                }
+            }
+        }
+        None => series,
+    };
+
+    V1Response::Success(AddResponse {
+        series,
+        source,
+        created_series,
+        created_source: true,
+    })
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct WatchedPayload {
+    /// Link to an episode on a supported site
+    pub url: String,
+}
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct WatchedResponse {
+    #[schema(value_type = Series)]
+    pub series: entity::series::Model,
+    /// Number of the episode in the series, after the source's episode mapping is applied
+    pub episode_number: f64,
+    /// Number of the episode on the site
+    pub site_episode_number: f64,
+    #[schema(value_type = LibraryEntry)]
+    pub entry: entity::library_entries::Model,
+}
+/// Mark the episode a link points at as watched
+///
+/// The link's series has to be a source of an anime in the library.
+#[utoipa::path(
+    post,
+    path = "/v1/anime/url/watched",
+    operation_id = "watched_from_url",
+    tag = "anime",
+    request_body = WatchedPayload,
+    responses(
+        (status = 200, body = WatchedResponse),
+        (status = 400, description = "Link isn't to an episode on a supported site, or the episode is left out by the source's episode mapping"),
+        (status = 404, description = "No anime in the library has the link's series as a source"),
+    )
+)]
+#[debug_handler]
+pub async fn watched(
+    Extension(app_state): Extension<AppState>,
+    AuthUser(user): AuthUser,
+    WithRejection(Json(payload), _): WithRejection<Json<WatchedPayload>, V1Response>,
+) -> V1Response<WatchedResponse> {
+    let db = app_state.db.connection();
+
+    let resolved = match metadata::resolve_url(&payload.url) {
+        Ok(resolved) => resolved,
+        Err(e) => return V1Response::Error(StatusCode::BAD_REQUEST, e.into()),
+    };
+    let Some(episode) = resolved.episode else {
+        return V1Response::Error(
+            StatusCode::BAD_REQUEST,
+            anyhow::anyhow!("Link isn't to an episode").into(),
+        );
+    };
+
+    let source = match sources::find_by_meta(&db, &resolved.series).await {
+        Ok(Some(source)) => source,
+        Ok(None) => {
+            return V1Response::Error(
+                StatusCode::NOT_FOUND,
+                anyhow::anyhow!("No anime in the library has this source").into(),
+            );
+        }
+        Err(e) => {
+            return V1Response::Error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                anyhow::anyhow!("Failed to fetch anime source: {}", e).into(),
+            );
+        }
+    };
+    let series = match entity::series::Entity::find_by_id(source.for_series_id)
+        .one(&db)
+        .await
+    {
+        Ok(Some(series)) => series,
+        Ok(None) => return V1Response::ErrorEmpty(StatusCode::NOT_FOUND),
+        Err(e) => {
+            return V1Response::Error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                anyhow::anyhow!("Failed to fetch anime: {}", e).into(),
+            );
+        }
+    };
+
+    let site_episode_number = match metadata::episode_number(&episode).await {
+        Ok(x) => x,
+        Err(e) => {
+            debug!("Error getting episode number: {:?}", e);
+
+            return V1Response::Error(StatusCode::BAD_REQUEST, e.into());
+        }
+    };
+    let Some(episode_number) = EpisodeMapping::of(&source).map(site_episode_number) else {
+        return V1Response::Error(
+            StatusCode::BAD_REQUEST,
+            anyhow::anyhow!(
+                "Episode {} is left out by the episode mapping of source {}",
+                site_episode_number,
+                source.id
+            )
+            .into(),
+        );
+    };
+
+    // Specials like 12.5 count as the episode before them
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let watched_episodes = episode_number.max(0.0).floor() as u32;
+    trace!(
+        "Marking episode {} of anime {} watched for user {}",
+        watched_episodes,
+        series.id,
+        user.id
+    );
+
+    match entries::mark_watched(&db, user.id, series.id, watched_episodes).await {
+        Ok(entry) => {
+            if series.mal_id.is_some() {
+                mal_sync::spawn_push(db, user.id, series.id);
+            }
+
+            V1Response::Success(WatchedResponse {
+                series,
+                episode_number,
+                site_episode_number,
+                entry,
+            })
+        }
+        Err(e) => V1Response::Error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            anyhow::anyhow!("Failed to update list entry: {}", e).into(),
+        ),
+    }
+}
@@ -0,0 +1,173 @@
+use axum::{
+    http::{header, HeaderMap},
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use axum_extra::extract::WithRejection;
+use axum_macros::debug_handler;
+use log::{debug, info};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::{
+    auth,
+    config::CONFIG,
+    server::{
+        router::routes::v1::{
+            auth::{clear_session_cookie, request_token, session_cookie},
+            response::V1Response,
+        },
+        state::AppState,
+    },
+};
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CredentialsPayload {
+    pub username: String,
+    pub password: String,
+}
+
+/// Create the first account. Once there is a user, only admins can create new ones.
+#[utoipa::path(
+    post,
+    path = "/v1/auth/register",
+    tag = "auth",
+    security(()),
+    request_body = CredentialsPayload,
+    responses(
+        (status = 200, body = User),
+        (status = 400, description = "Invalid username or password"),
+        (status = 403, description = "There already are users"),
+    )
+)]
+#[debug_handler]
+pub async fn register(
+    Extension(app_state): Extension<AppState>,
+    WithRejection(Json(payload), _): WithRejection<Json<CredentialsPayload>, V1Response>,
+) -> V1Response<entity::users::Model> {
+    let db = app_state.db.connection();
+
+    match auth::has_users(&db).await {
+        Ok(false) => {}
+        Ok(true) => {
+            return V1Response::Error(
+                StatusCode::FORBIDDEN,
+                anyhow::anyhow!("Registration is closed, ask an admin for an account").into(),
+            );
+        }
+        Err(e) => {
+            return V1Response::Error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                anyhow::anyhow!("Failed to check for existing users: {}", e).into(),
+            );
+        }
+    }
+
+    match auth::create_user(&db, &payload.username, &payload.password, true).await {
+        Ok(user) => {
+            info!("Registered first user {:?}", user.username);
+            V1Response::Success(user)
+        }
+        Err(e) => V1Response::Error(StatusCode::BAD_REQUEST, e.into()),
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LoginResponse {
+    #[schema(value_type = User)]
+    pub user: entity::users::Model,
+    /// Can also be sent as a `Bearer` token instead of relying on the cookie
+    pub token: String,
+    pub expires_at: String,
+}
+/// Start a session. The token is set as the `session` cookie and also returned.
+#[utoipa::path(
+    post,
+    path = "/v1/auth/login",
+    tag = "auth",
+    security(()),
+    request_body = CredentialsPayload,
+    responses(
+        (status = 200, body = LoginResponse),
+        (status = 401, description = "Wrong username or password"),
+    )
+)]
+#[debug_handler]
+pub async fn login(
+    Extension(app_state): Extension<AppState>,
+    WithRejection(Json(payload), _): WithRejection<Json<CredentialsPayload>, V1Response>,
+) -> Response {
+    let db = app_state.db.connection();
+
+    let user = match auth::authenticate(&db, &payload.username, &payload.password).await {
+        Ok(Some(user)) => user,
+        Ok(None) => {
+            debug!("Failed login for {:?}", payload.username);
+            return V1Response::<()>::Error(
+                StatusCode::UNAUTHORIZED,
+                anyhow::anyhow!("Wrong username or password").into(),
+            )
+            .into_response();
+        }
+        Err(e) => {
+            return V1Response::<()>::Error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                anyhow::anyhow!("Failed to log in: {}", e).into(),
+            )
+            .into_response();
+        }
+    };
+
+    let (token, session) =
+        match auth::create_session(&db, user.id, CONFIG.auth.session_duration).await {
+            Ok(x) => x,
+            Err(e) => {
+                return V1Response::<()>::Error(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    anyhow::anyhow!("Failed to create session: {}", e).into(),
+                )
+                .into_response();
+            }
+        };
+
+    (
+        [(header::SET_COOKIE, session_cookie(&token))],
+        V1Response::Success(LoginResponse {
+            user,
+            token,
+            expires_at: session.expires_at,
+        }),
+    )
+        .into_response()
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/auth/logout",
+    tag = "auth",
+    security(()),
+    responses((status = 200))
+)]
+#[debug_handler]
+pub async fn logout(Extension(app_state): Extension<AppState>, headers: HeaderMap) -> Response {
+    let db = app_state.db.connection();
+
+    if let Some(token) = request_token(&headers) {
+        if let Err(e) = auth::delete_session(&db, &token).await {
+            return V1Response::<()>::Error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                anyhow::anyhow!("Failed to log out: {}", e).into(),
+            )
+            .into_response();
+        }
+    }
+
+    (
+        [(header::SET_COOKIE, clear_session_cookie())],
+        V1Response::Success(()),
+    )
+        .into_response()
+}
@@ -2,6 +2,13 @@ use axum_macros::debug_handler;
 
 use crate::server::router::routes::v1::response::V1Response;
 
+#[utoipa::path(
+    get,
+    path = "/v1",
+    tag = "meta",
+    security(()),
+    responses((status = 200, body = String))
+)]
 #[debug_handler]
 pub async fn index() -> V1Response<&'static str> {
     V1Response::Success("Hello, World!")
@@ -0,0 +1,136 @@
+use axum::{
+    body::Bytes,
+    extract::Query,
+    http::header,
+    response::{IntoResponse, Response},
+    Extension,
+};
+use axum_extra::extract::WithRejection;
+use axum_macros::debug_handler;
+use log::{debug, trace};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use utoipa::IntoParams;
+
+use crate::{
+    library::{
+        export::{self, ExportFormat},
+        import::{self, ImportReport, MalImportOptions},
+        mal_xml,
+    },
+    server::{
+        router::routes::v1::{auth::AuthUser, response::V1Response},
+        state::AppState,
+    },
+};
+
+#[derive(Debug, Default, Serialize, Deserialize, IntoParams)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query)]
+pub struct ImportMalQuery {
+    #[serde(default)]
+    pub discover_sources: bool,
+}
+/// Import an `animelist.xml` export from MyAnimeList, optionally gzipped
+#[utoipa::path(
+    post,
+    path = "/v1/import/mal",
+    tag = "library",
+    params(ImportMalQuery),
+    request_body(content = Vec<u8>, content_type = "application/xml"),
+    responses(
+        (status = 200, body = ImportReport),
+        (status = 400, description = "Export could not be parsed"),
+    )
+)]
+#[debug_handler]
+pub async fn import_mal(
+    Extension(app_state): Extension<AppState>,
+    AuthUser(user): AuthUser,
+    WithRejection(Query(query), _): WithRejection<Query<ImportMalQuery>, V1Response>,
+    body: Bytes,
+) -> V1Response<ImportReport> {
+    let db = app_state.db.connection();
+
+    trace!("Importing MAL export of {} bytes", body.len());
+    let export = match tokio::task::spawn_blocking(move || mal_xml::parse(&body)).await {
+        Ok(Ok(export)) => export,
+        Ok(Err(e)) => {
+            debug!("Error parsing MAL export: {:?}", e);
+            return V1Response::Error(StatusCode::BAD_REQUEST, e.into());
+        }
+        Err(e) => {
+            return V1Response::Error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                anyhow::anyhow!("Failed to parse MAL export: {}", e).into(),
+            );
+        }
+    };
+
+    let options = MalImportOptions {
+        discover_sources: query.discover_sources,
+    };
+
+    match import::import_mal_export(&db, user.id, export, options).await {
+        Ok(report) => V1Response::Success(report),
+        Err(e) => V1Response::Error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            anyhow::anyhow!("Failed to import MAL export: {}", e).into(),
+        ),
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, IntoParams)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query)]
+pub struct ExportQuery {
+    pub format: ExportFormat,
+    /// Respond with just the exported file instead of the usual JSON envelope
+    #[serde(default)]
+    pub download: bool,
+}
+/// Export the list of the current user.
+///
+/// With `download` set the file itself is sent instead of the JSON envelope.
+#[utoipa::path(
+    get,
+    path = "/v1/export",
+    tag = "library",
+    params(ExportQuery),
+    responses((status = 200, body = Export))
+)]
+#[debug_handler]
+pub async fn export(
+    Extension(app_state): Extension<AppState>,
+    AuthUser(user): AuthUser,
+    WithRejection(Query(query), _): WithRejection<Query<ExportQuery>, V1Response>,
+) -> Response {
+    let db = app_state.db.connection();
+
+    let export = match export::export_library(&db, user.id, query.format).await {
+        Ok(export) => export,
+        Err(e) => {
+            return V1Response::<()>::Error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                anyhow::anyhow!("Failed to export library: {}", e).into(),
+            )
+            .into_response();
+        }
+    };
+
+    if !query.download {
+        return V1Response::Success(export).into_response();
+    }
+
+    (
+        [
+            (header::CONTENT_TYPE, export.mime_type),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}\"", export.file_name),
+            ),
+        ],
+        export.content,
+    )
+        .into_response()
+}
@@ -0,0 +1,202 @@
+use axum::{extract::Query, Extension};
+use axum_extra::extract::WithRejection;
+use axum_macros::debug_handler;
+use log::{debug, trace};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    library::mal_sync::{self, client::MalClient, MalSyncStatus, SyncReport},
+    server::{
+        router::routes::v1::{auth::AuthUser, response::V1Response},
+        state::AppState,
+    },
+};
+
+#[utoipa::path(
+    get,
+    path = "/v1/mal",
+    tag = "mal",
+    responses((status = 200, body = MalSyncStatus))
+)]
+#[debug_handler]
+pub async fn status(
+    Extension(app_state): Extension<AppState>,
+    AuthUser(user): AuthUser,
+) -> V1Response<MalSyncStatus> {
+    let db = app_state.db.connection();
+
+    match mal_sync::status(&db, user.id).await {
+        Ok(status) => V1Response::Success(status),
+        Err(e) => V1Response::Error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            anyhow::anyhow!("Failed to get MAL link status: {}", e).into(),
+        ),
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthorizeResponse {
+    pub authorization_url: String,
+}
+#[utoipa::path(
+    get,
+    path = "/v1/mal/auth",
+    tag = "mal",
+    responses((status = 200, body = AuthorizeResponse))
+)]
+#[debug_handler]
+pub async fn authorize(
+    Extension(app_state): Extension<AppState>,
+    AuthUser(user): AuthUser,
+) -> V1Response<AuthorizeResponse> {
+    let db = app_state.db.connection();
+    let client = match MalClient::from_config() {
+        Ok(x) => x,
+        Err(e) => {
+            return V1Response::Error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                anyhow::anyhow!("Failed to create MAL client: {}", e).into(),
+            );
+        }
+    };
+
+    match mal_sync::start_authorization(&db, &client, user.id).await {
+        Ok(authorization_url) => V1Response::Success(AuthorizeResponse { authorization_url }),
+        Err(e) => V1Response::Error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            anyhow::anyhow!("Failed to start MAL authorization: {}", e).into(),
+        ),
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, IntoParams)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query)]
+pub struct CallbackQuery {
+    pub code: String,
+    pub state: String,
+}
+/// Where MAL sends the user back to.
+///
+/// Needs the session of the user who started the authorization, whose state it has to be.
+#[utoipa::path(
+    get,
+    path = "/v1/mal/auth/callback",
+    tag = "mal",
+    params(CallbackQuery),
+    responses(
+        (status = 200, body = MalSyncStatus),
+        (status = 400, description = "Unknown or expired authorization state, or one another user started"),
+        (status = 502, description = "MAL refused the authorization code"),
+    )
+)]
+#[debug_handler]
+pub async fn callback(
+    Extension(app_state): Extension<AppState>,
+    AuthUser(user): AuthUser,
+    WithRejection(Query(query), _): WithRejection<Query<CallbackQuery>, V1Response>,
+) -> V1Response<MalSyncStatus> {
+    let db = app_state.db.connection();
+    let client = match MalClient::from_config() {
+        Ok(x) => x,
+        Err(e) => {
+            return V1Response::Error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                anyhow::anyhow!("Failed to create MAL client: {}", e).into(),
+            );
+        }
+    };
+
+    let pending = match mal_sync::take_pending_authorization(&db, &query.state, user.id).await {
+        Ok(Some(x)) => x,
+        Ok(None) => {
+            return V1Response::Error(
+                StatusCode::BAD_REQUEST,
+                anyhow::anyhow!("Unknown or expired authorization state").into(),
+            );
+        }
+        Err(e) => {
+            return V1Response::Error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                anyhow::anyhow!("Failed to look up authorization state: {}", e).into(),
+            );
+        }
+    };
+
+    trace!("Finishing MAL authorization");
+    if let Err(e) = mal_sync::finish_authorization(&db, &client, &pending, &query.code).await {
+        debug!("Error finishing MAL authorization: {:?}", e);
+        return V1Response::Error(
+            StatusCode::BAD_GATEWAY,
+            anyhow::anyhow!("Failed to finish MAL authorization: {}", e).into(),
+        );
+    }
+
+    match mal_sync::status(&db, pending.user_id).await {
+        Ok(status) => V1Response::Success(status),
+        Err(e) => V1Response::Error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            anyhow::anyhow!("Failed to get MAL link status: {}", e).into(),
+        ),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/v1/mal/auth",
+    tag = "mal",
+    responses((status = 200, body = MalSyncStatus))
+)]
+#[debug_handler]
+pub async fn unlink(
+    Extension(app_state): Extension<AppState>,
+    AuthUser(user): AuthUser,
+) -> V1Response<MalSyncStatus> {
+    let db = app_state.db.connection();
+
+    if let Err(e) = mal_sync::unlink(&db, user.id).await {
+        return V1Response::Error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            anyhow::anyhow!("Failed to unlink MAL account: {}", e).into(),
+        );
+    }
+
+    status(Extension(app_state), AuthUser(user)).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/mal/sync",
+    tag = "mal",
+    responses(
+        (status = 200, body = SyncReport),
+        (status = 502, description = "Syncing with MAL failed"),
+    )
+)]
+#[debug_handler]
+pub async fn sync(
+    Extension(app_state): Extension<AppState>,
+    AuthUser(user): AuthUser,
+) -> V1Response<SyncReport> {
+    let db = app_state.db.connection();
+    let client = match MalClient::from_config() {
+        Ok(x) => x,
+        Err(e) => {
+            return V1Response::Error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                anyhow::anyhow!("Failed to create MAL client: {}", e).into(),
+            );
+        }
+    };
+
+    match mal_sync::sync(&db, &client, user.id).await {
+        Ok(report) => V1Response::Success(report),
+        Err(e) => V1Response::Error(
+            StatusCode::BAD_GATEWAY,
+            anyhow::anyhow!("Failed to sync with MAL: {}", e).into(),
+        ),
+    }
+}
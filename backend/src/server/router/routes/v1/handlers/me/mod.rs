@@ -0,0 +1,274 @@
+use axum::{extract::Path, Extension, Json};
+use axum_extra::extract::WithRejection;
+use axum_macros::debug_handler;
+use chrono::{DateTime, Utc};
+use log::trace;
+use reqwest::StatusCode;
+use sea_orm::{prelude::*, ActiveValue, IntoActiveModel, QueryOrder};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::{
+    auth::{self, TokenScope},
+    server::{
+        router::routes::v1::{
+            auth::{AuthScope, AuthUser},
+            response::V1Response,
+        },
+        state::AppState,
+    },
+};
+
+#[utoipa::path(
+    get,
+    path = "/v1/me",
+    tag = "me",
+    responses((status = 200, body = User))
+)]
+#[debug_handler]
+pub async fn me(AuthUser(user): AuthUser) -> V1Response<entity::users::Model> {
+    V1Response::Success(user)
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/me/settings",
+    tag = "me",
+    responses((status = 200, body = UserSettings))
+)]
+#[debug_handler]
+pub async fn settings(
+    Extension(app_state): Extension<AppState>,
+    AuthUser(user): AuthUser,
+) -> V1Response<entity::user_settings::Model> {
+    let db = app_state.db.connection();
+
+    match entity::user_settings::Entity::find_by_id(user.id)
+        .one(&db)
+        .await
+    {
+        Ok(Some(settings)) => V1Response::Success(settings),
+        Ok(None) => V1Response::ErrorEmpty(StatusCode::NOT_FOUND),
+        Err(e) => V1Response::Error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            anyhow::anyhow!("Failed to fetch settings: {}", e).into(),
+        ),
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateSettingsPayload {
+    pub notify_new_episodes: Option<bool>,
+    pub notify_watching_only: Option<bool>,
+}
+#[utoipa::path(
+    put,
+    path = "/v1/me/settings",
+    tag = "me",
+    request_body = UpdateSettingsPayload,
+    responses((status = 200, body = UserSettings))
+)]
+#[debug_handler]
+pub async fn update_settings(
+    Extension(app_state): Extension<AppState>,
+    AuthUser(user): AuthUser,
+    WithRejection(Json(payload), _): WithRejection<Json<UpdateSettingsPayload>, V1Response>,
+) -> V1Response<entity::user_settings::Model> {
+    let db = app_state.db.connection();
+
+    trace!("Updating settings of user {}: {:?}", user.id, payload);
+    let existing = match entity::user_settings::Entity::find_by_id(user.id)
+        .one(&db)
+        .await
+    {
+        Ok(x) => x,
+        Err(e) => {
+            return V1Response::Error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                anyhow::anyhow!("Failed to fetch settings: {}", e).into(),
+            );
+        }
+    };
+
+    let mut model = match existing {
+        Some(existing) => existing.into_active_model(),
+        None => entity::user_settings::ActiveModel {
+            user_id: ActiveValue::Set(user.id),
+            notify_new_episodes: ActiveValue::Set(true),
+            notify_watching_only: ActiveValue::Set(true),
+            ..Default::default()
+        },
+    };
+    if let Some(x) = payload.notify_new_episodes {
+        model.notify_new_episodes = ActiveValue::Set(x);
+    }
+    if let Some(x) = payload.notify_watching_only {
+        model.notify_watching_only = ActiveValue::Set(x);
+    }
+
+    let saved = if model.created_at.is_unchanged() {
+        model.update(&db).await
+    } else {
+        model.insert(&db).await
+    };
+
+    match saved {
+        Ok(settings) => V1Response::Success(settings),
+        Err(e) => V1Response::Error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            anyhow::anyhow!("Failed to update settings: {}", e).into(),
+        ),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/me/tokens",
+    tag = "me",
+    responses((status = 200, body = Vec<ApiToken>))
+)]
+#[debug_handler]
+pub async fn tokens(
+    Extension(app_state): Extension<AppState>,
+    AuthUser(user): AuthUser,
+) -> V1Response<Vec<entity::api_tokens::Model>> {
+    let db = app_state.db.connection();
+
+    match entity::api_tokens::Entity::find()
+        .filter(entity::api_tokens::Column::UserId.eq(user.id))
+        .order_by_desc(entity::api_tokens::Column::Id)
+        .all(&db)
+        .await
+    {
+        Ok(tokens) => V1Response::Success(tokens),
+        Err(e) => V1Response::Error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            anyhow::anyhow!("Failed to fetch API tokens: {}", e).into(),
+        ),
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateTokenPayload {
+    pub name: String,
+    pub scope: TokenScope,
+    /// Tokens without an expiry date work until they are revoked
+    pub expires_at: Option<DateTime<Utc>>,
+}
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateTokenResponse {
+    /// Only ever shown here, it can't be looked up later
+    pub token: String,
+    #[schema(value_type = ApiToken)]
+    pub result: entity::api_tokens::Model,
+}
+#[utoipa::path(
+    post,
+    path = "/v1/me/tokens",
+    tag = "me",
+    request_body = CreateTokenPayload,
+    responses(
+        (status = 200, body = CreateTokenResponse),
+        (status = 400, description = "Invalid name or expiry date"),
+        (status = 403, description = "Scope is not allowed"),
+    )
+)]
+#[debug_handler]
+pub async fn create_token(
+    Extension(app_state): Extension<AppState>,
+    AuthUser(user): AuthUser,
+    scope: AuthScope,
+    WithRejection(Json(payload), _): WithRejection<Json<CreateTokenPayload>, V1Response>,
+) -> V1Response<CreateTokenResponse> {
+    let db = app_state.db.connection();
+
+    if payload.name.trim().is_empty() {
+        return V1Response::Error(
+            StatusCode::BAD_REQUEST,
+            anyhow::anyhow!("Token name can't be empty").into(),
+        );
+    }
+
+    // A token can't be used to hand out more access than it has itself
+    if !scope.allows(payload.scope) {
+        return V1Response::Error(
+            StatusCode::FORBIDDEN,
+            anyhow::anyhow!("Can't create a token with more access than the current one").into(),
+        );
+    }
+    if payload.scope == TokenScope::Admin && !user.is_admin {
+        return V1Response::Error(
+            StatusCode::FORBIDDEN,
+            anyhow::anyhow!("Only admins can create admin tokens").into(),
+        );
+    }
+    if payload.expires_at.is_some_and(|x| x <= Utc::now()) {
+        return V1Response::Error(
+            StatusCode::BAD_REQUEST,
+            anyhow::anyhow!("Expiry date must be in the future").into(),
+        );
+    }
+
+    trace!(
+        "Creating {:?} API token {:?} for user {}",
+        payload.scope,
+        payload.name,
+        user.id
+    );
+    match auth::create_api_token(
+        &db,
+        user.id,
+        payload.name.trim(),
+        payload.scope,
+        payload.expires_at,
+    )
+    .await
+    {
+        Ok((token, result)) => V1Response::Success(CreateTokenResponse { token, result }),
+        Err(e) => V1Response::Error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            anyhow::anyhow!("Failed to create API token: {}", e).into(),
+        ),
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DeleteTokenResponse {
+    pub token_id: i32,
+}
+#[utoipa::path(
+    delete,
+    path = "/v1/me/tokens/{token_id}",
+    tag = "me",
+    params(("token_id" = i32, Path, description = "Id of the token")),
+    responses(
+        (status = 200, body = DeleteTokenResponse),
+        (status = 404, description = "Token not found"),
+    )
+)]
+#[debug_handler]
+pub async fn delete_token(
+    Extension(app_state): Extension<AppState>,
+    AuthUser(user): AuthUser,
+    Path(token_id): Path<i32>,
+) -> V1Response<DeleteTokenResponse> {
+    let db = app_state.db.connection();
+
+    match entity::api_tokens::Entity::delete_many()
+        .filter(entity::api_tokens::Column::Id.eq(token_id))
+        .filter(entity::api_tokens::Column::UserId.eq(user.id))
+        .exec(&db)
+        .await
+    {
+        Ok(res) if res.rows_affected == 0 => V1Response::ErrorEmpty(StatusCode::NOT_FOUND),
+        Ok(_) => V1Response::Success(DeleteTokenResponse { token_id }),
+        Err(e) => V1Response::Error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            anyhow::anyhow!("Failed to delete API token: {}", e).into(),
+        ),
+    }
+}
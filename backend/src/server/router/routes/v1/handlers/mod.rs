@@ -1,2 +1,8 @@
+pub(crate) mod admin;
 pub(crate) mod anime;
+pub(crate) mod auth;
 pub(crate) mod index;
+pub(crate) mod library;
+pub(crate) mod mal;
+pub(crate) mod me;
+pub(crate) mod stream;
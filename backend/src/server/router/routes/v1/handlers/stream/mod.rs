@@ -0,0 +1,310 @@
+use std::time::Duration;
+
+use axum::{
+    body::{self, StreamBody},
+    extract::Query,
+    http::{header, HeaderMap, HeaderName, HeaderValue},
+    response::{IntoResponse, Response},
+};
+use axum_extra::extract::WithRejection;
+use axum_macros::debug_handler;
+use lazy_static::lazy_static;
+use log::{debug, trace};
+use reqwest::{Client, StatusCode};
+use serde::Deserialize;
+use url::Url;
+use utoipa::IntoParams;
+
+use crate::{
+    metadata::{public_client, subtitles, StreamKind, SubtitleFormat},
+    server::{
+        router::routes::v1::response::V1Response,
+        stream_proxy::{self, ProxyTarget, VerifyError},
+    },
+};
+
+lazy_static! {
+    /// Passes bodies on as they are, so `Content-Length` and `Range` keep lining up
+    static ref PROXY_CLIENT: Client = public_client::builder()
+        .connect_timeout(Duration::from_secs(3))
+        .no_gzip()
+        .no_deflate()
+        .build()
+        .unwrap();
+}
+
+/// Largest subtitle file that gets converted
+const MAX_SUBTITLES_SIZE: u64 = 10 * 1024 * 1024;
+
+/// Largest playlist that gets rewritten, they are read whole so the URIs in them can be replaced
+const MAX_PLAYLIST_SIZE: u64 = 10 * 1024 * 1024;
+
+/// Request headers passed on to the site
+const FORWARDED_HEADERS: [HeaderName; 2] = [header::RANGE, header::IF_RANGE];
+
+/// Response headers passed on from the site
+const PASSED_HEADERS: [HeaderName; 6] = [
+    header::CONTENT_LENGTH,
+    header::CONTENT_RANGE,
+    header::ACCEPT_RANGES,
+    header::CACHE_CONTROL,
+    header::ETAG,
+    header::LAST_MODIFIED,
+];
+
+/// Set on everything served from a site, so nothing it serves runs as a page of the API's origin
+const ISOLATION_HEADERS: [(HeaderName, &str); 2] = [
+    (header::X_CONTENT_TYPE_OPTIONS, "nosniff"),
+    (header::CONTENT_SECURITY_POLICY, "sandbox"),
+];
+
+/// Content types of the site passed on as they are, the rest are served as `application/octet-stream`
+fn is_media_type(content_type: &str) -> bool {
+    let content_type = content_type.trim().to_lowercase();
+
+    content_type.starts_with("video/")
+        || content_type.starts_with("audio/")
+        || content_type.starts_with("text/vtt")
+        || content_type.starts_with("application/octet-stream")
+        || content_type.contains("mpegurl")
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ProxyQuery {
+    pub token: String,
+    pub signature: String,
+}
+/// Fetch a stream through the server, with the headers the site wants
+///
+/// Links to this are handed out by `/v1/anime/info/for-episode/streams` and stop working after a while.
+/// HLS playlists are rewritten so everything they reference goes through here too,
+/// and `Range` requests are passed on, so MP4 files can be seeked.
+#[utoipa::path(
+    get,
+    path = "/v1/stream/proxy",
+    tag = "stream",
+    security(()),
+    params(
+        ProxyQuery,
+        ("Range" = Option<String>, Header, description = "Passed on to the site"),
+    ),
+    responses(
+        (status = 200, description = "The stream, as the site sent it"),
+        (status = 206, description = "Part of the stream, for `Range` requests"),
+        (status = 403, description = "The link wasn't handed out by this server"),
+        (status = 410, description = "The link expired, the streams have to be resolved again"),
+        (status = 502, description = "The site didn't serve the stream, or isn't on a public address"),
+    )
+)]
+#[debug_handler]
+pub async fn proxy(
+    WithRejection(Query(query), _): WithRejection<Query<ProxyQuery>, V1Response>,
+    headers: HeaderMap,
+) -> Response {
+    let target = match verify(&query) {
+        Ok(target) => target,
+        Err(response) => return response,
+    };
+
+    trace!("Proxying stream {:?}", target.url);
+    let upstream = match fetch(&target, &headers).await {
+        Ok(upstream) => upstream,
+        Err(e) => {
+            debug!("Failed to fetch stream {:?}: {:?}", target.url, e);
+
+            return V1Response::<()>::Error(
+                StatusCode::BAD_GATEWAY,
+                anyhow::anyhow!("Failed to fetch stream: {}", e).into(),
+            )
+            .into_response();
+        }
+    };
+
+    let status = upstream.status();
+    if !status.is_success() && status != StatusCode::RANGE_NOT_SATISFIABLE {
+        return V1Response::<()>::Error(
+            StatusCode::BAD_GATEWAY,
+            anyhow::anyhow!("Site responded with {}", status).into(),
+        )
+        .into_response();
+    }
+
+    if is_playlist(&target, &upstream) {
+        return playlist(&target, upstream).await;
+    }
+
+    passthrough(upstream)
+}
+
+fn verify(query: &ProxyQuery) -> Result<ProxyTarget, Response> {
+    ProxyTarget::verify(&query.token, &query.signature).map_err(|e| {
+        let status = match e {
+            VerifyError::Invalid => StatusCode::FORBIDDEN,
+            VerifyError::Expired => StatusCode::GONE,
+        };
+
+        V1Response::<()>::Error(status, anyhow::anyhow!("{}", e).into()).into_response()
+    })
+}
+
+async fn fetch(target: &ProxyTarget, headers: &HeaderMap) -> anyhow::Result<reqwest::Response> {
+    public_client::check_url(&Url::parse(&target.url)?)?;
+
+    let mut request = PROXY_CLIENT.get(&target.url);
+    for (name, value) in &target.headers {
+        request = request.header(name, value);
+    }
+    for name in FORWARDED_HEADERS {
+        if let Some(value) = headers.get(&name) {
+            request = request.header(name, value.clone());
+        }
+    }
+
+    Ok(request.send().await?)
+}
+
+fn is_playlist(target: &ProxyTarget, upstream: &reqwest::Response) -> bool {
+    let content_type = upstream
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|x| x.to_str().ok())
+        .unwrap_or_default()
+        .to_lowercase();
+
+    content_type.contains("mpegurl")
+        || StreamKind::of_url(&target.url) == Some(StreamKind::Hls)
+        || StreamKind::of_url(upstream.url().as_str()) == Some(StreamKind::Hls)
+}
+
+async fn playlist(target: &ProxyTarget, upstream: reqwest::Response) -> Response {
+    // Relative URIs are relative to where the playlist ended up after redirects
+    let base = upstream.url().clone();
+    let playlist = match read_text(upstream, MAX_PLAYLIST_SIZE).await {
+        Ok(playlist) => playlist,
+        Err(e) => {
+            return V1Response::<()>::Error(
+                StatusCode::BAD_GATEWAY,
+                anyhow::anyhow!("Failed to fetch playlist: {}", e).into(),
+            )
+            .into_response();
+        }
+    };
+
+    (
+        [(header::CONTENT_TYPE, "application/vnd.apple.mpegurl")],
+        ISOLATION_HEADERS,
+        stream_proxy::rewrite_playlist(&playlist, &base, target),
+    )
+        .into_response()
+}
+
+fn passthrough(upstream: reqwest::Response) -> Response {
+    let mut response = Response::builder().status(upstream.status());
+    for name in PASSED_HEADERS {
+        if let Some(value) = upstream.headers().get(&name) {
+            response = response.header(name, value.clone());
+        }
+    }
+    let content_type = upstream
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .filter(|x| x.to_str().is_ok_and(is_media_type))
+        .cloned()
+        .unwrap_or_else(|| HeaderValue::from_static("application/octet-stream"));
+    response = response.header(header::CONTENT_TYPE, content_type);
+    for (name, value) in ISOLATION_HEADERS {
+        response = response.header(name, value);
+    }
+
+    let chunks = futures::stream::unfold(Some(upstream), |upstream| async move {
+        let mut upstream = upstream?;
+
+        match upstream.chunk().await {
+            Ok(Some(chunk)) => Some((Ok(chunk), Some(upstream))),
+            Ok(None) => None,
+            Err(e) => Some((Err(e), None)),
+        }
+    });
+
+    response
+        .body(body::boxed(StreamBody::new(chunks)))
+        .unwrap_or_else(|e| {
+            V1Response::<()>::Error(
+                StatusCode::BAD_GATEWAY,
+                anyhow::anyhow!("Failed to pass on stream: {}", e).into(),
+            )
+            .into_response()
+        })
+}
+
+/// Fetch subtitles the site lists next to a stream, converted to VTT
+///
+/// Links to this are handed out as the `vttUrl` of subtitles by `/v1/anime/info/for-episode/streams`.
+/// SRT and ASS files are converted, styling and positioning of ASS are dropped.
+#[utoipa::path(
+    get,
+    path = "/v1/stream/subtitles",
+    tag = "stream",
+    security(()),
+    params(ProxyQuery),
+    responses(
+        (status = 200, description = "The subtitles as WebVTT", content_type = "text/vtt"),
+        (status = 403, description = "The link wasn't handed out by this server"),
+        (status = 410, description = "The link expired, the streams have to be resolved again"),
+        (status = 502, description = "The site didn't serve the subtitles, or isn't on a public address"),
+    )
+)]
+#[debug_handler]
+pub async fn subtitles(
+    WithRejection(Query(query), _): WithRejection<Query<ProxyQuery>, V1Response>,
+) -> Response {
+    let target = match verify(&query) {
+        Ok(target) => target,
+        Err(response) => return response,
+    };
+
+    trace!("Fetching subtitles {:?}", target.url);
+    let text = match fetch_subtitles(&target).await {
+        Ok(text) => text,
+        Err(e) => {
+            debug!("Failed to fetch subtitles {:?}: {:?}", target.url, e);
+
+            return V1Response::<()>::Error(
+                StatusCode::BAD_GATEWAY,
+                anyhow::anyhow!("Failed to fetch subtitles: {}", e).into(),
+            )
+            .into_response();
+        }
+    };
+
+    (
+        [(header::CONTENT_TYPE, "text/vtt; charset=utf-8")],
+        ISOLATION_HEADERS,
+        subtitles::to_webvtt(&text, SubtitleFormat::of_url(&target.url)),
+    )
+        .into_response()
+}
+
+async fn fetch_subtitles(target: &ProxyTarget) -> anyhow::Result<String> {
+    let response = fetch(target, &HeaderMap::new()).await?.error_for_status()?;
+
+    read_text(response, MAX_SUBTITLES_SIZE).await
+}
+
+/// Read a whole response as text, giving up as soon as it's bigger than `max_size` bytes
+async fn read_text(mut response: reqwest::Response, max_size: u64) -> anyhow::Result<String> {
+    if response.content_length().is_some_and(|x| x > max_size) {
+        anyhow::bail!("Response is too big");
+    }
+
+    let mut bytes = vec![];
+    while let Some(chunk) = response.chunk().await? {
+        if (bytes.len() + chunk.len()) as u64 > max_size {
+            anyhow::bail!("Response is too big");
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
@@ -1,56 +1,162 @@
-use axum::{routing::get, Router};
+use axum::{
+    extract::DefaultBodyLimit,
+    middleware,
+    routing::{delete, get, post, put},
+    Router,
+};
 use reqwest::StatusCode;
 use tower_http::validate_request::ValidateRequestHeaderLayer;
 
-use self::response::V1Response;
+use self::{
+    auth::{AdminUser, AuthUser},
+    response::V1Response,
+};
 
+pub mod auth;
 pub mod handlers;
+pub mod openapi;
+pub mod pagination;
 mod response;
 
+/// Largest accepted upload for imports and restores. Big libraries get well past axum's default 2MB.
+const MAX_IMPORT_SIZE: usize = 32 * 1024 * 1024;
+
 pub fn create_router() -> Router {
     Router::new()
+        .merge(protected_routes())
         .route("/", get(handlers::index::index))
         .nest(
-            "/anime",
+            "/auth",
             Router::new()
-                .route("/", get(handlers::anime::list).put(handlers::anime::add))
-                .nest(
-                    "/:series_id",
-                    Router::new()
-                        .route(
-                            "/",
-                            get(handlers::anime::info)
-                                .patch(handlers::anime::update)
-                                .delete(handlers::anime::remove),
-                        )
-                        .route("/mal-info", get(handlers::anime::mal_info))
-                        .route("/details", get(handlers::anime::info_extended))
-                        .route(
-                            "/sources",
-                            get(handlers::anime::sources::list_for_series)
-                                .put(handlers::anime::sources::add),
-                        ),
-                )
-                .nest(
-                    "/sources",
-                    Router::new()
-                        .route(
-                            "/",
-                            get(handlers::anime::sources::list).put(handlers::anime::sources::add),
-                        )
-                        .route(
-                            "/:source_id",
-                            get(handlers::anime::sources::info)
-                                .patch(handlers::anime::sources::update)
-                                .delete(handlers::anime::sources::remove),
-                        ),
-                )
-                .route("/info", get(handlers::anime::info::anime_info_floating))
-                .route(
-                    "/info/for-episode",
-                    get(handlers::anime::info::episode_info_floating),
-                ),
+                .route("/register", post(handlers::auth::register))
+                .route("/login", post(handlers::auth::login))
+                .route("/logout", post(handlers::auth::logout)),
         )
         .fallback(|| async { V1Response::<()>::ErrorEmpty(StatusCode::NOT_FOUND) })
         .layer(ValidateRequestHeaderLayer::accept("application/json"))
+        // Players don't ask for JSON, and the signature in the links is the only auth needed
+        .route("/stream/proxy", get(handlers::stream::proxy))
+        .route("/stream/subtitles", get(handlers::stream::subtitles))
+        .route("/openapi.json", get(openapi::spec))
+        .route("/docs", get(openapi::docs))
+}
+
+/// Routes that need a logged in user
+fn protected_routes() -> Router {
+    Router::new()
+        .nest(
+            "/me",
+            Router::new()
+                .route("/", get(handlers::me::me))
+                .route(
+                    "/settings",
+                    get(handlers::me::settings).put(handlers::me::update_settings),
+                )
+                .route(
+                    "/tokens",
+                    get(handlers::me::tokens).post(handlers::me::create_token),
+                )
+                .route("/tokens/:token_id", delete(handlers::me::delete_token)),
+        )
+        .nest("/anime", anime_routes())
+        .nest(
+            "/admin",
+            Router::new()
+                .route("/backup", get(handlers::admin::backup))
+                .route("/consistency", get(handlers::admin::consistency))
+                .route(
+                    "/restore",
+                    post(handlers::admin::restore).layer(DefaultBodyLimit::max(MAX_IMPORT_SIZE)),
+                )
+                .route(
+                    "/snapshots",
+                    get(handlers::admin::snapshots::list).post(handlers::admin::snapshots::create),
+                )
+                .route(
+                    "/users",
+                    get(handlers::admin::users::list).post(handlers::admin::users::create),
+                )
+                .route_layer(middleware::from_extractor::<AdminUser>()),
+        )
+        .route("/export", get(handlers::library::export))
+        .nest(
+            "/mal",
+            Router::new()
+                .route("/", get(handlers::mal::status))
+                .route(
+                    "/auth",
+                    get(handlers::mal::authorize).delete(handlers::mal::unlink),
+                )
+                .route("/auth/callback", get(handlers::mal::callback))
+                .route("/sync", post(handlers::mal::sync)),
+        )
+        .nest(
+            "/import",
+            Router::new().route(
+                "/mal",
+                post(handlers::library::import_mal).layer(DefaultBodyLimit::max(MAX_IMPORT_SIZE)),
+            ),
+        )
+        .route_layer(middleware::from_extractor::<AuthUser>())
+}
+
+/// Routes under `/anime`, which still need a logged in user through [`protected_routes`]
+fn anime_routes() -> Router {
+    Router::new()
+        .route("/", get(handlers::anime::list).put(handlers::anime::add))
+        .route("/search", get(handlers::anime::search))
+        .route("/url", put(handlers::anime::url::add))
+        .route("/url/watched", post(handlers::anime::url::watched))
+        .nest(
+            "/:series_id",
+            Router::new()
+                .route(
+                    "/",
+                    get(handlers::anime::info)
+                        .patch(handlers::anime::update)
+                        .delete(handlers::anime::remove),
+                )
+                .route("/mal-info", get(handlers::anime::mal_info))
+                .route("/list", put(handlers::anime::update_list))
+                .route("/details", get(handlers::anime::info_extended))
+                .route("/enrich", post(handlers::anime::enrich))
+                .route("/episodes", get(handlers::anime::episodes))
+                .route(
+                    "/skip-markers",
+                    get(handlers::anime::skip_markers::list)
+                        .put(handlers::anime::skip_markers::add),
+                )
+                .route(
+                    "/skip-markers/:marker_id",
+                    delete(handlers::anime::skip_markers::remove),
+                )
+                .route(
+                    "/sources",
+                    get(handlers::anime::sources::list_for_series)
+                        .put(handlers::anime::sources::add),
+                ),
+        )
+        .nest(
+            "/sources",
+            Router::new()
+                .route(
+                    "/",
+                    get(handlers::anime::sources::list).put(handlers::anime::sources::add),
+                )
+                .route(
+                    "/:source_id",
+                    get(handlers::anime::sources::info)
+                        .patch(handlers::anime::sources::update)
+                        .delete(handlers::anime::sources::remove),
+                ),
+        )
+        .route("/info", get(handlers::anime::info::anime_info_floating))
+        .route(
+            "/info/for-episode",
+            get(handlers::anime::info::episode_info_floating),
+        )
+        .route(
+            "/info/for-episode/streams",
+            get(handlers::anime::info::episode_streams_floating),
+        )
 }
@@ -0,0 +1,322 @@
+use axum::{
+    debug_handler,
+    response::{Html, IntoResponse, Response},
+    Json,
+};
+use lazy_static::lazy_static;
+use reqwest::StatusCode;
+use utoipa::{
+    openapi::{
+        schema::SchemaType,
+        security::{ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityScheme},
+        Content, ObjectBuilder, Ref, RefOr, Schema,
+    },
+    Modify, OpenApi,
+};
+
+use crate::{
+    config::CONFIG,
+    server::router::routes::v1::{auth::SESSION_COOKIE, handlers, response::V1Response},
+};
+
+#[cfg(test)]
+mod tests;
+
+lazy_static! {
+    static ref SPEC: utoipa::openapi::OpenApi = ApiDoc::openapi();
+}
+
+/// `OpenAPI` description of the v1 API.
+///
+/// Every handler documents its own path with `#[utoipa::path]`, so adding a route
+/// means annotating the handler and listing it here.
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "anime-watcher", description = "API of the anime-watcher backend"),
+    paths(
+        handlers::index::index,
+        handlers::auth::register,
+        handlers::auth::login,
+        handlers::auth::logout,
+        handlers::me::me,
+        handlers::me::settings,
+        handlers::me::update_settings,
+        handlers::me::tokens,
+        handlers::me::create_token,
+        handlers::me::delete_token,
+        handlers::anime::list,
+        handlers::anime::add,
+        handlers::anime::search,
+        handlers::anime::url::add,
+        handlers::anime::url::watched,
+        handlers::anime::info,
+        handlers::anime::update,
+        handlers::anime::remove,
+        handlers::anime::mal_info,
+        handlers::anime::update_list,
+        handlers::anime::info_extended,
+        handlers::anime::enrich,
+        handlers::anime::episodes,
+        handlers::anime::skip_markers::list,
+        handlers::anime::skip_markers::add,
+        handlers::anime::skip_markers::remove,
+        handlers::anime::sources::list,
+        handlers::anime::sources::list_for_series,
+        handlers::anime::sources::add,
+        handlers::anime::sources::info,
+        handlers::anime::sources::update,
+        handlers::anime::sources::remove,
+        handlers::anime::info::anime_info_floating,
+        handlers::anime::info::episode_info_floating,
+        handlers::anime::info::episode_streams_floating,
+        handlers::stream::proxy,
+        handlers::stream::subtitles,
+        handlers::admin::backup,
+        handlers::admin::restore,
+        handlers::admin::consistency,
+        handlers::admin::snapshots::list,
+        handlers::admin::snapshots::create,
+        handlers::admin::users::list,
+        handlers::admin::users::create,
+        handlers::library::export,
+        handlers::library::import_mal,
+        handlers::mal::status,
+        handlers::mal::authorize,
+        handlers::mal::callback,
+        handlers::mal::unlink,
+        handlers::mal::sync,
+    ),
+    components(schemas(
+        crate::server::router::response::ApiResponseMeta,
+        crate::server::router::response::PageMeta,
+        super::pagination::SortOrder,
+        entity::series::Model,
+        entity::series_sources::Model,
+        entity::skip_markers::Model,
+        entity::library_entries::Model,
+        entity::users::Model,
+        entity::user_settings::Model,
+        entity::api_tokens::Model,
+        crate::auth::TokenScope,
+        crate::metadata::AnimeSite,
+        crate::metadata::AnimeStatus,
+        crate::metadata::AltName,
+        crate::library::ListStatus,
+        crate::library::search::SearchHit,
+        crate::library::search::SearchHighlights,
+        crate::library::consistency::IssueKind,
+        crate::library::consistency::ConsistencyIssue,
+        crate::library::consistency::SourceCheck,
+        crate::library::consistency::ConsistencyReport,
+        crate::library::health::SourceErrorKind,
+        crate::library::merge::FieldOrigin,
+        crate::library::merge::MergedAnime,
+        crate::library::episodes::EpisodeRemap,
+        crate::library::episodes::EpisodeMapping,
+        crate::library::episodes::EpisodeLink,
+        crate::library::episodes::TranslationAvailability,
+        crate::library::episodes::TimelineEpisode,
+        crate::library::episodes::Progress,
+        crate::library::episodes::Timeline,
+        crate::metadata::SkipMarkerKind,
+        crate::metadata::SkipMarker,
+        crate::metadata::StreamKind,
+        crate::metadata::StreamSource,
+        crate::metadata::StreamVariant,
+        crate::metadata::RenditionKind,
+        crate::metadata::StreamRendition,
+        crate::metadata::SubtitleFormat,
+        crate::metadata::SubtitleTrack,
+        crate::metadata::SeriesTranslation,
+        crate::library::enrich::FieldProvenance,
+        crate::library::enrich::Provenance,
+        crate::library::enrich::EnrichedField,
+        crate::library::enrich::MalIdCandidate,
+        crate::library::enrich::FailedSource,
+        crate::library::enrich::EnrichReport,
+        crate::library::export::ExportFormat,
+        crate::library::export::ExportWarning,
+        crate::library::export::Export,
+        crate::library::import::ImportOutcome,
+        crate::library::import::ImportEntryReport,
+        crate::library::import::ImportSummary,
+        crate::library::import::ImportReport,
+        crate::library::mal_sync::MalSyncStatus,
+        crate::library::mal_sync::SyncAction,
+        crate::library::mal_sync::SyncSide,
+        crate::library::mal_sync::SyncEntryReport,
+        crate::library::mal_sync::SyncSummary,
+        crate::library::mal_sync::SyncReport,
+        crate::db::backup::Backup,
+        crate::db::backup::RestoreMode,
+        crate::db::backup::TableRestoreReport,
+        crate::db::backup::SkippedRow,
+        crate::db::backup::RestoreReport,
+        crate::db::snapshot::Snapshot,
+        handlers::auth::CredentialsPayload,
+        handlers::auth::LoginResponse,
+        handlers::me::UpdateSettingsPayload,
+        handlers::me::CreateTokenPayload,
+        handlers::me::CreateTokenResponse,
+        handlers::me::DeleteTokenResponse,
+        handlers::anime::AddPayloadSource,
+        handlers::anime::AddPayload,
+        handlers::anime::AddResponse,
+        handlers::anime::MalInfoResponse,
+        handlers::anime::UpdatePayload,
+        handlers::anime::UpdateResponse,
+        handlers::anime::UpdateListPayload,
+        handlers::anime::UpdateListResponse,
+        handlers::anime::RemoveResponse,
+        handlers::anime::ListResponseItem,
+        handlers::anime::ListSort,
+        handlers::anime::InfoResponse,
+        handlers::anime::SourceStatus,
+        handlers::anime::InfoExtendedSource,
+        handlers::anime::InfoExtendedResponse,
+        handlers::anime::EnrichResponse,
+        handlers::anime::EpisodesResponse,
+        handlers::anime::sources::ListResponse,
+        handlers::anime::sources::ListSort,
+        handlers::anime::sources::ListForSeriesResponse,
+        handlers::anime::sources::AddPayload,
+        handlers::anime::sources::AddResponse,
+        handlers::anime::sources::InfoResponse,
+        handlers::anime::sources::UpdatePayload,
+        handlers::anime::sources::UpdateResponse,
+        handlers::anime::sources::RemoveResponse,
+        handlers::anime::skip_markers::ListResponse,
+        handlers::anime::skip_markers::AddPayload,
+        handlers::anime::skip_markers::AddResponse,
+        handlers::anime::skip_markers::RemoveResponse,
+        handlers::anime::url::AddPayload,
+        handlers::anime::url::AddResponse,
+        handlers::anime::url::WatchedPayload,
+        handlers::anime::url::WatchedResponse,
+        handlers::anime::info::AnimeInfoFloatingResponse,
+        handlers::anime::info::EpisodeInfoFloatingResponse,
+        handlers::anime::info::EpisodeStreamsFloatingResponse,
+        handlers::admin::snapshots::SnapshotsResponse,
+        handlers::admin::users::CreatePayload,
+        handlers::mal::AuthorizeResponse,
+    )),
+    modifiers(&SecuritySchemes, &ResponseEnvelope),
+    security(("bearer" = []), ("session" = [])),
+    tags(
+        (name = "meta", description = "Information about the API itself"),
+        (name = "auth", description = "Logging in and out"),
+        (name = "me", description = "The logged in user, their settings and API tokens"),
+        (name = "anime", description = "Tracked series and their list entries"),
+        (name = "sources", description = "Sites a series can be watched on"),
+        (name = "stream", description = "Playing streams through the server"),
+        (name = "library", description = "Importing and exporting the library"),
+        (name = "mal", description = "MyAnimeList account link and list sync"),
+        (name = "admin", description = "Backups, snapshots and user management"),
+    )
+)]
+pub struct ApiDoc;
+
+struct SecuritySchemes;
+
+impl Modify for SecuritySchemes {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+
+        components.add_security_scheme(
+            "bearer",
+            SecurityScheme::Http(
+                HttpBuilder::new()
+                    .scheme(HttpAuthScheme::Bearer)
+                    .description(Some("A session token from logging in, or an API token"))
+                    .build(),
+            ),
+        );
+        components.add_security_scheme(
+            "session",
+            SecurityScheme::ApiKey(ApiKey::Cookie(ApiKeyValue::new(SESSION_COOKIE))),
+        );
+    }
+}
+
+/// Wraps every documented response body in the `{ meta, body }` envelope
+/// that `V1Response` actually sends.
+struct ResponseEnvelope;
+
+impl Modify for ResponseEnvelope {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let responses = openapi
+            .paths
+            .paths
+            .values_mut()
+            .flat_map(|item| item.operations.values_mut())
+            .flat_map(|operation| operation.responses.responses.iter_mut());
+
+        for (status, response) in responses {
+            let RefOr::T(response) = response else {
+                continue;
+            };
+
+            let is_success = status.starts_with('2');
+            let data = response
+                .content
+                .shift_remove("application/json")
+                .map(|content| content.schema);
+
+            let envelope = match (is_success, data) {
+                (true, Some(data)) => envelope(&["success"], data),
+                (true, None) => envelope(
+                    &["success", "empty"],
+                    ObjectBuilder::new().nullable(true).into(),
+                ),
+                (false, _) => envelope(
+                    &["error", "empty"],
+                    ObjectBuilder::new()
+                        .schema_type(SchemaType::String)
+                        .description(Some("What went wrong"))
+                        .nullable(true)
+                        .into(),
+                ),
+            };
+
+            response
+                .content
+                .insert("application/json".to_string(), Content::new(envelope));
+        }
+    }
+}
+
+fn envelope(body_types: &[&str], data: RefOr<Schema>) -> Schema {
+    let body = ObjectBuilder::new()
+        .property(
+            "type",
+            ObjectBuilder::new()
+                .schema_type(SchemaType::String)
+                .enum_values(Some(body_types.iter().copied())),
+        )
+        .required("type")
+        .property("data", data)
+        .required("data");
+
+    ObjectBuilder::new()
+        .property("meta", Ref::from_schema_name("ApiResponseMeta"))
+        .required("meta")
+        .property("body", body)
+        .required("body")
+        .into()
+}
+
+/// The `OpenAPI` spec of the v1 API
+#[debug_handler]
+pub async fn spec() -> Json<&'static utoipa::openapi::OpenApi> {
+    Json(&SPEC)
+}
+
+/// Interactive documentation for the spec, if enabled
+#[debug_handler]
+pub async fn docs() -> Response {
+    if !CONFIG.server.api_docs {
+        return V1Response::<()>::ErrorEmpty(StatusCode::NOT_FOUND).into_response();
+    }
+
+    Html(include_str!("docs.html")).into_response()
+}
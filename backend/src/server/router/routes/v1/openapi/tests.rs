@@ -0,0 +1,183 @@
+//! Keeps the generated spec in step with the router and the committed `openapi.json`
+
+use std::{collections::HashSet, fs, path::PathBuf};
+
+use axum::{
+    body::Body,
+    http::{Method, Request, StatusCode},
+};
+use tower::ServiceExt;
+use utoipa::{openapi::PathItemType, OpenApi};
+
+use super::ApiDoc;
+use crate::server::router::routes::v1::create_router;
+
+fn snapshot_path() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("openapi.json")
+}
+
+fn method(item_type: &PathItemType) -> Method {
+    match item_type {
+        PathItemType::Get => Method::GET,
+        PathItemType::Post => Method::POST,
+        PathItemType::Put => Method::PUT,
+        PathItemType::Delete => Method::DELETE,
+        PathItemType::Options => Method::OPTIONS,
+        PathItemType::Head => Method::HEAD,
+        PathItemType::Patch => Method::PATCH,
+        PathItemType::Trace => Method::TRACE,
+        PathItemType::Connect => Method::CONNECT,
+    }
+}
+
+/// Turns `/v1/anime/{series_id}` into `/anime/1`, the path as seen by the v1 router
+fn request_path(path: &str) -> String {
+    let path = path
+        .strip_prefix("/v1")
+        .expect("path should start with /v1");
+    let path = path
+        .split('/')
+        .map(|segment| {
+            if segment.starts_with('{') && segment.ends_with('}') {
+                "1"
+            } else {
+                segment
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("/");
+
+    if path.is_empty() {
+        "/".to_string()
+    } else {
+        path
+    }
+}
+
+/// Run with `UPDATE_OPENAPI=1` to regenerate `openapi.json` after changing the API
+#[test]
+fn spec_matches_committed_snapshot() {
+    let spec = ApiDoc::openapi()
+        .to_pretty_json()
+        .expect("spec should serialize")
+        + "\n";
+
+    if std::env::var_os("UPDATE_OPENAPI").is_some() {
+        fs::write(snapshot_path(), &spec).expect("snapshot should be writable");
+        return;
+    }
+
+    let committed = fs::read_to_string(snapshot_path()).unwrap_or_default();
+    assert!(
+        committed == spec,
+        "openapi.json is out of date, rerun the tests with UPDATE_OPENAPI=1 and commit the result"
+    );
+}
+
+#[tokio::test]
+async fn documented_routes_exist() {
+    let spec = ApiDoc::openapi();
+
+    for (path, item) in &spec.paths.paths {
+        for item_type in item.operations.keys() {
+            let method = method(item_type);
+            let uri = request_path(path);
+            let request = Request::builder()
+                .method(method.clone())
+                .uri(&uri)
+                .header("accept", "application/json")
+                .body(Body::empty())
+                .unwrap();
+
+            let response = create_router().oneshot(request).await.unwrap();
+
+            assert!(
+                !matches!(
+                    response.status(),
+                    StatusCode::NOT_FOUND | StatusCode::METHOD_NOT_ALLOWED
+                ),
+                "{method} {path} is documented but not routed (got {})",
+                response.status(),
+            );
+        }
+    }
+}
+
+/// Handlers that are routed but serve the documentation rather than the API
+const UNDOCUMENTED: &[&str] = &["openapi::spec", "openapi::docs"];
+
+/// Handlers passed to `get(..)`, `.post(..)` and the like in `source`
+fn routed_handlers(source: &str) -> Vec<&str> {
+    let mut handlers = vec![];
+
+    for (start, _) in source.match_indices('(') {
+        let before = source[..start].trim_end_matches(|c: char| c.is_ascii_lowercase());
+        let method = &source[before.len()..start];
+        if !matches!(method, "get" | "post" | "put" | "patch" | "delete") {
+            continue;
+        }
+
+        let rest = source[start + 1..].trim_start();
+        let end = rest
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == ':'))
+            .unwrap_or(rest.len());
+        if rest[end..].trim_start().starts_with(')') {
+            handlers.push(&rest[..end]);
+        }
+    }
+
+    handlers
+}
+
+/// Handlers listed in the `paths(..)` of [`ApiDoc`]
+fn documented_handlers(source: &str) -> HashSet<&str> {
+    let start = source
+        .find("    paths(\n")
+        .expect("ApiDoc should list its paths");
+    let end = start
+        + source[start..]
+            .find("\n    ),")
+            .expect("paths should be closed");
+
+    source[start..end]
+        .lines()
+        .skip(1)
+        .map(|x| x.trim().trim_end_matches(','))
+        .collect()
+}
+
+/// The router can't list its routes, so this goes by the source of both
+#[test]
+fn routed_handlers_are_documented() {
+    let routed = routed_handlers(include_str!("../mod.rs"));
+    let documented = documented_handlers(include_str!("mod.rs"));
+
+    assert!(routed.contains(&"handlers::anime::list"), "{routed:?}");
+    for handler in routed {
+        if UNDOCUMENTED.contains(&handler) {
+            continue;
+        }
+
+        assert!(
+            documented.contains(handler),
+            "{handler} is routed but not listed in ApiDoc"
+        );
+    }
+}
+
+#[test]
+fn operation_ids_are_unique() {
+    let spec = ApiDoc::openapi();
+    let mut seen = HashSet::new();
+
+    for operation in spec
+        .paths
+        .paths
+        .values()
+        .flat_map(|item| item.operations.values())
+    {
+        let id = operation.operation_id.clone().unwrap_or_default();
+
+        assert!(seen.insert(id.clone()), "operation id {id:?} is used twice");
+    }
+}
@@ -0,0 +1,59 @@
+//! Offset pagination and sort order shared by the list endpoints
+
+use sea_orm::{Order, QuerySelect};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::server::router::response::PageMeta;
+
+/// Size of a page when the client doesn't ask for one
+pub const DEFAULT_PAGE_SIZE: u64 = 100;
+
+/// Largest page a client can ask for
+pub const MAX_PAGE_SIZE: u64 = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum SortOrder {
+    Asc,
+    Desc,
+}
+
+impl From<SortOrder> for Order {
+    fn from(order: SortOrder) -> Self {
+        match order {
+            SortOrder::Asc => Self::Asc,
+            SortOrder::Desc => Self::Desc,
+        }
+    }
+}
+
+/// Which slice of a list to return
+#[derive(Debug, Clone, Copy)]
+pub struct Page {
+    pub offset: u64,
+    /// Always between 1 and [`MAX_PAGE_SIZE`]
+    pub limit: u64,
+}
+
+impl Page {
+    /// [`DEFAULT_PAGE_SIZE`] items when no limit is given
+    pub fn new(offset: Option<u64>, limit: Option<u64>) -> Self {
+        Self {
+            offset: offset.unwrap_or(0),
+            limit: limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE),
+        }
+    }
+
+    pub fn apply<S: QuerySelect>(self, select: S) -> S {
+        select.offset(self.offset).limit(self.limit)
+    }
+
+    pub fn meta(self, total: u64) -> PageMeta {
+        PageMeta {
+            total,
+            offset: self.offset,
+            limit: self.limit,
+        }
+    }
+}
@@ -6,13 +6,15 @@ use axum::{
 use reqwest::StatusCode;
 use serde::Serialize;
 
-use crate::server::router::response::{error::ApiError, ApiResponse};
+use crate::server::router::response::{error::ApiError, ApiResponse, PageMeta};
 
 pub const API_VERSION: &str = "v1";
 
 #[derive(Debug)]
 pub enum V1Response<TData: Serialize + Send = ()> {
     Success(TData),
+    /// One page of a longer list
+    Page(TData, PageMeta),
     Error(StatusCode, ApiError),
     ErrorEmpty(StatusCode),
 }
@@ -25,6 +27,7 @@ impl<TData: Serialize + Send> From<V1Response<TData>> for ApiResponse<TData> {
 
         match r {
             V1Response::Success(data) => resp.with_success_body(data),
+            V1Response::Page(data, page) => resp.with_page(page).with_success_body(data),
             V1Response::Error(status, error) => {
                 resp.with_status_code(status).with_error_body(error)
             }
@@ -0,0 +1,215 @@
+//! Signed links for fetching streams through the server.
+//!
+//! Sites often only serve their streams with the right `Referer`, or without CORS headers,
+//! so players can't fetch them on their own. A proxy link carries the upstream URL,
+//! the headers to fetch it with and when the link stops working. Links are signed,
+//! so the server only fetches what it handed out itself instead of being an open relay.
+
+use std::{collections::BTreeMap, fmt};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use url::Url;
+
+use crate::config::CONFIG;
+
+#[cfg(test)]
+mod tests;
+
+/// Where the proxy is served, from the server root
+pub const PROXY_PATH: &str = "/v1/stream/proxy";
+/// Where subtitles are served converted to VTT, from the server root
+pub const SUBTITLES_PATH: &str = "/v1/stream/subtitles";
+
+lazy_static! {
+    static ref SECRET: Vec<u8> = CONFIG.stream.proxy_secret.as_ref().map_or_else(
+        || rand::random::<[u8; 32]>().to_vec(),
+        |x| x.as_bytes().to_vec()
+    );
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VerifyError {
+    /// Not signed by this server, or tampered with
+    Invalid,
+    Expired,
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Invalid => write!(f, "Invalid proxy link"),
+            Self::Expired => write!(f, "Proxy link expired"),
+        }
+    }
+}
+
+/// What a proxy link fetches
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProxyTarget {
+    #[serde(rename = "u")]
+    pub url: String,
+    /// Headers the upstream request is made with
+    #[serde(rename = "h", default)]
+    pub headers: BTreeMap<String, String>,
+    /// Unix timestamp the link stops working at
+    #[serde(rename = "e")]
+    pub expires_at: i64,
+}
+
+impl ProxyTarget {
+    /// Proxy a stream for the configured time, or until the stream itself expires
+    pub fn new(
+        url: &str,
+        headers: &BTreeMap<String, String>,
+        stream_expires_at: Option<DateTime<Utc>>,
+    ) -> Self {
+        let ttl = chrono::Duration::from_std(CONFIG.stream.proxy_ttl)
+            .unwrap_or_else(|_| chrono::Duration::hours(6));
+        let mut expires_at = Utc::now() + ttl;
+        if let Some(stream_expires_at) = stream_expires_at {
+            expires_at = expires_at.min(stream_expires_at);
+        }
+
+        Self {
+            url: url.to_string(),
+            headers: headers.clone(),
+            expires_at: expires_at.timestamp(),
+        }
+    }
+
+    /// Another URL with the same headers and expiry, eg. a segment of a playlist
+    pub fn with_url(&self, url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            ..self.clone()
+        }
+    }
+
+    /// Query of the link, as `token=...&signature=...`
+    fn query(&self, secret: &[u8]) -> String {
+        let token = URL_SAFE_NO_PAD.encode(serde_json::to_vec(self).unwrap_or_default());
+        let signature = URL_SAFE_NO_PAD.encode(
+            mac(secret)
+                .chain_update(token.as_bytes())
+                .finalize()
+                .into_bytes(),
+        );
+
+        format!("token={token}&signature={signature}")
+    }
+
+    /// Link to the target through the proxy, from the server root
+    pub fn link(&self) -> String {
+        self.link_to(PROXY_PATH)
+    }
+
+    /// Link to the target through another endpoint that takes signed links, like [`SUBTITLES_PATH`]
+    pub fn link_to(&self, path: &str) -> String {
+        format!("{path}?{}", self.query(&SECRET))
+    }
+
+    /// Get back the target of a link, if this server signed it and it didn't expire yet
+    pub fn verify(token: &str, signature: &str) -> Result<Self, VerifyError> {
+        Self::verify_with(&SECRET, token, signature, Utc::now())
+    }
+
+    fn verify_with(
+        secret: &[u8],
+        token: &str,
+        signature: &str,
+        now: DateTime<Utc>,
+    ) -> Result<Self, VerifyError> {
+        let signature = URL_SAFE_NO_PAD
+            .decode(signature)
+            .map_err(|_| VerifyError::Invalid)?;
+        mac(secret)
+            .chain_update(token.as_bytes())
+            .verify_slice(&signature)
+            .map_err(|_| VerifyError::Invalid)?;
+
+        let target = URL_SAFE_NO_PAD
+            .decode(token)
+            .ok()
+            .and_then(|x| serde_json::from_slice::<Self>(&x).ok())
+            .ok_or(VerifyError::Invalid)?;
+
+        if target.expires_at <= now.timestamp() {
+            return Err(VerifyError::Expired);
+        }
+
+        Ok(target)
+    }
+}
+
+fn mac(secret: &[u8]) -> Hmac<Sha256> {
+    <Hmac<Sha256> as Mac>::new_from_slice(secret).expect("HMAC takes keys of any length")
+}
+
+/// Rewrite the URIs of an HLS playlist fetched for `target` from `base` to go through the proxy too.
+///
+/// The links are relative to the proxy, so they work wherever the server is mounted.
+pub fn rewrite_playlist(playlist: &str, base: &Url, target: &ProxyTarget) -> String {
+    rewrite_playlist_with(&SECRET, playlist, base, target)
+}
+
+fn rewrite_playlist_with(
+    secret: &[u8],
+    playlist: &str,
+    base: &Url,
+    target: &ProxyTarget,
+) -> String {
+    let proxied = |uri: &str| -> Option<String> {
+        let url = base.join(uri.trim()).ok()?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return None;
+        }
+
+        Some(format!(
+            "proxy?{}",
+            target.with_url(url.as_str()).query(secret)
+        ))
+    };
+
+    let mut rewritten = playlist
+        .lines()
+        .map(|line| {
+            let trimmed = line.trim();
+
+            if trimmed.is_empty() {
+                line.to_string()
+            } else if trimmed.starts_with('#') {
+                rewrite_uri_attribute(line, proxied)
+            } else {
+                proxied(trimmed).unwrap_or_else(|| line.to_string())
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    if playlist.ends_with('\n') {
+        rewritten.push('\n');
+    }
+
+    rewritten
+}
+
+/// Tags like `#EXT-X-KEY` and `#EXT-X-MEDIA` reference their file in a `URI="..."` attribute
+fn rewrite_uri_attribute(line: &str, proxied: impl Fn(&str) -> Option<String>) -> String {
+    const ATTRIBUTE: &str = "URI=\"";
+
+    let Some(start) = line.find(ATTRIBUTE).map(|x| x + ATTRIBUTE.len()) else {
+        return line.to_string();
+    };
+    let Some(length) = line[start..].find('"') else {
+        return line.to_string();
+    };
+
+    match proxied(&line[start..start + length]) {
+        Some(uri) => format!("{}{}{}", &line[..start], uri, &line[start + length..]),
+        None => line.to_string(),
+    }
+}
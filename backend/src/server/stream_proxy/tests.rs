@@ -0,0 +1,137 @@
+//! Signing proxy links and rewriting playlists to use them
+
+use std::collections::BTreeMap;
+
+use chrono::{Duration, TimeZone, Utc};
+use url::Url;
+
+use super::{rewrite_playlist_with, ProxyTarget, VerifyError};
+
+const SECRET: &[u8] = b"stream-proxy-secret";
+
+fn target(url: &str) -> ProxyTarget {
+    ProxyTarget {
+        url: url.to_string(),
+        headers: BTreeMap::from([("referer".to_string(), "https://allanime.to".to_string())]),
+        expires_at: Utc
+            .with_ymd_and_hms(2023, 11, 1, 0, 0, 0)
+            .unwrap()
+            .timestamp(),
+    }
+}
+
+/// Split `token=...&signature=...` back up
+fn parts(query: &str) -> (String, String) {
+    let pairs = url::form_urlencoded::parse(query.as_bytes())
+        .into_owned()
+        .collect::<BTreeMap<_, _>>();
+
+    (pairs["token"].clone(), pairs["signature"].clone())
+}
+
+fn before_expiry() -> chrono::DateTime<Utc> {
+    Utc.with_ymd_and_hms(2023, 10, 31, 0, 0, 0).unwrap()
+}
+
+#[test]
+fn verifies_signed_links() {
+    let target = target("https://cdn.example/video.mp4");
+    let (token, signature) = parts(&target.query(SECRET));
+
+    assert_eq!(
+        ProxyTarget::verify_with(SECRET, &token, &signature, before_expiry()),
+        Ok(target)
+    );
+}
+
+#[test]
+fn rejects_tampered_links() {
+    let (token, signature) = parts(&target("https://cdn.example/video.mp4").query(SECRET));
+    let (other_token, _) = parts(&target("https://evil.example/").query(SECRET));
+
+    assert_eq!(
+        ProxyTarget::verify_with(SECRET, &other_token, &signature, before_expiry()),
+        Err(VerifyError::Invalid)
+    );
+    assert_eq!(
+        ProxyTarget::verify_with(b"other-secret", &token, &signature, before_expiry()),
+        Err(VerifyError::Invalid)
+    );
+    assert_eq!(
+        ProxyTarget::verify_with(SECRET, &token, "not base64!", before_expiry()),
+        Err(VerifyError::Invalid)
+    );
+}
+
+#[test]
+fn rejects_expired_links() {
+    let target = target("https://cdn.example/video.mp4");
+    let (token, signature) = parts(&target.query(SECRET));
+    let expiry = Utc.timestamp_opt(target.expires_at, 0).unwrap();
+
+    assert_eq!(
+        ProxyTarget::verify_with(SECRET, &token, &signature, expiry),
+        Err(VerifyError::Expired)
+    );
+    assert_eq!(
+        ProxyTarget::verify_with(SECRET, &token, &signature, expiry + Duration::days(1)),
+        Err(VerifyError::Expired)
+    );
+}
+
+#[test]
+fn rewrites_playlists() {
+    let playlist = "#EXTM3U\n\
+        #EXT-X-KEY:METHOD=AES-128,URI=\"key.bin\",IV=0x1\n\
+        #EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID=\"aud\",URI=\"/audio/en.m3u8\"\n\
+        #EXTINF:10.0,\n\
+        segment-0.ts\n\
+        \n\
+        #EXTINF:10.0,\n\
+        https://other.example/segment-1.ts\n\
+        #EXT-X-SESSION-KEY:METHOD=SAMPLE-AES,URI=\"skd://key\"\n\
+        #EXT-X-ENDLIST\n";
+    let base = Url::parse("https://cdn.example/videos/1/index.m3u8").unwrap();
+    let target = target(base.as_str());
+
+    let rewritten = rewrite_playlist_with(SECRET, playlist, &base, &target);
+    let lines = rewritten.lines().collect::<Vec<_>>();
+
+    let proxied_url = |line: &str| {
+        let query = line
+            .split("proxy?")
+            .nth(1)
+            .unwrap()
+            .split('"')
+            .next()
+            .unwrap();
+        let (token, signature) = parts(query);
+
+        ProxyTarget::verify_with(SECRET, &token, &signature, before_expiry())
+            .unwrap()
+            .url
+    };
+
+    assert_eq!(lines[0], "#EXTM3U");
+    assert!(lines[1].starts_with("#EXT-X-KEY:METHOD=AES-128,URI=\"proxy?"));
+    assert!(lines[1].ends_with("\",IV=0x1"));
+    assert_eq!(
+        proxied_url(lines[1]),
+        "https://cdn.example/videos/1/key.bin"
+    );
+    assert_eq!(proxied_url(lines[2]), "https://cdn.example/audio/en.m3u8");
+    assert_eq!(lines[3], "#EXTINF:10.0,");
+    assert_eq!(
+        proxied_url(lines[4]),
+        "https://cdn.example/videos/1/segment-0.ts"
+    );
+    assert_eq!(lines[5], "");
+    assert_eq!(proxied_url(lines[7]), "https://other.example/segment-1.ts");
+    // Only http(s) can be fetched through the proxy
+    assert_eq!(
+        lines[8],
+        "#EXT-X-SESSION-KEY:METHOD=SAMPLE-AES,URI=\"skd://key\""
+    );
+    assert_eq!(lines[9], "#EXT-X-ENDLIST");
+    assert!(rewritten.ends_with('\n'));
+}
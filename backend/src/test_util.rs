@@ -0,0 +1,55 @@
+//! Things tests of several modules need
+
+use migration::{Migrator, MigratorTrait};
+use sea_orm::{ConnectOptions, Database, DatabaseConnection};
+
+/// An in-memory database with every migration applied
+pub async fn database() -> DatabaseConnection {
+    let mut opt = ConnectOptions::new("sqlite::memory:".to_string());
+    // Every connection to an in-memory database gets its own database
+    opt.max_connections(1).sqlx_logging(false);
+
+    let db = Database::connect(opt).await.unwrap();
+    Migrator::up(&db, None).await.unwrap();
+
+    db
+}
+
+/// Series 1, for tests to override what they care about with `Model { mal_id, ..test_series() }`
+pub fn test_series() -> entity::series::Model {
+    entity::series::Model {
+        id: 1,
+        name: "Sousou no Frieren".to_string(),
+        description: None,
+        created_at: None,
+        updated_at: None,
+        mal_id: None,
+        alt_names: None,
+        genres: None,
+        provenance: None,
+    }
+}
+
+/// A source of series 1 on aniwatch, for tests to override what they care about with
+/// `Model { id: 2, ..test_source() }`
+pub fn test_source() -> entity::series_sources::Model {
+    entity::series_sources::Model {
+        id: 1,
+        for_series_id: 1,
+        series_site: "aniwatch".to_string(),
+        series_site_id: "frieren".to_string(),
+        created_at: None,
+        updated_at: None,
+        verified_name: None,
+        verified_mal_id: None,
+        verified_at: None,
+        last_success_at: None,
+        last_failure_at: None,
+        consecutive_failures: 0,
+        last_error_kind: None,
+        last_error: None,
+        broken: false,
+        episode_mapping: None,
+        next_release_at: None,
+    }
+}
@@ -14,6 +14,14 @@ export type ApiResponse<TVersion extends `v${number}`, TData> = {
      * Status code of the response.
      */
     status: number;
+    /**
+     * Pagination info, only present on paginated list responses.
+     */
+    page?: {
+      total: number;
+      offset: number;
+      limit: number;
+    };
   };
 
   body:
@@ -11,8 +11,61 @@ export type Anime = {
   createdAt: string;
   updatedAt: string;
   malId: number | null;
+  /** Alternative names reported by the sources, one per line */
+  altNames: string | null;
+  /** Genres reported by the sources, one per line */
+  genres: string | null;
+  /** Which source each field that was filled in from the sources came from */
+  provenance: AnimeProvenance | null;
 };
 
+export type FieldProvenance = {
+  sourceId: number;
+  site: string;
+  seriesSiteId: string;
+  filledAt: string;
+};
+
+export type AnimeProvenance = {
+  name?: FieldProvenance;
+  description?: FieldProvenance;
+  malId?: FieldProvenance;
+  altNames?: Record<string, FieldProvenance>;
+};
+
+export type LibraryEntry = {
+  id: number;
+  userId: number | null;
+  seriesId: number;
+  listStatus: ListStatus | null;
+  score: number | null;
+  watchedEpisodes: number | null;
+  startedWatchingAt: string | null;
+  finishedWatchingAt: string | null;
+  listUpdatedAt: string | null;
+  malSyncedAt: string | null;
+  malListUpdatedAt: string | null;
+  createdAt: string | null;
+  updatedAt: string | null;
+  /** Leave filler episodes out of what's up next and the unwatched count */
+  skipFillers: boolean;
+};
+
+export type User = {
+  id: number;
+  username: string;
+  isAdmin: boolean;
+  createdAt: string | null;
+  updatedAt: string | null;
+};
+
+export type ListStatus =
+  | "watching"
+  | "completed"
+  | "on-hold"
+  | "dropped"
+  | "plan-to-watch";
+
 export type AnimeSource = {
   id: number;
   forSeriesId: number;
@@ -20,8 +73,40 @@ export type AnimeSource = {
   seriesSiteId: string;
   createdAt: string;
   updatedAt: string;
+  /** Name the site reported when the source was verified */
+  verifiedName: string | null;
+  verifiedMalId: number | null;
+  verifiedAt: string | null;
+  lastSuccessAt: string | null;
+  lastFailureAt: string | null;
+  consecutiveFailures: number;
+  lastErrorKind: SourceErrorKind | null;
+  lastError: string | null;
+  /** Failed too many times in a row, so background jobs skip it */
+  broken: boolean;
+  episodeMapping: EpisodeMapping | null;
+  /** When the site expects the next episode, as of the last time it was fetched */
+  nextReleaseAt: string | null;
 };
 
+/** How the episode numbers of a source line up with the episodes of its series */
+export type EpisodeMapping = {
+  offset?: number;
+  min?: number;
+  max?: number;
+  remap?: {
+    from: number;
+    /** `null` leaves the episode out */
+    to: number | null;
+  }[];
+};
+
+export type SourceErrorKind =
+  | "upstream"
+  | "parse"
+  | "timeout"
+  | "unsupportedSite";
+
 export const animeSourceAddValidation = z.object({
   forSeriesId: z.number(),
   seriesSite: z.string(),
@@ -31,9 +116,11 @@ export const animeSourceAddValidation = z.object({
 export type RespAnimeList = {
   anime: Anime;
   sources: AnimeSource[];
+  entry: LibraryEntry | null;
 }[];
 
 export type RespAnimeItem = {
   anime: Anime;
   sources: AnimeSource[];
+  entry: LibraryEntry | null;
 };
@@ -11,7 +11,7 @@ export const list = () => {
       anime: Anime;
       sources: AnimeSource[];
     }[]
-  >("/anime", {
+  >("/anime?limit=500", {
     next: {
       tags: [cacheTagAnime("list"), "$anime/list"],
     },
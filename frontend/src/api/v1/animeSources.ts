@@ -1,14 +1,21 @@
 import { cacheTagV1, fetchV1 } from "./$fetch";
+import { type AnimeSource } from "./$models";
 
 export const cacheTagAnimeSources = (...parts: unknown[]) =>
   cacheTagV1("anime", parts);
 
-export const add = (data: {
-  seriesId: number;
-  seriesSite: string;
-  seriesSiteId: string;
-}) => {
-  return fetchV1("/anime/sources", {
+export const add = (
+  data: {
+    seriesId: number;
+    seriesSite: string;
+    seriesSiteId: string;
+  },
+  { verify = true }: { verify?: boolean } = {},
+) => {
+  return fetchV1<{
+    result: AnimeSource;
+    warnings: string[];
+  }>(`/anime/sources?verify=${verify}`, {
     method: "PUT",
     headers: {
       "Content-Type": "application/json",
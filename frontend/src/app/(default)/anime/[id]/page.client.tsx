@@ -138,6 +138,14 @@ export const AddNewSourceButton: FC<{ forAnimeId: number }> = (props) => {
                     closeModal();
                     router.refresh();
 
+                    for (const warning of body.data.warnings) {
+                      notifications.show({
+                        title: "Warning",
+                        message: warning,
+                        color: "yellow",
+                      });
+                    }
+
                     return notifications.show({
                       title: "Success",
                       message: "Source added",